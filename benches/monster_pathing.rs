@@ -0,0 +1,79 @@
+//! Compares every monster running its own A* search to the player with all of them following one
+//! shared Dijkstra map, the way the monster AI does it.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use hellorust::dijkstra_map_system::{dijkstra_map, downhill};
+use hellorust::{Map, TileType};
+use rltk::RandomNumberGenerator;
+
+/// As big as the largest levels, caverns
+const WIDTH: i32 = 120;
+const HEIGHT: i32 = 80;
+
+/// A grid of rooms with a doorway in every wall, `monsters` of them scattered around and the
+/// player in the middle
+fn level(monsters: usize) -> (Map, usize, Vec<usize>) {
+    let mut map = Map::new(1, WIDTH, HEIGHT);
+    for y in 1..HEIGHT - 1 {
+        for x in 1..WIDTH - 1 {
+            let wall = (x % 12 == 0 && !(7..9).contains(&(y % 10)))
+                || (y % 10 == 0 && !(5..7).contains(&(x % 12)));
+            if !wall {
+                let idx = map.xy_idx(x, y);
+                map.tiles[idx] = TileType::Floor;
+            }
+        }
+    }
+    map.populate_blocked();
+
+    let player = map.xy_idx(WIDTH / 2 + 3, HEIGHT / 2 + 3);
+    let mut rng = RandomNumberGenerator::seeded(1);
+    let mut positions = Vec::new();
+    while positions.len() < monsters {
+        let idx = map.xy_idx(rng.range(1, WIDTH - 1), rng.range(1, HEIGHT - 1));
+        if idx != player && !map.blocked[idx] {
+            map.blocked[idx] = true;
+            positions.push(idx);
+        }
+    }
+    (map, player, positions)
+}
+
+fn per_monster_a_star(map: &Map, player: usize, monsters: &[usize]) -> Vec<Option<usize>> {
+    monsters
+        .iter()
+        .map(|monster| {
+            let path = rltk::a_star_search(*monster as i32, player as i32, map);
+            path.steps.get(1).copied()
+        })
+        .collect()
+}
+
+fn shared_dijkstra_map(map: &Map, player: usize, monsters: &[usize]) -> Vec<Option<usize>> {
+    let approach = dijkstra_map(map, [(player, 0.0)]);
+    monsters
+        .iter()
+        .map(|monster| downhill(map, &approach, *monster))
+        .collect()
+}
+
+fn monster_pathing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("monster_pathing");
+    for monsters in [1, 10, 50, 200] {
+        let (map, player, positions) = level(monsters);
+        group.bench_with_input(
+            BenchmarkId::new("per_monster_a_star", monsters),
+            &positions,
+            |b, positions| b.iter(|| per_monster_a_star(black_box(&map), player, positions)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("shared_dijkstra_map", monsters),
+            &positions,
+            |b, positions| b.iter(|| shared_dijkstra_map(black_box(&map), player, positions)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, monster_pathing);
+criterion_main!(benches);
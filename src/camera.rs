@@ -0,0 +1,227 @@
+use rltk::{Point, Rltk, RGB};
+use specs::prelude::*;
+
+use super::{Hidden, Map, Position, Renderable, TileType};
+
+/// Part of the screen the map is drawn on, the rows below it belong to the UI
+pub const VIEW_WIDTH: i32 = 80;
+pub const VIEW_HEIGHT: i32 = 43;
+
+fn in_view(screen: Point) -> bool {
+    (0..VIEW_WIDTH).contains(&screen.x) && (0..VIEW_HEIGHT).contains(&screen.y)
+}
+
+fn in_map(map: &Map, tile: Point) -> bool {
+    (0..map.width).contains(&tile.x) && (0..map.height).contains(&tile.y)
+}
+
+/// Where the viewport starts along one axis. It follows `focus` without showing anything past the
+/// edges of the map, and maps smaller than the viewport are centered instead.
+fn axis_origin(focus: i32, view_size: i32, map_size: i32) -> i32 {
+    if map_size <= view_size {
+        (map_size - view_size) / 2
+    } else {
+        i32::clamp(focus - view_size / 2, 0, map_size - view_size)
+    }
+}
+
+/// Map coordinates of the top left corner of the viewport when it follows `focus`
+fn view_origin(map: &Map, focus: Point) -> Point {
+    Point::new(
+        axis_origin(focus.x, VIEW_WIDTH, map.width),
+        axis_origin(focus.y, VIEW_HEIGHT, map.height),
+    )
+}
+
+/// Map coordinates of the top left corner of the viewport, which follows the player
+fn screen_origin(ecs: &World) -> Point {
+    view_origin(&ecs.fetch::<Map>(), *ecs.fetch::<Point>())
+}
+
+/// The map tile under a point of the screen, `None` when it's off the map or under the UI
+pub fn screen_to_map(ecs: &World, screen: Point) -> Option<Point> {
+    let map = ecs.fetch::<Map>();
+    let origin = view_origin(&map, *ecs.fetch::<Point>());
+    let tile = Point::new(screen.x + origin.x, screen.y + origin.y);
+    (in_view(screen) && in_map(&map, tile)).then_some(tile)
+}
+
+/// Where a map tile is drawn on the screen, `None` when it's out of the viewport
+pub fn map_to_screen(ecs: &World, tile: Point) -> Option<Point> {
+    let origin = screen_origin(ecs);
+    let screen = Point::new(tile.x - origin.x, tile.y - origin.y);
+    in_view(screen).then_some(screen)
+}
+
+/// Draws the part of the current level around the player, and whatever visible entities are on it
+pub fn render_camera(ecs: &World, ctx: &mut Rltk) {
+    let map = ecs.fetch::<Map>();
+    let origin = view_origin(&map, *ecs.fetch::<Point>());
+    render_tiles(&map, origin, ctx);
+
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let hidden = ecs.read_storage::<Hidden>();
+
+    let mut data = (&positions, &renderables, !&hidden)
+        .join()
+        .collect::<Vec<_>>();
+    data.sort_by_key(|&(_pos, render, _hidden)| std::cmp::Reverse(render.render_order));
+
+    for (pos, render, _hidden) in data.iter() {
+        let screen = Point::new(pos.x - origin.x, pos.y - origin.y);
+        let idx = map.xy_idx(pos.x, pos.y);
+        if in_view(screen) && map.visible_tiles[idx] {
+            ctx.set(screen.x, screen.y, render.fg, render.bg, render.glyph);
+        }
+    }
+}
+
+/// Draws a map snapshot of the map generation visualizer, centered on the middle of the map
+pub fn render_debug_map(map: &Map, ctx: &mut Rltk) {
+    let origin = view_origin(map, Point::new(map.width / 2, map.height / 2));
+    render_tiles(map, origin, ctx);
+}
+
+fn render_tiles(map: &Map, origin: Point, ctx: &mut Rltk) {
+    for y in 0..VIEW_HEIGHT {
+        for x in 0..VIEW_WIDTH {
+            let tile = Point::new(x + origin.x, y + origin.y);
+            if !in_map(map, tile) {
+                continue;
+            }
+            let idx = map.xy_idx(tile.x, tile.y);
+            if map.revealed_tiles[idx] {
+                let (glyph, fg, bg) = tile_glyph(idx, map);
+                ctx.set(x, y, fg, bg, glyph);
+            }
+        }
+    }
+}
+
+/// How a revealed tile looks: tinted by the light on it, greyed out when it's out of sight
+fn tile_glyph(idx: usize, map: &Map) -> (rltk::FontCharType, RGB, RGB) {
+    let x = idx as i32 % map.width;
+    let y = idx as i32 / map.width;
+    let glyph;
+    let mut fg;
+    let mut bg = RGB::from_f32(0., 0., 0.);
+    match map.tiles[idx] {
+        TileType::Floor => {
+            glyph = rltk::to_cp437('.');
+            fg = RGB::from_f32(0.0, 0.5, 0.5);
+        }
+        TileType::VisitedFloor => {
+            glyph = rltk::to_cp437('.');
+            fg = RGB::from_f32(1.0, 0.0, 1.0);
+        }
+        TileType::Wall => {
+            fg = RGB::from_f32(0.0, 1.0, 0.0);
+            glyph = wall_glyph(map, x, y);
+        }
+        TileType::DownStairs => {
+            fg = RGB::from_f32(0., 1.0, 1.0);
+            glyph = rltk::to_cp437('>');
+        }
+        TileType::UpStairs => {
+            fg = RGB::from_f32(0., 1.0, 1.0);
+            glyph = rltk::to_cp437('<');
+        }
+        TileType::Stalactite => {
+            fg = RGB::from_f32(0.5, 0.5, 0.5);
+            glyph = rltk::to_cp437('╨');
+        }
+        TileType::Road => {
+            fg = RGB::from_f32(0.5, 0.5, 0.5);
+            glyph = rltk::to_cp437('≡');
+        }
+        TileType::Grass => {
+            fg = RGB::from_f32(0.0, 0.6, 0.0);
+            glyph = rltk::to_cp437('"');
+        }
+        TileType::Gravel => {
+            fg = RGB::from_f32(0.6, 0.6, 0.6);
+            glyph = rltk::to_cp437(';');
+        }
+        TileType::Tree => {
+            fg = RGB::from_f32(0.0, 0.8, 0.0);
+            glyph = rltk::to_cp437('♣');
+        }
+        TileType::ShallowWater => {
+            fg = RGB::from_f32(0.3, 0.6, 1.0);
+            glyph = rltk::to_cp437('~');
+        }
+        TileType::DeepWater => {
+            fg = RGB::from_f32(0.1, 0.2, 1.0);
+            glyph = rltk::to_cp437('≈');
+        }
+        TileType::Lava => {
+            fg = RGB::from_f32(1.0, 0.4, 0.0);
+            bg = RGB::from_f32(0.5, 0.1, 0.0);
+            glyph = rltk::to_cp437('≈');
+        }
+        TileType::Bridge => {
+            fg = RGB::from_f32(0.55, 0.35, 0.2);
+            glyph = rltk::to_cp437('=');
+        }
+        TileType::Debug(dbg_glyph) => {
+            fg = RGB::from_f32(1.0, 1.0, 1.0);
+            glyph = rltk::to_cp437(dbg_glyph);
+        }
+    }
+
+    if map.bloodstains.contains(&idx) {
+        bg = RGB::from_f32(0.75, 0., 0.);
+    }
+    if map.visible_tiles[idx] {
+        fg = fg * map.light[idx];
+    } else {
+        fg = fg.to_greyscale()
+    }
+    (glyph, fg, bg)
+}
+
+fn wall_glyph(map: &Map, x: i32, y: i32) -> rltk::FontCharType {
+    if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
+        return 35;
+    }
+    let mut mask: u8 = 0;
+
+    if is_revealed_and_wall(map, x, y - 1) {
+        mask += 1;
+    }
+    if is_revealed_and_wall(map, x, y + 1) {
+        mask += 2;
+    }
+    if is_revealed_and_wall(map, x - 1, y) {
+        mask += 4;
+    }
+    if is_revealed_and_wall(map, x + 1, y) {
+        mask += 8;
+    }
+
+    match mask {
+        0 => 9,    // Pillar because we can't see neighbors
+        1 => 186,  // Wall only to the north
+        2 => 186,  // Wall only to the south
+        3 => 186,  // Wall to the north and south
+        4 => 205,  // Wall only to the west
+        5 => 188,  // Wall to the north and west
+        6 => 187,  // Wall to the south and west
+        7 => 185,  // Wall to the north, south and west
+        8 => 205,  // Wall only to the east
+        9 => 200,  // Wall to the north and east
+        10 => 201, // Wall to the south and east
+        11 => 204, // Wall to the north, south and east
+        12 => 205, // Wall to the east and west
+        13 => 202, // Wall to the east, west, and south
+        14 => 203, // Wall to the east, west, and north
+        15 => 206, // ╬ Wall on all sides
+        _ => 35,   // We missed one?
+    }
+}
+
+fn is_revealed_and_wall(map: &Map, x: i32, y: i32) -> bool {
+    let idx = map.xy_idx(x, y);
+    map.tiles[idx] == TileType::Wall && map.revealed_tiles[idx]
+}
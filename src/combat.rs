@@ -0,0 +1,70 @@
+use rltk::RandomNumberGenerator;
+
+use super::{Attributes, MeleeWeapon, Skills, WeaponAttribute};
+
+/// Armor class of someone with no defense at all
+const BASE_ARMOR_CLASS: i32 = 10;
+
+/// What fighting with bare hands is like
+pub const UNARMED: MeleeWeapon = MeleeWeapon {
+    attribute: WeaponAttribute::Might,
+    damage_n_dice: 1,
+    damage_die_type: 4,
+    damage_bonus: 0,
+    hit_bonus: 0,
+};
+
+/// Bonus (or malus) an attribute value adds to the rolls it's used for
+pub fn attribute_bonus(value: i32) -> i32 {
+    (value - 10).div_euclid(2)
+}
+
+/// Bonus `attributes` give to attacks with `attribute`, nothing for those without attributes
+pub fn weapon_attribute_bonus(attributes: Option<&Attributes>, attribute: WeaponAttribute) -> i32 {
+    attributes.map_or(0, |attributes| match attribute {
+        WeaponAttribute::Might => attribute_bonus(attributes.might),
+        WeaponAttribute::Quickness => attribute_bonus(attributes.quickness),
+    })
+}
+
+/// How hard someone is to hit, out of their defense (stats and armor), quickness and skill
+pub fn armor_class(defense: i32, attributes: Option<&Attributes>, skills: Option<&Skills>) -> i32 {
+    BASE_ARMOR_CLASS
+        + defense
+        + weapon_attribute_bonus(attributes, WeaponAttribute::Quickness)
+        + skills.map_or(0, |skills| skills.defense)
+}
+
+/// How an attack roll turned out
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum AttackRoll {
+    /// A natural 1, which always misses
+    Fumble,
+    Miss,
+    Hit,
+    /// A natural 20, which always hits and rolls damage twice
+    Critical,
+}
+
+/// Rolls a d20 plus `modifier` against `armor_class`
+pub fn roll_to_hit(rng: &mut RandomNumberGenerator, modifier: i32, armor_class: i32) -> AttackRoll {
+    match rng.roll_dice(1, 20) {
+        1 => AttackRoll::Fumble,
+        20 => AttackRoll::Critical,
+        natural if natural + modifier >= armor_class => AttackRoll::Hit,
+        _ => AttackRoll::Miss,
+    }
+}
+
+/// Rolls the damage dice of `weapon`, twice over on a critical hit
+pub fn roll_weapon_damage(
+    rng: &mut RandomNumberGenerator,
+    weapon: &MeleeWeapon,
+    roll: AttackRoll,
+) -> i32 {
+    let times = if roll == AttackRoll::Critical { 2 } else { 1 };
+    (0..times)
+        .map(|_| rng.roll_dice(weapon.damage_n_dice, weapon.damage_die_type))
+        .sum::<i32>()
+        + weapon.damage_bonus
+}
@@ -11,6 +11,14 @@ pub struct Position {
     pub y: i32,
 }
 
+/// Replaces [Position] for entities left behind on a level the player isn't on
+#[derive(Component, ConvertSaveload, Clone, Debug, Copy, PartialEq)]
+pub struct OtherLevelPosition {
+    pub x: i32,
+    pub y: i32,
+    pub depth: i32,
+}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Renderable {
     pub glyph: rltk::FontCharType,
@@ -22,6 +30,13 @@ pub struct Renderable {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Player {}
 
+/// Lights up the tiles it can see within `range`, fading with the distance
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct LightSource {
+    pub color: RGB,
+    pub range: i32,
+}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Viewshed {
     pub visible_tiles: Vec<rltk::Point>,
@@ -32,6 +47,33 @@ pub struct Viewshed {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
+/// What a monster is up to, decided every monster turn by the AI
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum MonsterBehaviour {
+    /// Stands still until it sees the player or gets bored
+    Idle,
+    /// Roams around at random
+    Wander,
+    /// Goes for the closest enemy in sight
+    Chase,
+    /// Runs away from whatever it fears (or from its enemies when badly hurt)
+    Flee,
+    /// Walks to where its target was last seen
+    Search,
+}
+
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MonsterAIState {
+    pub behaviour: MonsterBehaviour,
+    pub last_seen_target: Option<rltk::Point>,
+}
+
+/// Group an entity belongs to, the raws say how each faction reacts to the others
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Faction {
+    pub name: String,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct Name {
     pub name: String,
@@ -40,6 +82,16 @@ pub struct Name {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
 
+/// Keeps anyone from seeing through the tile the entity stands on
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct BlocksVisibility {}
+
+/// A door that can be opened and closed, closed ones block their tile and sight
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Door {
+    pub open: bool,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct CombatStats {
     pub max_hp: i32,
@@ -48,6 +100,34 @@ pub struct CombatStats {
     pub power: i32,
 }
 
+/// Might (hitting hard), fitness (health), quickness (dodging, aiming) and intelligence (magic).
+/// 10 is average, see [crate::combat::attribute_bonus] for what they're worth.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Attributes {
+    pub might: i32,
+    pub fitness: i32,
+    pub quickness: i32,
+    pub intelligence: i32,
+}
+
+/// Training added to attack rolls (melee, ranged) and to armor class (defense)
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Skills {
+    pub melee: i32,
+    pub ranged: i32,
+    pub defense: i32,
+}
+
+/// Level and experience towards the next one. Monsters only use the level, it sets what they're
+/// worth when killed.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Experience {
+    pub level: i32,
+    pub xp: i32,
+    /// Level ups the player hasn't picked an improvement for yet
+    pub improvements: i32,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct MagicStats {
     pub max_mana: i32,
@@ -60,27 +140,48 @@ pub struct WantsToMelee {
     pub target: Entity,
 }
 
-#[derive(Component, Debug, ConvertSaveload, Clone)]
-pub struct SufferDamage {
-    pub amount: Vec<i32>,
+#[derive(Component, Debug, Clone, ConvertSaveload)]
+pub struct WantsToOpenDoor {
+    pub door: Entity,
 }
 
-impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
-        if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
-        } else {
-            let dmg = SufferDamage {
-                amount: vec![amount],
-            };
-            store.insert(victim, dmg).expect("Unable to insert damage");
-        }
-    }
+#[derive(Component, Debug, Clone, ConvertSaveload)]
+pub struct WantsToCloseDoor {
+    pub door: Entity,
 }
 
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Item {}
 
+/// What an item is worth, in gold, before enchantments
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ItemValue {
+    pub gold: i32,
+}
+
+/// Gold an entity carries
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Gold {
+    pub amount: i32,
+}
+
+/// Sells the items in its backpack and buys whatever the player brings. `categories` are the kinds
+/// of items its stock is rolled from (`consumables`, `food`, `weapons` or `armour`).
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Vendor {
+    pub categories: Vec<String>,
+}
+
+/// Goes after the items lying around when it has nothing better to do, and picks them up
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Looter {}
+
+/// How heavy an item is, in pounds. For ammunition it's the weight of a single projectile.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Weight {
+    pub weight: f32,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct ProvidesHealing {
     pub heal_amount: i32,
@@ -108,6 +209,39 @@ pub struct WantsToUseItem {
     pub target: Option<rltk::Point>,
 }
 
+#[derive(Component, Debug, ConvertSaveload)]
+pub struct WantsToCastSpell {
+    pub spell: Entity,
+    pub target: Option<rltk::Point>,
+}
+
+/// A spell anyone knowing it can cast, with the same effect components as an item. There's one
+/// (position-less) entity per spell in the raws.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct SpellTemplate {
+    pub mana_cost: i32,
+}
+
+/// Names of the spells an entity can cast
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct KnownSpells {
+    pub spells: Vec<String>,
+}
+
+/// Reading the item teaches the named spell
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct TeachesSpell {
+    pub spell: String,
+}
+
+/// Using the item identifies every unidentified item its user carries
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Identifies {}
+
+/// Using the item lifts the curse of everything its user carries
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RemovesCurse {}
+
 #[derive(Component, Debug, Clone, ConvertSaveload)]
 pub struct WantsToDropItem {
     pub item: Entity,
@@ -131,27 +265,201 @@ pub struct AreaOfEffect {
     pub radius: i32,
 }
 
-#[derive(Component, Debug, ConvertSaveload, Clone)]
-pub struct Confusion {
+/// The kinds of timed effects something can be under
+#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum StatusEffectKind {
+    /// Loses 1 hp every tick, stacks
+    Poison,
+    /// Heals 1 hp every tick
+    Regeneration,
+    /// Half speed
+    Slow,
+    /// Double speed
+    Haste,
+    /// Can only see adjacent tiles
+    Blindness,
+    /// Loses every turn
+    Paralysis,
+    /// Monsters lose their turns, the player stumbles around
+    Confusion,
+}
+
+/// What happens when an effect is put on something already under an effect of the same kind
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum StatusStacking {
+    /// Both run side by side
+    Stack,
+    /// The remaining turns add up
+    Extend,
+    /// Only the longest of the two is kept
+    Refresh,
+}
+
+impl StatusEffectKind {
+    pub fn stacking(&self) -> StatusStacking {
+        match self {
+            StatusEffectKind::Poison => StatusStacking::Stack,
+            StatusEffectKind::Regeneration => StatusStacking::Extend,
+            _ => StatusStacking::Refresh,
+        }
+    }
+
+    /// Effect that is cancelled when this one is applied
+    pub fn opposite(&self) -> Option<StatusEffectKind> {
+        match self {
+            StatusEffectKind::Slow => Some(StatusEffectKind::Haste),
+            StatusEffectKind::Haste => Some(StatusEffectKind::Slow),
+            _ => None,
+        }
+    }
+
+    /// How the log and UI describe someone under the effect
+    pub fn adjective(&self) -> &'static str {
+        match self {
+            StatusEffectKind::Poison => "poisoned",
+            StatusEffectKind::Regeneration => "regenerating",
+            StatusEffectKind::Slow => "slowed",
+            StatusEffectKind::Haste => "hasted",
+            StatusEffectKind::Blindness => "blind",
+            StatusEffectKind::Paralysis => "paralysed",
+            StatusEffectKind::Confusion => "confused",
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusEffectKind,
+    /// Game ticks left before it wears off
     pub turns: i32,
 }
 
+/// Timed effects an entity is currently under
+#[derive(Component, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusEffectKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// Adds `effect` following the stacking rule of its kind
+    pub fn add(&mut self, effect: StatusEffect) {
+        if let Some(opposite) = effect.kind.opposite() {
+            self.effects.retain(|active| active.kind != opposite);
+        }
+
+        let existing = self
+            .effects
+            .iter_mut()
+            .find(|active| active.kind == effect.kind);
+        match (existing, effect.kind.stacking()) {
+            (Some(active), StatusStacking::Extend) => active.turns += effect.turns,
+            (Some(active), StatusStacking::Refresh) => {
+                active.turns = i32::max(active.turns, effect.turns)
+            }
+            _ => self.effects.push(effect),
+        }
+    }
+
+    pub fn inflict(store: &mut WriteStorage<StatusEffects>, victim: Entity, effect: StatusEffect) {
+        if let Some(statuses) = store.get_mut(victim) {
+            statuses.add(effect);
+        } else {
+            store
+                .insert(
+                    victim,
+                    StatusEffects {
+                        effects: vec![effect],
+                    },
+                )
+                .expect("Unable to insert status effects");
+        }
+    }
+}
+
+/// Status effects an item puts on its targets, or a trap on whoever steps on it
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsStatus {
+    pub effects: Vec<StatusEffect>,
+}
+
 pub struct SerializeMe;
 
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct SerializationHelper {
     pub map: super::map::Map,
+    pub seed: u64,
+    /// Gameplay RNG state, so a loaded game keeps rolling the same sequence
+    pub rng: rltk::RandomNumberGenerator,
+    /// Levels visited other than the current one
+    pub dungeon_master: super::dungeon::MasterDungeonMap,
+    pub clock: super::initiative_system::GameClock,
 }
 
-#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum EquipmentSlot {
     Melee,
     Shield,
+    Ranged,
+    Head,
+    Torso,
+    Legs,
+    Feet,
+    Hands,
+    Ring,
+    Amulet,
+}
+
+impl EquipmentSlot {
+    /// Every slot, in the order the equipment screen lists them
+    pub const ALL: [EquipmentSlot; 10] = [
+        EquipmentSlot::Melee,
+        EquipmentSlot::Shield,
+        EquipmentSlot::Ranged,
+        EquipmentSlot::Head,
+        EquipmentSlot::Torso,
+        EquipmentSlot::Legs,
+        EquipmentSlot::Feet,
+        EquipmentSlot::Hands,
+        EquipmentSlot::Ring,
+        EquipmentSlot::Amulet,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EquipmentSlot::Melee => "Melee",
+            EquipmentSlot::Shield => "Shield",
+            EquipmentSlot::Ranged => "Ranged",
+            EquipmentSlot::Head => "Head",
+            EquipmentSlot::Torso => "Torso",
+            EquipmentSlot::Legs => "Legs",
+            EquipmentSlot::Feet => "Feet",
+            EquipmentSlot::Hands => "Hands",
+            EquipmentSlot::Ring => "Ring",
+            EquipmentSlot::Amulet => "Amulet",
+        }
+    }
 }
 
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Equippable {
     pub slot: EquipmentSlot,
+    /// Two-handed weapons take up the shield hand as well
+    pub two_handed: bool,
+}
+
+impl Equippable {
+    /// Every slot the item takes up once equipped
+    pub fn slots(&self) -> Vec<EquipmentSlot> {
+        if self.two_handed {
+            vec![self.slot, EquipmentSlot::Shield]
+        } else {
+            vec![self.slot]
+        }
+    }
 }
 
 #[derive(Component, ConvertSaveload, Clone)]
@@ -165,11 +473,60 @@ pub struct MeleePowerBonus {
     pub power: i32,
 }
 
+/// Attribute a weapon's hit and damage rolls add their bonus from
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum WeaponAttribute {
+    Might,
+    Quickness,
+}
+
+/// Damage dice and accuracy of a melee weapon. On a mob it's its natural attack, used when it
+/// wields nothing.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MeleeWeapon {
+    pub attribute: WeaponAttribute,
+    pub damage_n_dice: i32,
+    pub damage_die_type: i32,
+    pub damage_bonus: i32,
+    pub hit_bonus: i32,
+}
+
+/// Launcher (or the hands, for thrown ammunition) that shoots ammunition of kind `ammo`
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RangedWeapon {
+    pub range: i32,
+    pub power_bonus: i32,
+    pub ammo: String,
+}
+
+/// A stack of `count` projectiles of kind `ammo`
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ammunition {
+    pub ammo: String,
+    pub damage: i32,
+    pub count: i32,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToShoot {
+    pub target: rltk::Point,
+}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct DefenseBonus {
     pub defense: i32,
 }
 
+/// Magic bonus (or malus) of a piece of equipment: weapons add it to hit and damage, armour to
+/// defense. Cursed equipment can't be taken off once worn. The player `knows` about both once
+/// they wore the item or identified it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Enchantment {
+    pub bonus: i32,
+    pub cursed: bool,
+    pub known: bool,
+}
+
 #[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToRemoveItem {
     pub item: Entity,
@@ -197,12 +554,45 @@ pub struct HungerClock {
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct ProvidesFood {}
 
+/// How much an entity can carry
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub enum Burden {
+    Unburdened,
+    Burdened,
+    Strained,
+}
+
+/// Weight (in pounds) of everything an entity carries and how much it can carry at most, kept up
+/// to date by the encumbrance system
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Encumbrance {
+    pub carried: f32,
+    pub capacity: f32,
+}
+
+impl Encumbrance {
+    /// Past half its capacity an entity is burdened, past three quarters strained
+    pub fn burden(&self) -> Burden {
+        if self.carried > self.capacity * 0.75 {
+            Burden::Strained
+        } else if self.carried > self.capacity * 0.5 {
+            Burden::Burdened
+        } else {
+            Burden::Unburdened
+        }
+    }
+}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct MagicMapper {
     // power determines the radius reached by the mapper
     pub power: i32,
 }
 
+/// Sends whoever uses it to a random spot of the level
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Teleporter {}
+
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct Hidden {}
 
@@ -214,3 +604,21 @@ pub struct EntityMoved {}
 
 #[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct SingleActivation {}
+
+/// When an entity gets to act: every game tick takes `speed` off `current` and the entity acts
+/// once it's down to zero, each action then adds its cost back
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Initiative {
+    pub current: i32,
+    pub speed: i32,
+}
+
+/// Given by the initiative system to whoever gets to act this tick
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MyTurn {}
+
+/// How much initiative using an item takes: drinking or reading it, or swinging it once wielded
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ActionCost {
+    pub cost: i32,
+}
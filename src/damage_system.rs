@@ -1,33 +1,6 @@
-use super::{CombatStats, GameLog, Map, Name, Player, Position, RunState, SufferDamage};
+use super::{CombatStats, Equipped, GameLog, InBackpack, Name, Player, Position, RunState};
 use specs::prelude::*;
 
-pub struct DamageSystem {}
-
-impl<'a> System<'a> for DamageSystem {
-    type SystemData = (
-        WriteStorage<'a, CombatStats>,
-        WriteStorage<'a, SufferDamage>,
-        ReadStorage<'a, Position>,
-        WriteExpect<'a, Map>,
-        Entities<'a>,
-    );
-
-    fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage, positions, mut map, entities) = data;
-
-        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
-            stats.hp -= damage.amount.iter().sum::<i32>();
-
-            if let Some(pos) = positions.get(entity) {
-                let idx = map.xy_idx(pos.x, pos.y);
-                map.bloodstains.insert(idx);
-            }
-        }
-
-        damage.clear();
-    }
-}
-
 /// deletes all the dead entities and returns true if any died, false otherwise
 pub fn delete_the_dead(ecs: &mut World) {
     let mut dead: Vec<Entity> = Vec::new();
@@ -56,6 +29,35 @@ pub fn delete_the_dead(ecs: &mut World) {
         }
     }
 
+    // Whatever the dead carried falls to the ground where they stood
+    {
+        let entities = ecs.entities();
+        let mut positions = ecs.write_storage::<Position>();
+        let mut backpacks = ecs.write_storage::<InBackpack>();
+        let mut equipped = ecs.write_storage::<Equipped>();
+        for victim in &dead {
+            let victim_pos = match positions.get(*victim) {
+                Some(pos) => *pos,
+                None => continue,
+            };
+            let belongings: Vec<Entity> = (&entities, (&backpacks).maybe(), (&equipped).maybe())
+                .join()
+                .filter(|(_item, pack, equip)| {
+                    pack.is_some_and(|pack| pack.owner == *victim)
+                        || equip.is_some_and(|equip| equip.owner == *victim)
+                })
+                .map(|(item, _pack, _equip)| item)
+                .collect();
+            for item in belongings {
+                backpacks.remove(item);
+                equipped.remove(item);
+                positions
+                    .insert(item, victim_pos)
+                    .expect("Unable to drop item");
+            }
+        }
+    }
+
     for victim in &dead {
         ecs.delete_entity(*victim)
             .expect("Unable to delete entity!");
@@ -0,0 +1,172 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+use rltk::Point;
+use specs::prelude::*;
+
+use super::{Item, Map, Monster, MyTurn, Position, RunState};
+
+/// Value of the tiles nothing on the map leads to
+pub const UNREACHABLE: f32 = f32::MAX;
+/// How the distance from the player turns into safety on the flee map. Past -1 it pays to run a
+/// little towards the player on the way out of a dead end, instead of cowering in it.
+const FLEE_FACTOR: f32 = -1.2;
+
+/// Maps built once a turn and followed by every monster, rather than each of them looking for its
+/// own path. Tiles hold how far they are from where the map leads, so going there is a matter of
+/// stepping [downhill].
+///
+/// Only the terrain counts: monsters bump into each other when following them, and spread out
+/// around whoever they go for. That also keeps the maps good for as long as the player and the
+/// items stay put, they have to be reset whenever the map is replaced though.
+#[derive(Default)]
+pub struct DijkstraMaps {
+    /// Leads to the player
+    pub approach: Vec<f32>,
+    /// Leads away from the player, to wherever is furthest from them rather than the closest
+    /// corner
+    pub flee: Vec<f32>,
+    /// Leads to the closest item lying around
+    pub items: Vec<f32>,
+    /// Player tile the approach and flee maps were built for
+    player_tile: Option<usize>,
+    /// Tiles of the items the items map was built for
+    item_tiles: Option<Vec<usize>>,
+    /// Maps leading to whatever else monsters go for this turn, built the first time one of them
+    /// needs it
+    towards: HashMap<usize, Vec<f32>>,
+}
+
+impl DijkstraMaps {
+    /// Map leading to `target`, shared by everyone heading there this turn
+    pub fn towards(&mut self, map: &Map, target: usize) -> &[f32] {
+        self.towards
+            .entry(target)
+            .or_insert_with(|| dijkstra_map(map, [(target, 0.0)]))
+    }
+}
+
+/// Builds the [DijkstraMaps] for the monsters whose turn it is, if there are any
+pub struct DijkstraMapSystem {}
+
+impl<'a> System<'a> for DijkstraMapSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        ReadExpect<'a, Point>,
+        ReadExpect<'a, RunState>,
+        WriteExpect<'a, DijkstraMaps>,
+        ReadStorage<'a, Monster>,
+        ReadStorage<'a, MyTurn>,
+        ReadStorage<'a, Item>,
+        ReadStorage<'a, Position>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, player_pos, runstate, mut dijkstra_maps, monsters, turns, items, positions) =
+            data;
+
+        if *runstate != RunState::Ticking || (&monsters, &turns).join().next().is_none() {
+            return;
+        }
+
+        // Whoever those maps lead to may have moved since
+        dijkstra_maps.towards.clear();
+
+        let player_tile = Some(map.xy_idx(player_pos.x, player_pos.y));
+        if dijkstra_maps.player_tile != player_tile {
+            let approach = dijkstra_map(&map, [(map.xy_idx(player_pos.x, player_pos.y), 0.0)]);
+            dijkstra_maps.flee = dijkstra_map(
+                &map,
+                approach
+                    .iter()
+                    .enumerate()
+                    .filter(|(_idx, distance)| **distance < UNREACHABLE)
+                    .map(|(idx, distance)| (idx, distance * FLEE_FACTOR)),
+            );
+            dijkstra_maps.approach = approach;
+            dijkstra_maps.player_tile = player_tile;
+        }
+
+        let mut tiles: Vec<usize> = (&items, &positions)
+            .join()
+            .map(|(_item, pos)| map.xy_idx(pos.x, pos.y))
+            .collect();
+        tiles.sort_unstable();
+        if dijkstra_maps.item_tiles.as_ref() != Some(&tiles) {
+            dijkstra_maps.items = dijkstra_map(&map, tiles.iter().map(|idx| (*idx, 0.0)));
+            dijkstra_maps.item_tiles = Some(tiles);
+        }
+    }
+}
+
+/// Value of every tile of `map`: the lowest one among the `seeds` (tiles with their own starting
+/// value) plus the cost of walking from it
+pub fn dijkstra_map(map: &Map, seeds: impl IntoIterator<Item = (usize, f32)>) -> Vec<f32> {
+    let mut values = vec![UNREACHABLE; map.tiles.len()];
+    let mut open = BinaryHeap::new();
+    for (idx, value) in seeds {
+        if value < values[idx] {
+            values[idx] = value;
+            open.push(OpenTile { idx, value });
+        }
+    }
+
+    while let Some(OpenTile { idx, value }) = open.pop() {
+        // Already reached some cheaper way since it was queued
+        if value > values[idx] {
+            continue;
+        }
+        for (exit, cost) in map.terrain_exits(idx) {
+            let exit_value = value + cost;
+            if exit_value < values[exit] {
+                values[exit] = exit_value;
+                open.push(OpenTile {
+                    idx: exit,
+                    value: exit_value,
+                });
+            }
+        }
+    }
+    values
+}
+
+/// The lowest tile of `values` around `idx` that nobody stands on, as long as it's no higher than
+/// `idx` itself. When the best way is taken the next best one will do, so monsters go around
+/// each other instead of queueing up.
+pub fn downhill(map: &Map, values: &[f32], idx: usize) -> Option<usize> {
+    if values[idx] == UNREACHABLE {
+        return None;
+    }
+    map.terrain_exits(idx)
+        .iter()
+        .map(|(exit, _cost)| *exit)
+        .filter(|exit| {
+            values[*exit] <= values[idx] && (!map.blocked[*exit] || map.closed_doors.contains(exit))
+        })
+        .min_by(|a, b| values[*a].total_cmp(&values[*b]))
+}
+
+/// A tile waiting in [dijkstra_map]'s queue, the one with the lowest value comes out first
+#[derive(PartialEq)]
+struct OpenTile {
+    idx: usize,
+    value: f32,
+}
+
+impl Eq for OpenTile {}
+
+impl Ord for OpenTile {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .value
+            .total_cmp(&self.value)
+            .then_with(|| other.idx.cmp(&self.idx))
+    }
+}
+
+impl PartialOrd for OpenTile {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
@@ -0,0 +1,105 @@
+use specs::prelude::*;
+
+use super::{
+    gamelog::GameLog, BlocksTile, BlocksVisibility, Door, Map, Position, Renderable, Viewshed,
+    WantsToCloseDoor, WantsToOpenDoor,
+};
+
+const OPEN_DOOR_GLYPH: char = '/';
+const CLOSED_DOOR_GLYPH: char = '+';
+
+/// Opens and closes the doors people want to. A closed door blocks its tile and sight, so every
+/// field of view gets recomputed whenever one of them changes.
+pub struct DoorSystem {}
+
+impl<'a> System<'a> for DoorSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToOpenDoor>,
+        WriteStorage<'a, WantsToCloseDoor>,
+        WriteStorage<'a, Door>,
+        WriteStorage<'a, BlocksTile>,
+        WriteStorage<'a, BlocksVisibility>,
+        WriteStorage<'a, Renderable>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Viewshed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            map,
+            mut gamelog,
+            entities,
+            mut wants_open,
+            mut wants_close,
+            mut doors,
+            mut blocks_tile,
+            mut blocks_visibility,
+            mut renderables,
+            positions,
+            mut viewsheds,
+        ) = data;
+
+        let mut changed = false;
+
+        for open in wants_open.join() {
+            let door = match doors.get_mut(open.door) {
+                Some(door) if !door.open => door,
+                _ => continue,
+            };
+            door.open = true;
+            blocks_tile.remove(open.door);
+            blocks_visibility.remove(open.door);
+            if let Some(render) = renderables.get_mut(open.door) {
+                render.glyph = rltk::to_cp437(OPEN_DOOR_GLYPH);
+            }
+            changed = true;
+        }
+
+        for (entity, close) in (&entities, &wants_close).join() {
+            let door = match doors.get_mut(close.door) {
+                Some(door) if door.open => door,
+                _ => continue,
+            };
+            // Nothing can be shut on whoever (or whatever) stands in the doorway
+            let in_the_way = positions.get(close.door).is_some_and(|pos| {
+                map.tile_content[map.xy_idx(pos.x, pos.y)]
+                    .iter()
+                    .any(|other| *other != close.door)
+            });
+            if in_the_way {
+                if entity == *player_entity {
+                    gamelog
+                        .entries
+                        .push("Something is in the way of the door.".to_string());
+                }
+                continue;
+            }
+            door.open = false;
+            blocks_tile
+                .insert(close.door, BlocksTile {})
+                .expect("Unable to insert BlocksTile");
+            blocks_visibility
+                .insert(close.door, BlocksVisibility {})
+                .expect("Unable to insert BlocksVisibility");
+            if let Some(render) = renderables.get_mut(close.door) {
+                render.glyph = rltk::to_cp437(CLOSED_DOOR_GLYPH);
+            }
+            changed = true;
+        }
+
+        wants_open.clear();
+        wants_close.clear();
+
+        if changed {
+            for viewshed in (&mut viewsheds).join() {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
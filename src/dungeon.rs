@@ -0,0 +1,123 @@
+use rltk::RGB;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use super::{Enchantment, Map, OtherLevelPosition, Player, Position, Viewshed};
+
+/// How an unidentified kind of item looks during a run
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ItemAppearance {
+    pub name: String,
+    pub fg: RGB,
+}
+
+/// Every level the player already visited, indexed by depth, along with what they found out
+/// about the run's items
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct MasterDungeonMap {
+    maps: HashMap<i32, Map>,
+    /// Appearance of every kind of item that needs identifying, indexed by its real name
+    pub item_appearances: HashMap<String, ItemAppearance>,
+    /// Real names of the kinds of items the player identified
+    pub identified_items: HashSet<String>,
+}
+
+impl MasterDungeonMap {
+    pub fn new() -> MasterDungeonMap {
+        MasterDungeonMap {
+            maps: HashMap::new(),
+            item_appearances: HashMap::new(),
+            identified_items: HashSet::new(),
+        }
+    }
+
+    /// The name the player knows an item named `name` by: its appearance until it's identified,
+    /// along with its enchantment once they know about it
+    pub fn item_name(&self, name: &str, enchantment: Option<&Enchantment>) -> String {
+        let name = match self.item_appearances.get(name) {
+            Some(appearance) if !self.identified_items.contains(name) => appearance.name.clone(),
+            _ => name.to_string(),
+        };
+        match enchantment {
+            Some(enchantment) if enchantment.known && enchantment.cursed => {
+                format!("{:+} {} (cursed)", enchantment.bonus, name)
+            }
+            Some(enchantment) if enchantment.known => format!("{:+} {}", enchantment.bonus, name),
+            _ => name,
+        }
+    }
+
+    /// Marks the kind of item named `name` as identified, returning whether it wasn't yet
+    pub fn identify(&mut self, name: &str) -> bool {
+        self.item_appearances.contains_key(name) && self.identified_items.insert(name.to_string())
+    }
+
+    pub fn store_map(&mut self, map: &Map) {
+        self.maps.insert(map.depth, map.clone());
+    }
+
+    pub fn get_map(&self, depth: i32) -> Option<Map> {
+        self.maps.get(&depth).map(|map| {
+            let mut map = map.clone();
+            // The entity index isn't stored, it gets rebuilt by the map indexing system
+            map.tile_content = vec![Vec::new(); map.tiles.len()];
+            map
+        })
+    }
+}
+
+/// Takes every entity out of the current level (except the player and whatever they carry),
+/// remembering where they were so [thaw_level_entities] can bring them back.
+pub fn freeze_level_entities(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut other_level_positions = ecs.write_storage::<OtherLevelPosition>();
+    let players = ecs.read_storage::<Player>();
+    let depth = ecs.fetch::<Map>().depth;
+
+    let mut pos_to_delete: Vec<Entity> = Vec::new();
+    for (entity, pos, _player) in (&entities, &positions, !&players).join() {
+        other_level_positions
+            .insert(
+                entity,
+                OtherLevelPosition {
+                    x: pos.x,
+                    y: pos.y,
+                    depth,
+                },
+            )
+            .expect("Insert fail");
+        pos_to_delete.push(entity);
+    }
+
+    for entity in pos_to_delete.iter() {
+        positions.remove(*entity);
+    }
+}
+
+/// Puts back the entities that were frozen on the current level
+pub fn thaw_level_entities(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut other_level_positions = ecs.write_storage::<OtherLevelPosition>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let depth = ecs.fetch::<Map>().depth;
+
+    let mut pos_to_delete: Vec<Entity> = Vec::new();
+    for (entity, pos) in (&entities, &other_level_positions).join() {
+        if pos.depth == depth {
+            positions
+                .insert(entity, Position { x: pos.x, y: pos.y })
+                .expect("Insert fail");
+            if let Some(viewshed) = viewsheds.get_mut(entity) {
+                viewshed.dirty = true;
+            }
+            pos_to_delete.push(entity);
+        }
+    }
+
+    for entity in pos_to_delete.iter() {
+        other_level_positions.remove(*entity);
+    }
+}
@@ -0,0 +1,52 @@
+use specs::prelude::*;
+
+use super::{entity_position, EffectSpawner, EffectType};
+use crate::{experience, shop, CombatStats, MagicStats, Map};
+
+pub fn inflict_damage(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    if let EffectType::Damage { amount } = effect.effect_type {
+        let killed = {
+            let mut stats = ecs.write_storage::<CombatStats>();
+            match stats.get_mut(target) {
+                Some(stats) => {
+                    let was_alive = stats.hp > 0;
+                    stats.hp -= amount;
+
+                    if let Some(idx) = entity_position(ecs, target) {
+                        ecs.fetch_mut::<Map>().bloodstains.insert(idx);
+                    }
+
+                    was_alive && stats.hp < 1
+                }
+                None => false,
+            }
+        };
+
+        // Whoever dealt the killing blow takes the victim's gold, and the experience if it was the
+        // player
+        if let Some(killer) = effect.creator {
+            if killed && killer != target {
+                experience::award_kill(ecs, killer, target);
+                shop::loot_gold(ecs, killer, target);
+            }
+        }
+    }
+}
+
+pub fn heal_damage(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    if let EffectType::Healing { amount } = effect.effect_type {
+        let mut stats = ecs.write_storage::<CombatStats>();
+        if let Some(stats) = stats.get_mut(target) {
+            stats.hp = i32::min(stats.max_hp, stats.hp + amount);
+        }
+    }
+}
+
+pub fn restore_mana(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    if let EffectType::RestoreMana { amount } = effect.effect_type {
+        let mut stats = ecs.write_storage::<MagicStats>();
+        if let Some(stats) = stats.get_mut(target) {
+            stats.mana = i32::min(stats.max_mana, stats.mana + amount);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use specs::prelude::*;
+
+use crate::{HungerClock, HungerState};
+
+pub fn feed(ecs: &mut World, target: Entity) {
+    let mut hunger_clocks = ecs.write_storage::<HungerClock>();
+    if let Some(hunger_clock) = hunger_clocks.get_mut(target) {
+        // This is sort of filling a hunger bar
+        hunger_clock.duration += 150;
+        if hunger_clock.duration > 200 {
+            match hunger_clock.state {
+                HungerState::WellFed => {
+                    hunger_clock.duration = 30;
+                }
+                HungerState::Normal => {
+                    hunger_clock.duration = i32::min(hunger_clock.duration - 200, 30);
+                    hunger_clock.state = HungerState::WellFed;
+                }
+                HungerState::Hungry => {
+                    hunger_clock.duration -= 200;
+                    hunger_clock.state = HungerState::Normal;
+                }
+                HungerState::Starving => {
+                    hunger_clock.duration -= 200;
+                    hunger_clock.state = HungerState::Hungry;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::VecDeque;
+
+use rltk::{FontCharType, Point, RGB};
+use specs::prelude::*;
+
+use super::{Map, Position, StatusEffect};
+
+mod damage;
+mod hunger;
+mod movement;
+mod particles;
+mod status;
+mod triggers;
+
+/// What an effect does to whatever it ends up targeting
+#[derive(Clone, Debug)]
+pub enum EffectType {
+    Damage {
+        amount: i32,
+    },
+    Healing {
+        amount: i32,
+    },
+    RestoreMana {
+        amount: i32,
+    },
+    Status {
+        effect: StatusEffect,
+    },
+    /// Fills the target's hunger clock
+    Feed,
+    Particle {
+        glyph: FontCharType,
+        fg: RGB,
+        bg: RGB,
+        lifetime: f32,
+    },
+    /// Moves the target somewhere else on the current level
+    Teleport {
+        destination: Point,
+    },
+    /// Expands into the effects of `item` (its damage, healing, status effects...)
+    ItemUse {
+        item: Entity,
+    },
+    /// Expands into the effects of `spell`, strengthened by the caster's spell power
+    SpellUse {
+        spell: Entity,
+    },
+    /// Expands into the effects of a trap someone stepped on
+    TriggerFire {
+        trigger: Entity,
+    },
+}
+
+/// A particle effect of `color` over black
+pub fn particle(color: (u8, u8, u8), glyph: char, lifetime: f32) -> EffectType {
+    EffectType::Particle {
+        glyph: rltk::to_cp437(glyph),
+        fg: RGB::named(color),
+        bg: RGB::named(rltk::BLACK),
+        lifetime,
+    }
+}
+
+/// Who or where an effect lands
+#[derive(Clone, Debug)]
+pub enum Targets {
+    Single {
+        target: Entity,
+    },
+    /// Everyone standing on the tile
+    Tile {
+        tile_idx: usize,
+    },
+    /// Every tile in view of `center` within `radius`, and everyone on them
+    Area {
+        center: Point,
+        radius: i32,
+    },
+}
+
+pub struct EffectSpawner {
+    /// The entity responsible for the effect, if any
+    pub creator: Option<Entity>,
+    pub effect_type: EffectType,
+    pub targets: Targets,
+}
+
+/// Effects waiting to be applied. Systems add to it and [run_effects_queue] applies them all in
+/// one place, in the order they were added.
+pub struct EffectQueue {
+    queue: VecDeque<EffectSpawner>,
+}
+
+impl EffectQueue {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> EffectQueue {
+        EffectQueue {
+            queue: VecDeque::new(),
+        }
+    }
+
+    pub fn add(&mut self, creator: Option<Entity>, effect_type: EffectType, targets: Targets) {
+        self.queue.push_back(EffectSpawner {
+            creator,
+            effect_type,
+            targets,
+        });
+    }
+}
+
+/// Applies every queued effect, including the ones queued while doing so
+pub fn run_effects_queue(ecs: &mut World) {
+    loop {
+        let effect = ecs.write_resource::<EffectQueue>().queue.pop_front();
+        match effect {
+            Some(effect) => target_applicator(ecs, &effect),
+            None => break,
+        }
+    }
+}
+
+fn target_applicator(ecs: &mut World, effect: &EffectSpawner) {
+    match effect.effect_type {
+        EffectType::ItemUse { item } => {
+            triggers::item_trigger(ecs, effect.creator, item, &effect.targets)
+        }
+        EffectType::SpellUse { spell } => {
+            triggers::spell_trigger(ecs, effect.creator, spell, &effect.targets)
+        }
+        EffectType::TriggerFire { trigger } => {
+            triggers::trigger(ecs, effect.creator, trigger, &effect.targets)
+        }
+        _ => match effect.targets {
+            Targets::Single { target } => affect_entity(ecs, effect, target),
+            _ => {
+                for tile_idx in affected_tiles(ecs, &effect.targets) {
+                    affect_tile(ecs, effect, tile_idx);
+                }
+            }
+        },
+    }
+}
+
+/// Tiles `targets` covers. A single target is followed wherever it is, so it covers no tile.
+fn affected_tiles(ecs: &World, targets: &Targets) -> Vec<usize> {
+    match targets {
+        Targets::Single { .. } => Vec::new(),
+        Targets::Tile { tile_idx } => vec![*tile_idx],
+        Targets::Area { center, radius } => {
+            let map = ecs.fetch::<Map>();
+            let mut tiles: Vec<usize> = rltk::field_of_view(*center, *radius, &*map)
+                .iter()
+                .filter(|p| p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1)
+                .map(|p| map.xy_idx(p.x, p.y))
+                .collect();
+            // The field of view comes in no particular order, and the rolls for each tile have to
+            // come out the same every run
+            tiles.sort_unstable();
+            tiles
+        }
+    }
+}
+
+/// Entities `targets` lands on at the moment
+pub fn targeted_entities(ecs: &World, targets: &Targets) -> Vec<Entity> {
+    match targets {
+        Targets::Single { target } => vec![*target],
+        _ => {
+            let map = ecs.fetch::<Map>();
+            affected_tiles(ecs, targets)
+                .iter()
+                .flat_map(|idx| map.tile_content[*idx].iter().copied())
+                .collect()
+        }
+    }
+}
+
+fn affect_tile(ecs: &mut World, effect: &EffectSpawner, tile_idx: usize) {
+    if let EffectType::Particle { .. } = effect.effect_type {
+        particles::particle_to_tile(ecs, tile_idx, &effect.effect_type);
+        return;
+    }
+
+    let content = ecs.fetch::<Map>().tile_content[tile_idx].clone();
+    for entity in content.iter() {
+        affect_entity(ecs, effect, *entity);
+    }
+}
+
+fn affect_entity(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    match &effect.effect_type {
+        EffectType::Damage { .. } => damage::inflict_damage(ecs, effect, target),
+        EffectType::Healing { .. } => damage::heal_damage(ecs, effect, target),
+        EffectType::RestoreMana { .. } => damage::restore_mana(ecs, effect, target),
+        EffectType::Status { .. } => status::apply_status(ecs, effect, target),
+        EffectType::Feed => hunger::feed(ecs, target),
+        EffectType::Particle { .. } => {
+            if let Some(tile_idx) = entity_position(ecs, target) {
+                particles::particle_to_tile(ecs, tile_idx, &effect.effect_type);
+            }
+        }
+        EffectType::Teleport { .. } => movement::teleport(ecs, effect, target),
+        EffectType::ItemUse { .. }
+        | EffectType::SpellUse { .. }
+        | EffectType::TriggerFire { .. } => {}
+    }
+}
+
+/// Map index of the tile `entity` stands on, `None` if it isn't on the map (e.g. in a backpack)
+pub fn entity_position(ecs: &World, entity: Entity) -> Option<usize> {
+    let positions = ecs.read_storage::<Position>();
+    let map = ecs.fetch::<Map>();
+    positions.get(entity).map(|pos| map.xy_idx(pos.x, pos.y))
+}
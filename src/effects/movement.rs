@@ -0,0 +1,29 @@
+use specs::prelude::*;
+
+use super::{EffectSpawner, EffectType};
+use crate::{EntityMoved, Map, Position, Viewshed};
+
+pub fn teleport(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    if let EffectType::Teleport { destination } = effect.effect_type {
+        let mut positions = ecs.write_storage::<Position>();
+        let map = ecs.fetch::<Map>();
+        if map.blocked[map.xy_idx(destination.x, destination.y)] {
+            return;
+        }
+
+        if let Some(pos) = positions.get_mut(target) {
+            pos.x = destination.x;
+            pos.y = destination.y;
+
+            if target == *ecs.fetch::<Entity>() {
+                *ecs.write_resource::<rltk::Point>() = destination;
+            }
+            if let Some(viewshed) = ecs.write_storage::<Viewshed>().get_mut(target) {
+                viewshed.dirty = true;
+            }
+            ecs.write_storage::<EntityMoved>()
+                .insert(target, EntityMoved {})
+                .expect("Unable to insert EntityMoved marker");
+        }
+    }
+}
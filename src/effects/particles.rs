@@ -0,0 +1,20 @@
+use specs::prelude::*;
+
+use super::EffectType;
+use crate::{Map, ParticleBuilder};
+
+pub fn particle_to_tile(ecs: &mut World, tile_idx: usize, effect: &EffectType) {
+    if let EffectType::Particle {
+        glyph,
+        fg,
+        bg,
+        lifetime,
+    } = effect
+    {
+        let map = ecs.fetch::<Map>();
+        let x = tile_idx as i32 % map.width;
+        let y = tile_idx as i32 / map.width;
+        ecs.fetch_mut::<ParticleBuilder>()
+            .request(x, y, *fg, *bg, *glyph, *lifetime);
+    }
+}
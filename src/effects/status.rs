@@ -0,0 +1,17 @@
+use specs::prelude::*;
+
+use super::{EffectSpawner, EffectType};
+use crate::{StatusEffectKind, StatusEffects, Viewshed};
+
+pub fn apply_status(ecs: &mut World, effect: &EffectSpawner, target: Entity) {
+    if let EffectType::Status { effect } = effect.effect_type {
+        StatusEffects::inflict(&mut ecs.write_storage::<StatusEffects>(), target, effect);
+
+        // Going blind shrinks what the target sees right away
+        if effect.kind == StatusEffectKind::Blindness {
+            if let Some(viewshed) = ecs.write_storage::<Viewshed>().get_mut(target) {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,392 @@
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+
+use super::{particle, targeted_entities, EffectQueue, EffectType, Targets};
+use crate::dungeon::MasterDungeonMap;
+use crate::gamelog::GameLog;
+use crate::status_effect_system::describe_statuses;
+use crate::{
+    CombatStats, Consumable, Enchantment, Equipped, HungerClock, Identifies, InBackpack,
+    InflictsDamage, InflictsStatus, KnownSpells, MagicMapper, MagicStats, Map, Name, ProvidesFood,
+    ProvidesHealing, ProvidesManaRestore, RemovesCurse, RunState, SingleActivation, TeachesSpell,
+    Teleporter,
+};
+
+/// Queues the effects of `item` used by `creator` on `targets`, then consumes it if it's
+/// a consumable
+pub fn item_trigger(ecs: &mut World, creator: Option<Entity>, item: Entity, targets: &Targets) {
+    let by_player = creator == Some(*ecs.fetch::<Entity>());
+
+    // Using an item tells the player what it is
+    if by_player {
+        if let Some(name) = ecs.read_storage::<Name>().get(item) {
+            ecs.write_resource::<MasterDungeonMap>()
+                .identify(&name.name);
+        }
+    }
+
+    queue_effects(ecs, creator, item, targets, None);
+
+    // Maybe it identifies what the player carries, or lifts their curses
+    if by_player && ecs.read_storage::<Identifies>().contains(item) {
+        identify_carried(ecs, creator.unwrap());
+    }
+    if by_player && ecs.read_storage::<RemovesCurse>().contains(item) {
+        remove_curses(ecs, creator.unwrap());
+    }
+
+    // Maybe it teaches a spell
+    if let Some(teaches) = ecs.read_storage::<TeachesSpell>().get(item) {
+        if let Some(learner) = creator {
+            let mut known = ecs.write_storage::<KnownSpells>();
+            let mut gamelog = ecs.write_resource::<GameLog>();
+            match known.get_mut(learner) {
+                Some(known) if !known.spells.contains(&teaches.spell) => {
+                    known.spells.push(teaches.spell.clone());
+                    if by_player {
+                        gamelog
+                            .entries
+                            .push(format!("You learn to cast {}.", teaches.spell));
+                    }
+                }
+                _ => {
+                    if by_player {
+                        gamelog
+                            .entries
+                            .push(format!("You learn nothing new about {}.", teaches.spell));
+                    }
+                }
+            }
+        }
+    }
+
+    // Consume consumables
+    if ecs.read_storage::<Consumable>().get(item).is_some() {
+        ecs.entities().delete(item).expect("Delete Failed");
+    }
+}
+
+/// Whether `item` is in the backpack of `owner` or equipped by them
+fn is_carried_by(ecs: &World, item: Entity, owner: Entity) -> bool {
+    ecs.read_storage::<InBackpack>()
+        .get(item)
+        .is_some_and(|pack| pack.owner == owner)
+        || ecs
+            .read_storage::<Equipped>()
+            .get(item)
+            .is_some_and(|equipped| equipped.owner == owner)
+}
+
+/// Identifies every kind of item `owner` carries (in the backpack or equipped), and reveals
+/// their enchantments
+fn identify_carried(ecs: &mut World, owner: Entity) {
+    let names = ecs.read_storage::<Name>();
+    let mut enchantments = ecs.write_storage::<Enchantment>();
+    let mut dungeon_master = ecs.write_resource::<MasterDungeonMap>();
+    let mut gamelog = ecs.write_resource::<GameLog>();
+
+    let mut identified = false;
+    for (entity, name) in (&ecs.entities(), &names).join() {
+        if !is_carried_by(ecs, entity, owner) {
+            continue;
+        }
+        let before = dungeon_master.item_name(&name.name, enchantments.get(entity));
+        dungeon_master.identify(&name.name);
+        if let Some(enchantment) = enchantments.get_mut(entity) {
+            enchantment.known = true;
+        }
+        let after = dungeon_master.item_name(&name.name, enchantments.get(entity));
+        if before != after {
+            gamelog
+                .entries
+                .push(format!("The {} is a {}.", before, after));
+            identified = true;
+        }
+    }
+    if !identified {
+        gamelog
+            .entries
+            .push("You already know everything you carry.".to_string());
+    }
+}
+
+/// Lifts the curse of everything `owner` carries (in the backpack or equipped)
+fn remove_curses(ecs: &mut World, owner: Entity) {
+    let mut enchantments = ecs.write_storage::<Enchantment>();
+    let mut uncursed = false;
+    for (entity, enchantment) in (&ecs.entities(), &mut enchantments).join() {
+        if enchantment.cursed && is_carried_by(ecs, entity, owner) {
+            enchantment.cursed = false;
+            uncursed = true;
+        }
+    }
+
+    let mut gamelog = ecs.write_resource::<GameLog>();
+    if uncursed {
+        gamelog
+            .entries
+            .push("You feel as if someone is watching over you.".to_string());
+    } else {
+        gamelog.entries.push("Nothing seems to happen.".to_string());
+    }
+}
+
+/// Queues the effects of `spell` cast by `creator` on `targets`. The caster's spell power makes
+/// them stronger.
+pub fn spell_trigger(ecs: &mut World, creator: Option<Entity>, spell: Entity, targets: &Targets) {
+    let spell_power = creator
+        .and_then(|caster| {
+            ecs.read_storage::<MagicStats>()
+                .get(caster)
+                .map(|m| m.power)
+        })
+        .unwrap_or(0);
+    queue_effects(ecs, creator, spell, targets, Some(spell_power));
+}
+
+/// Queues whatever the effect components of `source` (an item or a spell) do. `spell_power` is
+/// set when it's cast as a spell.
+fn queue_effects(
+    ecs: &mut World,
+    creator: Option<Entity>,
+    source: Entity,
+    targets: &Targets,
+    spell_power: Option<i32>,
+) {
+    let by_player = creator == Some(*ecs.fetch::<Entity>());
+    let target_entities = targeted_entities(ecs, targets);
+    let cast = spell_power.is_some();
+    let verb = if cast { "cast" } else { "use" };
+    // Spell power adds to damage and healing, and to how long status effects last
+    let power_bonus = |divisor: i32| spell_power.map_or(0, |power| power / divisor);
+
+    let mut queue = ecs.write_resource::<EffectQueue>();
+    let mut gamelog = ecs.write_resource::<GameLog>();
+    let names = ecs.read_storage::<Name>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let item_name = &names.get(source).unwrap().name;
+
+    // Area effects light up every tile they reach
+    if let Targets::Area { .. } = targets {
+        queue.add(creator, particle(rltk::ORANGE, '░', 200.0), targets.clone());
+    }
+
+    // Maybe Healing?
+    if let Some(healer) = ecs.read_storage::<ProvidesHealing>().get(source) {
+        let heal_amount = healer.heal_amount + power_bonus(1);
+        queue.add(
+            creator,
+            EffectType::Healing {
+                amount: heal_amount,
+            },
+            targets.clone(),
+        );
+        for target in target_entities.iter() {
+            if combat_stats.get(*target).is_none() {
+                continue;
+            }
+            if by_player && cast {
+                gamelog.entries.push(format!(
+                    "You cast {}, healing {} hp",
+                    item_name, heal_amount
+                ));
+            } else if by_player {
+                gamelog.entries.push(format!(
+                    "You drink the {}, healing {} hp",
+                    item_name, heal_amount
+                ));
+            }
+            queue.add(
+                creator,
+                particle(rltk::GREEN, '♥', 200.0),
+                Targets::Single { target: *target },
+            );
+        }
+    }
+
+    // Maybe Mana Restoring?
+    if let Some(mana_restorer) = ecs.read_storage::<ProvidesManaRestore>().get(source) {
+        queue.add(
+            creator,
+            EffectType::RestoreMana {
+                amount: mana_restorer.mana_amount,
+            },
+            targets.clone(),
+        );
+        let magic_stats = ecs.read_storage::<MagicStats>();
+        for target in target_entities.iter() {
+            if magic_stats.get(*target).is_none() {
+                continue;
+            }
+            if by_player {
+                gamelog.entries.push(format!(
+                    "You drink the {}, restoring {} mana",
+                    item_name, mana_restorer.mana_amount
+                ));
+            }
+            queue.add(
+                creator,
+                particle(rltk::BLUE, '♥', 200.0),
+                Targets::Single { target: *target },
+            );
+        }
+    }
+
+    // if it's edible, eat it
+    if ecs.read_storage::<ProvidesFood>().get(source).is_some() {
+        queue.add(creator, EffectType::Feed, targets.clone());
+        let hunger_clocks = ecs.read_storage::<HungerClock>();
+        if target_entities
+            .iter()
+            .any(|target| hunger_clocks.get(*target).is_some())
+        {
+            gamelog.entries.push(format!("You eat the {}.", item_name));
+        }
+    }
+
+    // Deals Damage?
+    if let Some(damage) = ecs.read_storage::<InflictsDamage>().get(source) {
+        let damage = damage.damage + power_bonus(2);
+        queue.add(
+            creator,
+            EffectType::Damage { amount: damage },
+            targets.clone(),
+        );
+        for mob in target_entities.iter() {
+            if combat_stats.get(*mob).is_none() {
+                continue;
+            }
+            if by_player {
+                gamelog.entries.push(format!(
+                    "You {} {} on {}, inflicting {} hp.",
+                    verb,
+                    item_name,
+                    names.get(*mob).unwrap().name,
+                    damage
+                ));
+            }
+            queue.add(
+                creator,
+                particle(rltk::RED, '‼', 200.0),
+                Targets::Single { target: *mob },
+            );
+        }
+    }
+
+    // Applies status effects?
+    if let Some(inflicts) = ecs.read_storage::<InflictsStatus>().get(source) {
+        for effect in inflicts.effects.iter() {
+            let mut effect = *effect;
+            effect.turns += power_bonus(4);
+            queue.add(creator, EffectType::Status { effect }, targets.clone());
+        }
+        for mob in target_entities.iter() {
+            if combat_stats.get(*mob).is_none() {
+                continue;
+            }
+            if by_player {
+                gamelog.entries.push(format!(
+                    "You {} {} on {}, leaving them {}.",
+                    verb,
+                    item_name,
+                    names.get(*mob).unwrap().name,
+                    describe_statuses(&inflicts.effects)
+                ));
+            }
+            queue.add(
+                creator,
+                particle(rltk::MAGENTA, '?', 200.0),
+                Targets::Single { target: *mob },
+            );
+        }
+    }
+
+    // Teleports somewhere at random?
+    if ecs.read_storage::<Teleporter>().get(source).is_some() {
+        let map = ecs.fetch::<Map>();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let open_tiles: Vec<usize> = (0..map.tiles.len())
+            .filter(|idx| !map.blocked[*idx] && map.tiles[*idx].is_walkable())
+            .collect();
+        for target in target_entities.iter() {
+            if let Some(idx) = rng.random_slice_entry(&open_tiles) {
+                queue.add(
+                    creator,
+                    EffectType::Teleport {
+                        destination: Point::new(*idx as i32 % map.width, *idx as i32 / map.width),
+                    },
+                    Targets::Single { target: *target },
+                );
+            }
+        }
+        if by_player {
+            gamelog
+                .entries
+                .push(format!("The {} whisks you away!", item_name));
+        }
+    }
+
+    // Maybe reveal map
+    if let Some(MagicMapper { power }) = ecs.read_storage::<MagicMapper>().get(source) {
+        *ecs.write_resource::<RunState>() = RunState::MagicMapReveal {
+            remaining_power: *power,
+            offset: 1,
+        };
+        gamelog.entries.push("Magic Mapper Activate!".to_string());
+    }
+}
+
+/// Queues the effects of the trap `trigger` on whoever set it off
+pub fn trigger(ecs: &mut World, creator: Option<Entity>, trigger: Entity, targets: &Targets) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let target_entities = targeted_entities(ecs, targets);
+
+    let mut queue = ecs.write_resource::<EffectQueue>();
+    let mut log = ecs.write_resource::<GameLog>();
+
+    if let Some(damage) = ecs.read_storage::<InflictsDamage>().get(trigger) {
+        queue.add(
+            creator,
+            EffectType::Damage {
+                amount: damage.damage,
+            },
+            targets.clone(),
+        );
+        for target in target_entities.iter() {
+            queue.add(
+                creator,
+                particle(rltk::ORANGE, '‼', 200.0),
+                Targets::Single { target: *target },
+            );
+            if *target == player_entity {
+                log.entries
+                    .push(format!("you suffer {} damage!", &damage.damage));
+            }
+        }
+    }
+
+    if let Some(inflicts) = ecs.read_storage::<InflictsStatus>().get(trigger) {
+        for effect in inflicts.effects.iter() {
+            queue.add(
+                creator,
+                EffectType::Status { effect: *effect },
+                targets.clone(),
+            );
+        }
+        if target_entities.contains(&player_entity) {
+            log.entries
+                .push(format!("You are {}!", describe_statuses(&inflicts.effects)));
+        }
+    }
+
+    // Remove single activation traps
+    if ecs
+        .read_storage::<SingleActivation>()
+        .get(trigger)
+        .is_some()
+    {
+        ecs.entities()
+            .delete(trigger)
+            .expect("Unable to delete single activation trigger");
+    }
+}
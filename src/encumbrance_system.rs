@@ -0,0 +1,77 @@
+use super::{
+    gamelog::GameLog, Ammunition, Attributes, Burden, Encumbrance, Equipped, InBackpack, Weight,
+};
+use specs::prelude::*;
+
+/// Pounds an entity can carry for every point of might
+pub const CAPACITY_PER_MIGHT: f32 = 10.0;
+
+/// Weight of an item, counting every projectile of an ammunition stack
+pub fn stack_weight(weight: Option<&Weight>, ammunition: Option<&Ammunition>) -> f32 {
+    let weight = weight.map_or(0.0, |w| w.weight);
+    match ammunition {
+        Some(ammo) => weight * ammo.count as f32,
+        None => weight,
+    }
+}
+
+/// Adds up what everyone keeping track of their encumbrance carries (in the backpack and
+/// equipped), and how much their might lets them carry
+pub struct EncumbranceSystem {}
+
+impl<'a> System<'a> for EncumbranceSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, Encumbrance>,
+        ReadStorage<'a, Attributes>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, Ammunition>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Equipped>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            player,
+            mut log,
+            mut encumbrances,
+            attributes,
+            weights,
+            ammunition,
+            backpack,
+            equipped,
+        ) = data;
+
+        for (entity, encumbrance) in (&entities, &mut encumbrances).join() {
+            let carried: f32 = (&entities, &weights)
+                .join()
+                .filter(|(item, _weight)| {
+                    backpack.get(*item).is_some_and(|pack| pack.owner == entity)
+                        || equipped.get(*item).is_some_and(|eq| eq.owner == entity)
+                })
+                .map(|(item, weight)| stack_weight(Some(weight), ammunition.get(item)))
+                .sum();
+            let might = attributes.get(entity).map_or(10, |a| a.might);
+
+            let before = encumbrance.burden();
+            encumbrance.carried = carried;
+            encumbrance.capacity = might as f32 * CAPACITY_PER_MIGHT;
+            let after = encumbrance.burden();
+
+            if entity == *player && before != after {
+                log.entries.push(
+                    match after {
+                        Burden::Unburdened => "You are no longer weighed down.",
+                        Burden::Burdened => "You are burdened by what you carry.",
+                        Burden::Strained => "You strain under the weight of what you carry!",
+                    }
+                    .to_string(),
+                );
+            }
+        }
+    }
+}
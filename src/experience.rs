@@ -0,0 +1,140 @@
+use specs::prelude::*;
+
+use super::{
+    combat::attribute_bonus, gamelog::GameLog, Attributes, CombatStats, Experience, MagicStats,
+};
+
+/// Experience needed for each level, reaching level N + 1 takes N times this
+const XP_PER_LEVEL: i32 = 1000;
+/// Experience a kill is worth for each level of the victim
+const KILL_XP_PER_LEVEL: i32 = 100;
+/// What every level up brings on its own, on top of the improvement the player picks. Fitness
+/// adds its bonus to the hit points, intelligence to the mana.
+const HP_PER_LEVEL: i32 = 10;
+const MANA_PER_LEVEL: i32 = 2;
+const POWER_PER_LEVEL: i32 = 1;
+
+/// The extra the player can pick each time they level up
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Improvement {
+    Toughness,
+    Might,
+    Guard,
+    Focus,
+}
+
+impl Improvement {
+    /// Every improvement, in the order the level up screen lists them
+    pub const ALL: [Improvement; 4] = [
+        Improvement::Toughness,
+        Improvement::Might,
+        Improvement::Guard,
+        Improvement::Focus,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Improvement::Toughness => "Toughness (+5 max HP)",
+            Improvement::Might => "Might (+1 damage)",
+            Improvement::Guard => "Guard (+1 defense)",
+            Improvement::Focus => "Focus (+5 max mana, +1 spell power)",
+        }
+    }
+}
+
+/// Total experience needed to leave `level` for the next one
+pub fn xp_to_next_level(level: i32) -> i32 {
+    level * XP_PER_LEVEL
+}
+
+/// Gives `killer` the experience `victim` is worth, levelling them up if it's enough. Only the
+/// player earns any, monsters killing each other stay at the level they were spawned with.
+pub fn award_kill(ecs: &World, killer: Entity, victim: Entity) {
+    if killer != *ecs.fetch::<Entity>() {
+        return;
+    }
+    let mut experience = ecs.write_storage::<Experience>();
+    let victim_level = experience.get(victim).map_or(1, |xp| xp.level);
+
+    let killer_xp = match experience.get_mut(killer) {
+        Some(xp) => xp,
+        None => return,
+    };
+    killer_xp.xp += victim_level * KILL_XP_PER_LEVEL;
+
+    let (fitness, intelligence) =
+        ecs.read_storage::<Attributes>()
+            .get(killer)
+            .map_or((0, 0), |attributes| {
+                (
+                    attribute_bonus(attributes.fitness),
+                    attribute_bonus(attributes.intelligence),
+                )
+            });
+    let mut combat_stats = ecs.write_storage::<CombatStats>();
+    let mut magic_stats = ecs.write_storage::<MagicStats>();
+    while killer_xp.xp >= xp_to_next_level(killer_xp.level) {
+        killer_xp.xp -= xp_to_next_level(killer_xp.level);
+        killer_xp.level += 1;
+        killer_xp.improvements += 1;
+
+        if let Some(stats) = combat_stats.get_mut(killer) {
+            stats.max_hp += i32::max(1, HP_PER_LEVEL + fitness);
+            stats.hp = stats.max_hp;
+            stats.power += POWER_PER_LEVEL;
+        }
+        if let Some(stats) = magic_stats.get_mut(killer) {
+            stats.max_mana += i32::max(0, MANA_PER_LEVEL + intelligence);
+            stats.mana = stats.max_mana;
+        }
+
+        ecs.write_resource::<GameLog>().entries.push(format!(
+            "Congratulations, you are now level {}!",
+            killer_xp.level
+        ));
+    }
+}
+
+/// Spends one of `entity`'s pending level up improvements on `improvement`
+pub fn apply_improvement(ecs: &World, entity: Entity, improvement: Improvement) {
+    let mut experience = ecs.write_storage::<Experience>();
+    match experience.get_mut(entity) {
+        Some(xp) if xp.improvements > 0 => xp.improvements -= 1,
+        _ => return,
+    }
+
+    let mut combat_stats = ecs.write_storage::<CombatStats>();
+    let mut magic_stats = ecs.write_storage::<MagicStats>();
+    match improvement {
+        Improvement::Toughness => {
+            if let Some(stats) = combat_stats.get_mut(entity) {
+                stats.max_hp += 5;
+                stats.hp += 5;
+            }
+        }
+        Improvement::Might => {
+            if let Some(stats) = combat_stats.get_mut(entity) {
+                stats.power += 1;
+            }
+        }
+        Improvement::Guard => {
+            if let Some(stats) = combat_stats.get_mut(entity) {
+                stats.defense += 1;
+            }
+        }
+        Improvement::Focus => {
+            if let Some(stats) = magic_stats.get_mut(entity) {
+                stats.max_mana += 5;
+                stats.mana += 5;
+                stats.power += 1;
+            }
+        }
+    }
+}
+
+/// Whether `entity` levelled up and still has improvements to pick
+pub fn has_pending_improvements(ecs: &World, entity: Entity) -> bool {
+    ecs.read_storage::<Experience>()
+        .get(entity)
+        .is_some_and(|xp| xp.improvements > 0)
+}
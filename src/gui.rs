@@ -1,8 +1,13 @@
-use crate::{rex_assets::RexAssets, Hidden};
+use crate::{
+    camera, dungeon::MasterDungeonMap, experience::xp_to_next_level, raws::find_spell_entity,
+    rex_assets::RexAssets, run_seed::RunSeed, shop, spell_system::can_afford, Ammunition, Burden,
+    Enchantment, Encumbrance, Hidden, Improvement, KnownSpells, SpellTemplate, StatusEffectKind,
+    StatusEffects,
+};
 
 use super::{
-    CombatStats, Equipped, GameLog, HungerClock, HungerState, InBackpack, MagicStats, Map, Name,
-    Player, Position, RunState, State, Viewshed,
+    CombatStats, EquipmentSlot, Equippable, Equipped, Experience, GameLog, Gold, HungerClock,
+    HungerState, InBackpack, MagicStats, Map, Name, Player, Position, RunState, State, Viewshed,
 };
 use rltk::{Point, Rltk, VirtualKeyCode, RGB};
 use specs::prelude::*;
@@ -24,7 +29,11 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     let hunger_status = ecs.read_storage::<HungerClock>();
 
     let map = ecs.fetch::<Map>();
-    let depth = format!("Depth: {}", map.depth);
+    let depth = if map.depth == 0 {
+        "Town".to_string()
+    } else {
+        format!("Depth: {}", map.depth)
+    };
     ctx.print_color(
         2,
         43,
@@ -76,25 +85,73 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         );
     }
 
-    // Hunger
+    // Status effects, each kind once with the longest time it has left
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let mut x = 2;
+    for (_player, status) in (&player, &statuses).join() {
+        let mut shown: Vec<StatusEffectKind> = Vec::new();
+        for effect in status.effects.iter() {
+            if shown.contains(&effect.kind) {
+                continue;
+            }
+            shown.push(effect.kind);
+
+            let turns = status
+                .effects
+                .iter()
+                .filter(|other| other.kind == effect.kind)
+                .map(|other| other.turns)
+                .max()
+                .unwrap_or(effect.turns);
+            let label = format!("{} ({})", effect.kind.adjective(), turns);
+            ctx.print_color(
+                x,
+                49,
+                status_color(effect.kind),
+                RGB::named(rltk::BLACK),
+                &label,
+            );
+            x += label.len() as i32 + 1;
+        }
+    }
+
+    // Burden, after the status effects
+    let encumbrances = ecs.read_storage::<Encumbrance>();
+    for (_player, encumbrance) in (&player, &encumbrances).join() {
+        let label = match encumbrance.burden() {
+            Burden::Unburdened => continue,
+            Burden::Burdened => "Burdened",
+            Burden::Strained => "Strained",
+        };
+        ctx.print_color(
+            x,
+            49,
+            burden_color(encumbrance.burden()),
+            RGB::named(rltk::BLACK),
+            label,
+        );
+        x += label.len() as i32 + 1;
+    }
+
+    // Hunger, after the status effects and burden
     for (_player, hunger) in (&player, &hunger_status).join() {
         match hunger.state {
             HungerState::WellFed => ctx.print_color(
-                35,
+                x,
                 49,
                 RGB::named(rltk::GREEN),
                 RGB::named(rltk::BLACK),
                 "Well Fed",
             ),
             HungerState::Hungry => ctx.print_color(
-                35,
+                x,
                 49,
                 RGB::named(rltk::ORANGE),
                 RGB::named(rltk::BLACK),
                 "Hungry",
             ),
             HungerState::Starving => ctx.print_color(
-                35,
+                x,
                 49,
                 RGB::named(rltk::RED),
                 RGB::named(rltk::BLACK),
@@ -104,6 +161,39 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         }
     }
 
+    // Seed, and the player's level and progress towards the next one (then their gold) left of it
+    let seed = format!("Seed: {}", ecs.fetch::<RunSeed>().seed);
+    let seed_x = 78 - seed.len() as i32;
+    ctx.print_color(
+        seed_x,
+        49,
+        RGB::named(rltk::GREY),
+        RGB::named(rltk::BLACK),
+        &seed,
+    );
+
+    let experience = ecs.read_storage::<Experience>();
+    let gold = ecs.read_storage::<Gold>();
+    for (_player, xp, gold) in (&player, &experience, &gold).join() {
+        let level = format!("Lvl {} {}/{}", xp.level, xp.xp, xp_to_next_level(xp.level));
+        let level_x = seed_x - 1 - level.len() as i32;
+        ctx.print_color(
+            level_x,
+            49,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+            &level,
+        );
+        let gold = format!("{} gp", gold.amount);
+        ctx.print_color(
+            level_x - 1 - gold.len() as i32,
+            49,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            &gold,
+        );
+    }
+
     // Log
     let log = ecs.fetch::<GameLog>();
 
@@ -122,22 +212,62 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     draw_tooltips(ecs, ctx);
 }
 
+fn status_color(kind: StatusEffectKind) -> RGB {
+    match kind {
+        StatusEffectKind::Poison => RGB::named(rltk::GREEN),
+        StatusEffectKind::Regeneration => RGB::named(rltk::PINK),
+        StatusEffectKind::Haste => RGB::named(rltk::CYAN),
+        StatusEffectKind::Slow | StatusEffectKind::Paralysis => RGB::named(rltk::ORANGE),
+        StatusEffectKind::Blindness => RGB::named(rltk::GREY),
+        StatusEffectKind::Confusion => RGB::named(rltk::MAGENTA),
+    }
+}
+
+fn burden_color(burden: Burden) -> RGB {
+    match burden {
+        Burden::Unburdened => RGB::named(rltk::WHITE),
+        Burden::Burdened => RGB::named(rltk::ORANGE),
+        Burden::Strained => RGB::named(rltk::RED),
+    }
+}
+
 fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
+    let dungeon_master = ecs.fetch::<MasterDungeonMap>();
+    let enchantments = ecs.read_storage::<Enchantment>();
     let positions = ecs.read_storage::<Position>();
     let hidden = ecs.read_storage::<Hidden>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let entities = ecs.entities();
 
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height {
-        return;
-    }
+    let mouse_tile = match camera::screen_to_map(ecs, Point::new(mouse_pos.0, mouse_pos.1)) {
+        Some(tile) => tile,
+        None => return,
+    };
 
     let mut tooltip: Vec<String> = Vec::new();
-    for (name, position, _hidden) in (&names, &positions, !&hidden).join() {
+    for (entity, name, position, _hidden) in (&entities, &names, &positions, !&hidden).join() {
         let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
-            tooltip.push(name.name.to_string());
+        if position.x == mouse_tile.x && position.y == mouse_tile.y && map.visible_tiles[idx] {
+            match statuses.get(entity) {
+                Some(status) => {
+                    let mut kinds: Vec<StatusEffectKind> =
+                        status.effects.iter().map(|effect| effect.kind).collect();
+                    kinds.sort();
+                    kinds.dedup();
+                    let adjectives: Vec<&str> = kinds.iter().map(|kind| kind.adjective()).collect();
+                    tooltip.push(format!(
+                        "{} ({})",
+                        dungeon_master.item_name(&name.name, enchantments.get(entity)),
+                        adjectives.join(", ")
+                    ));
+                }
+                None => {
+                    tooltip.push(dungeon_master.item_name(&name.name, enchantments.get(entity)))
+                }
+            }
         }
     }
 
@@ -227,10 +357,21 @@ pub enum ItemMenuResult {
     Selected,
 }
 
+/// Menu line for an item, with how many are left in ammunition stacks
+fn item_label(name: &str, ammunition: Option<&Ammunition>) -> String {
+    match ammunition {
+        Some(ammo) => format!("{} ({})", name, ammo.count),
+        None => name.to_string(),
+    }
+}
+
 pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
+    let dungeon_master = gs.ecs.fetch::<MasterDungeonMap>();
+    let enchantments = gs.ecs.read_storage::<Enchantment>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
+    let ammunition = gs.ecs.read_storage::<Ammunition>();
     let entities = gs.ecs.entities();
 
     let inventory = (&backpack, &names)
@@ -254,6 +395,16 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
         RGB::named(rltk::BLACK),
         "Inventory",
     );
+    if let Some(encumbrance) = gs.ecs.read_storage::<Encumbrance>().get(*player_entity) {
+        let load = format!("{:.1}/{:.1} lbs", encumbrance.carried, encumbrance.capacity);
+        ctx.print_color(
+            45 - load.len() as i32,
+            y - 2,
+            burden_color(encumbrance.burden()),
+            RGB::named(rltk::BLACK),
+            &load,
+        );
+    }
     ctx.print_color(
         18,
         y + count as i32 + 1,
@@ -290,7 +441,14 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(
+            21,
+            y,
+            item_label(
+                &dungeon_master.item_name(&name.name, enchantments.get(entity)),
+                ammunition.get(entity),
+            ),
+        );
         equippable.push(entity);
         y += 1;
         j += 1;
@@ -315,7 +473,10 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
 pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
+    let dungeon_master = gs.ecs.fetch::<MasterDungeonMap>();
+    let enchantments = gs.ecs.read_storage::<Enchantment>();
     let backpack = gs.ecs.read_storage::<InBackpack>();
+    let ammunition = gs.ecs.read_storage::<Ammunition>();
     let entities = gs.ecs.entities();
 
     let inventory = (&backpack, &names)
@@ -375,7 +536,14 @@ pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(
+            21,
+            y,
+            item_label(
+                &dungeon_master.item_name(&name.name, enchantments.get(entity)),
+                ammunition.get(entity),
+            ),
+        );
         equippable.push(entity);
         y += 1;
         j += 1;
@@ -420,7 +588,9 @@ pub fn ranged_target(
         for idx in visible.visible_tiles.iter() {
             let distance = rltk::DistanceAlg::Pythagoras.distance2d(*player_pos, *idx);
             if distance <= range as f32 {
-                ctx.set_bg(idx.x, idx.y, RGB::named(rltk::BLUE));
+                if let Some(screen) = camera::map_to_screen(&gs.ecs, *idx) {
+                    ctx.set_bg(screen.x, screen.y, RGB::named(rltk::BLUE));
+                }
                 available_cells.push(idx);
             }
         }
@@ -430,16 +600,12 @@ pub fn ranged_target(
 
     // Draw mouse cursor
     let mouse_pos = ctx.mouse_pos();
-    let valid_target = available_cells
-        .iter()
-        .any(|&idx| idx.x == mouse_pos.0 && idx.y == mouse_pos.1);
+    let mouse_tile = camera::screen_to_map(&gs.ecs, Point::new(mouse_pos.0, mouse_pos.1));
+    let valid_target = available_cells.iter().any(|&idx| Some(*idx) == mouse_tile);
     if valid_target {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::CYAN));
         if ctx.left_click {
-            return (
-                ItemMenuResult::Selected,
-                Some(Point::new(mouse_pos.0, mouse_pos.1)),
-            );
+            return (ItemMenuResult::Selected, mouse_tile);
         }
     } else {
         ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::CYAN));
@@ -455,9 +621,10 @@ pub fn ranged_target(
  *  MAIN MENU
  */
 
-#[derive(PartialEq, Copy, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum MainMenuSelection {
     NewGame,
+    ChangeSeed,
     SaveGame,
     LoadGame,
     Quit,
@@ -481,7 +648,7 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
         24,
         21,
         31,
-        10,
+        12,
         RGB::named(rltk::WHEAT),
         RGB::named(rltk::BLACK),
     );
@@ -493,6 +660,13 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
         "Rust Roguelike Tutorial",
     );
 
+    ctx.print_color_centered(
+        17,
+        RGB::named(rltk::GREY),
+        RGB::named(rltk::BLACK),
+        format!("Seed: {}", gs.ecs.fetch::<RunSeed>().seed),
+    );
+
     if let RunState::MainMenu {
         menu_selection: selection,
     } = *runstate
@@ -516,6 +690,23 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
         }
         y += 2;
 
+        if selection == MainMenuSelection::ChangeSeed {
+            ctx.print_color_centered(
+                y,
+                RGB::named(rltk::MAGENTA),
+                RGB::named(rltk::BLACK),
+                "Change Seed",
+            );
+        } else {
+            ctx.print_color_centered(
+                y,
+                RGB::named(rltk::WHITE),
+                RGB::named(rltk::BLACK),
+                "Change Seed",
+            );
+        }
+        y += 2;
+
         if selection == MainMenuSelection::SaveGame {
             ctx.print_color_centered(
                 y,
@@ -578,7 +769,8 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
                 let mut newselection;
                 match selection {
                     MainMenuSelection::NewGame => newselection = MainMenuSelection::Quit,
-                    MainMenuSelection::SaveGame => newselection = MainMenuSelection::NewGame,
+                    MainMenuSelection::ChangeSeed => newselection = MainMenuSelection::NewGame,
+                    MainMenuSelection::SaveGame => newselection = MainMenuSelection::ChangeSeed,
                     MainMenuSelection::LoadGame => newselection = MainMenuSelection::SaveGame,
                     MainMenuSelection::Quit => newselection = MainMenuSelection::LoadGame,
                 }
@@ -594,7 +786,8 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
             Some(VirtualKeyCode::Down) => {
                 let mut newselection;
                 match selection {
-                    MainMenuSelection::NewGame => newselection = MainMenuSelection::SaveGame,
+                    MainMenuSelection::NewGame => newselection = MainMenuSelection::ChangeSeed,
+                    MainMenuSelection::ChangeSeed => newselection = MainMenuSelection::SaveGame,
                     MainMenuSelection::SaveGame => newselection = MainMenuSelection::LoadGame,
                     MainMenuSelection::LoadGame => newselection = MainMenuSelection::Quit,
                     MainMenuSelection::Quit => newselection = MainMenuSelection::NewGame,
@@ -626,10 +819,94 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
 }
 
 /*
- *  LOAD MENU
+ *  SEED ENTRY
  */
 
 #[derive(PartialEq, Copy, Clone)]
+pub enum SeedEntryResult {
+    NoResponse,
+    Cancel,
+    Selected(u64),
+}
+
+pub fn seed_entry(gs: &mut State, ctx: &mut Rltk) -> SeedEntryResult {
+    let assets = gs.ecs.fetch::<RexAssets>();
+    ctx.render_xp_sprite(&assets.menu, 0, 0);
+
+    // Enclose everything in a box
+    ctx.draw_box_double(
+        24,
+        21,
+        31,
+        8,
+        RGB::named(rltk::WHEAT),
+        RGB::named(rltk::BLACK),
+    );
+
+    ctx.print_color_centered(
+        23,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Enter a seed",
+    );
+    ctx.print_color_centered(
+        25,
+        RGB::named(rltk::MAGENTA),
+        RGB::named(rltk::BLACK),
+        format!("{}_", gs.seed_input),
+    );
+    ctx.print_color_centered(
+        27,
+        RGB::named(rltk::GREY),
+        RGB::named(rltk::BLACK),
+        "ENTER to accept, ESC to cancel",
+    );
+
+    match ctx.key {
+        None => SeedEntryResult::NoResponse,
+        Some(VirtualKeyCode::Escape) => SeedEntryResult::Cancel,
+        Some(VirtualKeyCode::Back) => {
+            gs.seed_input.pop();
+            SeedEntryResult::NoResponse
+        }
+        Some(VirtualKeyCode::Return) => match gs.seed_input.parse::<u64>() {
+            Ok(seed) => SeedEntryResult::Selected(seed),
+            Err(_) => SeedEntryResult::NoResponse,
+        },
+        Some(key) => {
+            if let Some(digit) = key_to_digit(key) {
+                // Don't let the typed number overflow a u64
+                let candidate = format!("{}{}", gs.seed_input, digit);
+                if candidate.parse::<u64>().is_ok() {
+                    gs.seed_input = candidate;
+                }
+            }
+            SeedEntryResult::NoResponse
+        }
+    }
+}
+
+fn key_to_digit(key: VirtualKeyCode) -> Option<char> {
+    match key {
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some('0'),
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some('1'),
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some('2'),
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some('3'),
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some('4'),
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some('5'),
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some('6'),
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some('7'),
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some('8'),
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some('9'),
+        _ => None,
+    }
+}
+
+/*
+ *  LOAD MENU
+ */
+
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum LoadMenuSelection {
     Quit,
     Selecting(i32),
@@ -727,6 +1004,8 @@ pub fn load_menu(gs: &mut State, ctx: &mut Rltk) -> LoadMenuResult {
 pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let names = gs.ecs.read_storage::<Name>();
+    let dungeon_master = gs.ecs.fetch::<MasterDungeonMap>();
+    let enchantments = gs.ecs.read_storage::<Enchantment>();
     let equipped = gs.ecs.read_storage::<Equipped>();
     let entities = gs.ecs.entities();
 
@@ -787,7 +1066,11 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Opti
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
+        ctx.print(
+            21,
+            y,
+            dungeon_master.item_name(&name.name, enchantments.get(entity)),
+        );
         removable.push(entity);
         y += 1;
         j += 1;
@@ -809,6 +1092,424 @@ pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Opti
     }
 }
 
+/// Lists every equipment slot with what the player wears in it. Picking a filled slot takes
+/// its item off.
+pub fn show_equipment(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let names = gs.ecs.read_storage::<Name>();
+    let dungeon_master = gs.ecs.fetch::<MasterDungeonMap>();
+    let enchantments = gs.ecs.read_storage::<Enchantment>();
+    let equipped = gs.ecs.read_storage::<Equipped>();
+    let equippable = gs.ecs.read_storage::<Equippable>();
+    let entities = gs.ecs.entities();
+
+    let worn: Vec<(Entity, &Equipped, &Equippable, &Name)> =
+        (&entities, &equipped, &equippable, &names)
+            .join()
+            .filter(|item| item.1.owner == *player_entity)
+            .collect();
+    let count = EquipmentSlot::ALL.len();
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        40,
+        (count + 3) as i32,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Equipment",
+    );
+    ctx.print_color(
+        18,
+        y + count as i32 + 1,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "ESCAPE to cancel",
+    );
+
+    let mut in_slot: Vec<Option<Entity>> = Vec::new();
+    for (y, (j, slot)) in (y..).zip(EquipmentSlot::ALL.iter().enumerate()) {
+        ctx.set(
+            17,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437('('),
+        );
+        ctx.set(
+            18,
+            y,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            97 + j as rltk::FontCharType,
+        );
+        ctx.set(
+            19,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437(')'),
+        );
+        ctx.print_color(
+            21,
+            y,
+            RGB::named(rltk::GREY),
+            RGB::named(rltk::BLACK),
+            slot.name(),
+        );
+
+        // The item meant for the slot, or else whatever else takes it up (a two-handed weapon)
+        let item = worn
+            .iter()
+            .find(|(_entity, equipped_by, _equippable, _name)| equipped_by.slot == *slot)
+            .or_else(|| {
+                worn.iter()
+                    .find(|(_entity, _equipped_by, equippable, _name)| {
+                        equippable.slots().contains(slot)
+                    })
+            });
+        match item {
+            Some((entity, equipped_by, _equippable, name)) if equipped_by.slot == *slot => {
+                ctx.print(
+                    29,
+                    y,
+                    dungeon_master.item_name(&name.name, enchantments.get(*entity)),
+                );
+                in_slot.push(Some(*entity));
+            }
+            Some((entity, _equipped_by, _equippable, name)) => {
+                ctx.print_color(
+                    29,
+                    y,
+                    RGB::named(rltk::GREY),
+                    RGB::named(rltk::BLACK),
+                    format!(
+                        "({})",
+                        dungeon_master.item_name(&name.name, enchantments.get(*entity))
+                    ),
+                );
+                in_slot.push(Some(*entity));
+            }
+            None => {
+                ctx.print_color(29, y, RGB::named(rltk::GREY), RGB::named(rltk::BLACK), "-");
+                in_slot.push(None);
+            }
+        }
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(VirtualKeyCode::Escape) => (ItemMenuResult::Cancel, None),
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection > -1 && selection < count as i32 {
+                if let Some(item) = in_slot[selection as usize] {
+                    return (ItemMenuResult::Selected, Some(item));
+                }
+            }
+            (ItemMenuResult::NoResponse, None)
+        }
+    }
+}
+
+/// Menu of the spells the player knows, with their mana cost. Those the player can't afford
+/// right now are greyed out and can't be picked.
+pub fn show_spells(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let spells: Vec<(Entity, String, i32)> = {
+        let known_spells = gs.ecs.read_storage::<KnownSpells>();
+        let templates = gs.ecs.read_storage::<SpellTemplate>();
+        known_spells
+            .get(player_entity)
+            .map_or(&[] as &[String], |known| &known.spells)
+            .iter()
+            .filter_map(|name| {
+                let spell = find_spell_entity(&gs.ecs, name)?;
+                let cost = templates.get(spell)?.mana_cost;
+                Some((spell, name.clone(), cost))
+            })
+            .collect()
+    };
+    let count = spells.len();
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        31,
+        (count + 3) as i32,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Spells",
+    );
+    ctx.print_color(
+        18,
+        y + count as i32 + 1,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "ESCAPE to cancel",
+    );
+
+    for (j, (spell, name, cost)) in spells.iter().enumerate() {
+        let y = y + j as i32;
+        let fg = if can_afford(&gs.ecs, player_entity, *spell) {
+            RGB::named(rltk::WHITE)
+        } else {
+            RGB::named(rltk::GREY)
+        };
+        ctx.set(17, y, fg, RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(
+            18,
+            y,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            97 + j as rltk::FontCharType,
+        );
+        ctx.set(19, y, fg, RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+        ctx.print_color(
+            21,
+            y,
+            fg,
+            RGB::named(rltk::BLACK),
+            format!("{} ({} mana)", name, cost),
+        );
+    }
+
+    match ctx.key {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(VirtualKeyCode::Escape) => (ItemMenuResult::Cancel, None),
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection > -1 && selection < count as i32 {
+                let spell = spells[selection as usize].0;
+                if can_afford(&gs.ecs, player_entity, spell) {
+                    return (ItemMenuResult::Selected, Some(spell));
+                }
+            }
+            (ItemMenuResult::NoResponse, None)
+        }
+    }
+}
+
+/// Which side of the counter the vendor screen shows
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum VendorMode {
+    Buy,
+    Sell,
+}
+
+pub enum VendorResult {
+    NoResponse,
+    Cancel,
+    SwitchMode,
+    Buy(Entity),
+    Sell(Entity),
+}
+
+/// Trading screen for `vendor`: their stock with what they ask for it when buying, the player's
+/// backpack with what they pay for it when selling
+pub fn show_vendor(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    vendor: Entity,
+    mode: VendorMode,
+) -> VendorResult {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let (owner, title) = match mode {
+        VendorMode::Buy => (vendor, "Buy (TAB to sell)"),
+        VendorMode::Sell => (player_entity, "Sell (TAB to buy)"),
+    };
+    let wares: Vec<(Entity, String, Option<i32>)> = {
+        let names = gs.ecs.read_storage::<Name>();
+        let dungeon_master = gs.ecs.fetch::<MasterDungeonMap>();
+        let enchantments = gs.ecs.read_storage::<Enchantment>();
+        let backpack = gs.ecs.read_storage::<InBackpack>();
+        let ammunition = gs.ecs.read_storage::<Ammunition>();
+        (&gs.ecs.entities(), &backpack, &names)
+            .join()
+            .filter(|(_entity, pack, _name)| pack.owner == owner)
+            .map(|(entity, _pack, name)| {
+                let label = item_label(
+                    &dungeon_master.item_name(&name.name, enchantments.get(entity)),
+                    ammunition.get(entity),
+                );
+                let price = match mode {
+                    VendorMode::Buy => shop::buy_price(&gs.ecs, entity),
+                    VendorMode::Sell => shop::sell_price(&gs.ecs, entity),
+                };
+                (entity, label, price)
+            })
+            .collect()
+    };
+    let count = wares.len();
+    let gold = shop::gold(&gs.ecs, player_entity);
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        51,
+        (count + 3) as i32,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        title,
+    );
+    let purse = format!("{} gp", gold);
+    ctx.print_color(
+        65 - purse.len() as i32,
+        y - 2,
+        RGB::named(rltk::GOLD),
+        RGB::named(rltk::BLACK),
+        &purse,
+    );
+    ctx.print_color(
+        18,
+        y + count as i32 + 1,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "ESCAPE to leave",
+    );
+
+    for (j, (_item, label, price)) in wares.iter().enumerate() {
+        let y = y + j as i32;
+        // Greyed out when the player can't afford it, or the vendor doesn't want it
+        let fg = match (mode, price) {
+            (VendorMode::Buy, Some(price)) if *price <= gold => RGB::named(rltk::WHITE),
+            (VendorMode::Sell, Some(_)) => RGB::named(rltk::WHITE),
+            _ => RGB::named(rltk::GREY),
+        };
+        ctx.set(17, y, fg, RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(
+            18,
+            y,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            97 + j as rltk::FontCharType,
+        );
+        ctx.set(19, y, fg, RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+        ctx.print_color(21, y, fg, RGB::named(rltk::BLACK), label);
+        if let Some(price) = price {
+            let price = format!("{} gp", price);
+            ctx.print_color(
+                65 - price.len() as i32,
+                y,
+                fg,
+                RGB::named(rltk::BLACK),
+                &price,
+            );
+        }
+    }
+
+    match ctx.key {
+        None => VendorResult::NoResponse,
+        Some(VirtualKeyCode::Escape) => VendorResult::Cancel,
+        Some(VirtualKeyCode::Tab) => VendorResult::SwitchMode,
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection > -1 && selection < count as i32 {
+                let item = wares[selection as usize].0;
+                return match mode {
+                    VendorMode::Buy => VendorResult::Buy(item),
+                    VendorMode::Sell => VendorResult::Sell(item),
+                };
+            }
+            VendorResult::NoResponse
+        }
+    }
+}
+
+pub enum LevelUpResult {
+    NoSelection,
+    Selected(Improvement),
+}
+
+/// Lets the player pick what they get out of a level up. There's no backing out of it.
+pub fn level_up_menu(gs: &mut State, ctx: &mut Rltk) -> LevelUpResult {
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let experience = gs.ecs.read_storage::<Experience>();
+    let level = experience.get(*player_entity).map_or(1, |xp| xp.level);
+    let count = Improvement::ALL.len();
+
+    let y = (25 - (count / 2)) as i32;
+    ctx.draw_box(
+        15,
+        y - 2,
+        45,
+        (count + 3) as i32,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        y - 2,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        format!("Welcome to level {}!", level),
+    );
+    ctx.print_color(
+        18,
+        y + count as i32 + 1,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Pick an improvement",
+    );
+
+    for (y, (j, improvement)) in (y..).zip(Improvement::ALL.iter().enumerate()) {
+        ctx.set(
+            17,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437('('),
+        );
+        ctx.set(
+            18,
+            y,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            97 + j as rltk::FontCharType,
+        );
+        ctx.set(
+            19,
+            y,
+            RGB::named(rltk::WHITE),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437(')'),
+        );
+        ctx.print(21, y, improvement.description());
+    }
+
+    match ctx.key {
+        None => LevelUpResult::NoSelection,
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection > -1 && selection < count as i32 {
+                return LevelUpResult::Selected(Improvement::ALL[selection as usize]);
+            }
+            LevelUpResult::NoSelection
+        }
+    }
+}
+
 pub enum GameOverResult {
     NoSelection,
     QuitToMenu,
@@ -0,0 +1,101 @@
+use specs::prelude::*;
+
+use super::{damage_system, particle_system, perform_command, PlayerCommand, RunState, State};
+use crate::run_seed::RunSeed;
+
+/// Runs the game without an rltk window: the player acts through [PlayerCommand]s and every
+/// other state (monster turns, level changes, map generation) is resolved right away.
+///
+/// Meant for integration tests and bots, a new game is already waiting for input once created.
+pub struct HeadlessGame {
+    state: State,
+}
+
+impl HeadlessGame {
+    pub fn new(seed: u64) -> HeadlessGame {
+        let mut state = State::new(RunSeed::new(seed));
+        let runstate = state.start_new_game();
+
+        let mut game = HeadlessGame { state };
+        game.set_run_state(runstate);
+        game.advance();
+
+        game
+    }
+
+    pub fn ecs(&self) -> &World {
+        &self.state.ecs
+    }
+
+    pub fn ecs_mut(&mut self) -> &mut World {
+        &mut self.state.ecs
+    }
+
+    pub fn run_state(&self) -> RunState {
+        *self.state.ecs.fetch::<RunState>()
+    }
+
+    /// Plays `command` as the player's turn and runs the game until it needs input again.
+    ///
+    /// Commands are ignored unless the game is waiting for input (e.g. after a game over), the
+    /// returned state tells which one it is. After a level up only
+    /// [PlayerCommand::ChooseImprovement] is taken. Doing anything but trading walks away from a
+    /// vendor.
+    pub fn step(&mut self, command: PlayerCommand) -> RunState {
+        // Level ups have to be dealt with before anything else
+        let accepted = match self.run_state() {
+            RunState::AwaitingInput | RunState::ShowVendor { .. } => true,
+            RunState::LevelUp => matches!(command, PlayerCommand::ChooseImprovement { .. }),
+            _ => false,
+        };
+        if !accepted {
+            return self.run_state();
+        }
+
+        let newrunstate = perform_command(&mut self.state.ecs, command);
+        self.set_run_state(newrunstate);
+        self.finish_frame();
+
+        self.advance()
+    }
+
+    /// Resolves states until the player has to decide something (or the game is over)
+    fn advance(&mut self) -> RunState {
+        loop {
+            let runstate = self.run_state();
+            let newrunstate = match runstate {
+                RunState::AwaitingInput
+                | RunState::LevelUp
+                | RunState::ShowVendor { .. }
+                | RunState::GameOver => return runstate,
+                // There's nobody to watch the visualizer, so skip straight past it
+                RunState::MapGeneration => self
+                    .state
+                    .mapgen_next_state
+                    .expect("map generation without a next state"),
+                RunState::PreRun
+                | RunState::PlayerTurn
+                | RunState::Ticking
+                | RunState::NextLevel
+                | RunState::PreviousLevel
+                | RunState::MagicMapReveal { .. } => self.state.advance_simulation(runstate),
+                _ => panic!("the headless runner can't handle menu states"),
+            };
+
+            self.set_run_state(newrunstate);
+            self.finish_frame();
+        }
+    }
+
+    fn set_run_state(&mut self, runstate: RunState) {
+        let mut runstatewriter = self.state.ecs.write_resource::<RunState>();
+        *runstatewriter = runstate;
+    }
+
+    /// Same clean up the rltk loop does after every tick
+    fn finish_frame(&mut self) {
+        // Nothing renders the particles, so they can go right away
+        particle_system::cull_dead_particles(&mut self.state.ecs, f32::INFINITY);
+        damage_system::delete_the_dead(&mut self.state.ecs);
+    }
+}
@@ -1,4 +1,9 @@
-use super::{gamelog::GameLog, HungerClock, HungerState, RunState, SufferDamage};
+use super::{
+    effects::{EffectQueue, EffectType, Targets},
+    gamelog::GameLog,
+    initiative_system::GameClock,
+    HungerClock, HungerState,
+};
 use specs::prelude::*;
 
 pub struct HungerSystem {}
@@ -9,54 +14,60 @@ impl<'a> System<'a> for HungerSystem {
         Entities<'a>,
         WriteStorage<'a, HungerClock>,
         ReadExpect<'a, Entity>,
-        ReadExpect<'a, RunState>,
-        WriteStorage<'a, SufferDamage>,
+        ReadExpect<'a, GameClock>,
         WriteExpect<'a, GameLog>,
+        WriteExpect<'a, EffectQueue>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut hunger_clock, player, runstate, mut inflict_damage, mut log) = data;
+        let (entities, mut hunger_clock, player, game_clock, mut log, mut effect_queue) = data;
+
+        // Hunger grows with time passing, not with how many actions one gets
+        if !game_clock.ticked {
+            return;
+        }
 
         for (entity, clock) in (&entities, &mut hunger_clock).join() {
             let is_player = entity == *player;
-            match (&*runstate, is_player) {
-                (RunState::PlayerTurn, true) | (RunState::MonsterTurn, false) => {
-                    clock.duration -= 1;
-                    if clock.duration < 1 {
-                        match clock.state {
-                            HungerState::WellFed => {
-                                clock.state = HungerState::Normal;
-                                clock.duration = 200;
-                                if is_player {
-                                    log.entries.push("You are no longer well fed.".to_string());
-                                }
-                            }
-                            HungerState::Normal => {
-                                clock.state = HungerState::Hungry;
-                                clock.duration = 200;
-                                if is_player {
-                                    log.entries.push("You are hungry.".to_string());
-                                }
-                            }
-                            HungerState::Hungry => {
-                                clock.state = HungerState::Starving;
-                                clock.duration = 200;
-                                if is_player {
-                                    log.entries.push(
-                                        "You are starving! Eat something, quick!".to_string(),
-                                    );
-                                }
-                            }
-                            HungerState::Starving => {
-                                if is_player {
-                                    log.entries.push("Your hunger pangs are getting painful! You suffer 1 hp damage.".to_string());
-                                }
-                                SufferDamage::new_damage(&mut inflict_damage, entity, 1);
-                            }
+            clock.duration -= 1;
+            if clock.duration < 1 {
+                match clock.state {
+                    HungerState::WellFed => {
+                        clock.state = HungerState::Normal;
+                        clock.duration = 200;
+                        if is_player {
+                            log.entries.push("You are no longer well fed.".to_string());
+                        }
+                    }
+                    HungerState::Normal => {
+                        clock.state = HungerState::Hungry;
+                        clock.duration = 200;
+                        if is_player {
+                            log.entries.push("You are hungry.".to_string());
+                        }
+                    }
+                    HungerState::Hungry => {
+                        clock.state = HungerState::Starving;
+                        clock.duration = 200;
+                        if is_player {
+                            log.entries
+                                .push("You are starving! Eat something, quick!".to_string());
+                        }
+                    }
+                    HungerState::Starving => {
+                        if is_player {
+                            log.entries.push(
+                                "Your hunger pangs are getting painful! You suffer 1 hp damage."
+                                    .to_string(),
+                            );
                         }
+                        effect_queue.add(
+                            None,
+                            EffectType::Damage { amount: 1 },
+                            Targets::Single { target: entity },
+                        );
                     }
                 }
-                _ => {}
             }
         }
     }
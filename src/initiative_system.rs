@@ -0,0 +1,163 @@
+use std::ops::Deref;
+
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use specs::storage::MaskedStorage;
+
+use super::{
+    ActionCost, Burden, Encumbrance, Equipped, Initiative, MeleeWeapon, MyTurn, Position,
+    RangedWeapon, RunState, StatusEffectKind, StatusEffects,
+};
+
+/// Initiative a normal speed entity regains every tick
+pub const NORMAL_SPEED: i32 = 100;
+/// Initiative a standard action (a step, a punch, waiting around) takes
+pub const ACTION_COST: i32 = 100;
+
+/// Game time, counted in ticks. A tick is as long as a standard action at normal speed.
+#[derive(Default, Serialize, Deserialize, Clone)]
+pub struct GameClock {
+    pub ticks: u64,
+    /// Whether the last run of the initiative system moved time forward
+    #[serde(skip)]
+    pub ticked: bool,
+}
+
+/// Decides who acts next. When nobody is ready it moves the game clock forward one tick, then
+/// hands [MyTurn] to everyone whose initiative ran out.
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Initiative>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, MyTurn>,
+        ReadStorage<'a, StatusEffects>,
+        WriteExpect<'a, GameClock>,
+        ReadExpect<'a, RunState>,
+        ReadStorage<'a, Encumbrance>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut initiatives,
+            positions,
+            mut turns,
+            statuses,
+            mut clock,
+            runstate,
+            encumbrances,
+        ) = data;
+
+        clock.ticked = false;
+        if *runstate != RunState::Ticking {
+            return;
+        }
+
+        // Turns only last for the run they were given in
+        turns.clear();
+
+        // Entities on other levels have no position and wait for the player to come back
+        let anyone_ready = (&initiatives, &positions)
+            .join()
+            .any(|(initiative, _pos)| initiative.current <= 0);
+
+        if !anyone_ready {
+            clock.ticks += 1;
+            clock.ticked = true;
+
+            for (entity, initiative, _pos) in (&entities, &mut initiatives, &positions).join() {
+                initiative.current -=
+                    effective_speed(initiative, statuses.get(entity), encumbrances.get(entity));
+            }
+        }
+
+        for (entity, initiative, _pos) in (&entities, &mut initiatives, &positions).join() {
+            if initiative.current > 0 {
+                continue;
+            }
+
+            // Paralysed entities let their turns go by
+            let paralysed = statuses
+                .get(entity)
+                .is_some_and(|status| status.has(StatusEffectKind::Paralysis));
+            if paralysed {
+                initiative.current += ACTION_COST;
+            } else {
+                turns
+                    .insert(entity, MyTurn {})
+                    .expect("Unable to insert turn");
+            }
+        }
+    }
+}
+
+/// Initiative regained every tick once haste, slowness and encumbrance are accounted for
+fn effective_speed(
+    initiative: &Initiative,
+    statuses: Option<&StatusEffects>,
+    encumbrance: Option<&Encumbrance>,
+) -> i32 {
+    let speed = match statuses {
+        Some(status) if status.has(StatusEffectKind::Haste) => initiative.speed * 2,
+        Some(status) if status.has(StatusEffectKind::Slow) => initiative.speed / 2,
+        _ => initiative.speed,
+    };
+    match encumbrance.map(|e| e.burden()) {
+        Some(Burden::Burdened) => speed * 3 / 4,
+        Some(Burden::Strained) => speed / 2,
+        _ => speed,
+    }
+}
+
+/// Initiative `attacker` spends on a melee attack, which depends on the weapon they wield
+pub fn attack_cost(ecs: &World, attacker: Entity) -> i32 {
+    weapon_cost(
+        attacker,
+        &ecs.read_storage::<Equipped>(),
+        &ecs.read_storage::<MeleeWeapon>(),
+        &ecs.read_storage::<ActionCost>(),
+    )
+}
+
+/// Initiative `shooter` spends on a shot, which depends on the launcher they have equipped
+pub fn shot_cost(ecs: &World, shooter: Entity) -> i32 {
+    weapon_cost(
+        shooter,
+        &ecs.read_storage::<Equipped>(),
+        &ecs.read_storage::<RangedWeapon>(),
+        &ecs.read_storage::<ActionCost>(),
+    )
+}
+
+/// Initiative `owner` spends attacking with their equipped weapon of the kind `weapons` holds, a
+/// standard action without one. For systems, which have the storages at hand rather than the world.
+pub fn weapon_cost<W, E, K, C>(
+    owner: Entity,
+    equipped: &Storage<Equipped, E>,
+    weapons: &Storage<W, K>,
+    costs: &Storage<ActionCost, C>,
+) -> i32
+where
+    W: Component,
+    E: Deref<Target = MaskedStorage<Equipped>>,
+    K: Deref<Target = MaskedStorage<W>>,
+    C: Deref<Target = MaskedStorage<ActionCost>>,
+{
+    (equipped, weapons, costs)
+        .join()
+        .find(|(equipped_by, _weapon, _cost)| equipped_by.owner == owner)
+        .map_or(ACTION_COST, |(_equipped_by, _weapon, cost)| cost.cost)
+}
+
+/// Charges `entity` for an action, pushing back when it gets to act again
+pub fn spend_initiative(ecs: &World, entity: Entity, cost: i32) {
+    let mut initiatives = ecs.write_storage::<Initiative>();
+    if let Some(initiative) = initiatives.get_mut(entity) {
+        initiative.current += cost;
+    }
+    ecs.write_storage::<MyTurn>().remove(entity);
+}
@@ -1,11 +1,12 @@
-use crate::{HungerClock, HungerState, MagicMapper, ParticleBuilder, ProvidesFood, RunState};
+use crate::dungeon::MasterDungeonMap;
+use crate::effects::{EffectQueue, EffectType, Targets};
+use crate::encumbrance_system::stack_weight;
 use specs::prelude::*;
 
 use super::{
-    gamelog::GameLog, AreaOfEffect, CombatStats, Confusion, Consumable, Equippable, Equipped,
-    InBackpack, InflictsDamage, MagicStats, Map, Name, Position, ProvidesHealing,
-    ProvidesManaRestore, SufferDamage, WantsToDropItem, WantsToPickupItem, WantsToRemoveItem,
-    WantsToUseItem,
+    gamelog::GameLog, Ammunition, AreaOfEffect, Enchantment, Encumbrance, Equippable, Equipped,
+    InBackpack, Map, Name, Position, WantsToDropItem, WantsToPickupItem, WantsToRemoveItem,
+    WantsToUseItem, Weight,
 };
 pub struct ItemCollectionSystem {}
 
@@ -13,32 +14,72 @@ impl<'a> System<'a> for ItemCollectionSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         ReadExpect<'a, Entity>,
+        ReadExpect<'a, MasterDungeonMap>,
         WriteExpect<'a, GameLog>,
+        Entities<'a>,
         WriteStorage<'a, WantsToPickupItem>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Ammunition>,
+        ReadStorage<'a, Enchantment>,
+        ReadStorage<'a, Weight>,
+        WriteStorage<'a, Encumbrance>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
-            data;
+        let (
+            player_entity,
+            dungeon_master,
+            mut gamelog,
+            entities,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            mut ammunition,
+            enchantments,
+            weights,
+            mut encumbrances,
+        ) = data;
 
         for pickup in wants_pickup.join() {
+            // Nobody picks up more than they can carry
+            let weight = stack_weight(weights.get(pickup.item), ammunition.get(pickup.item));
+            if let Some(encumbrance) = encumbrances.get_mut(pickup.collected_by) {
+                if encumbrance.carried + weight > encumbrance.capacity {
+                    if pickup.collected_by == *player_entity {
+                        gamelog.entries.push(format!(
+                            "You can't carry the {} as well, it's too heavy.",
+                            dungeon_master.item_name(
+                                &names.get(pickup.item).unwrap().name,
+                                enchantments.get(pickup.item)
+                            )
+                        ));
+                    }
+                    continue;
+                }
+                encumbrance.carried += weight;
+            }
+
             positions.remove(pickup.item);
-            backpack
-                .insert(
-                    pickup.item,
-                    InBackpack {
-                        owner: pickup.collected_by,
-                    },
-                )
-                .expect("Unable to insert backpack entry");
+
+            add_to_backpack(
+                &entities,
+                pickup.collected_by,
+                pickup.item,
+                &names,
+                &mut backpack,
+                &mut ammunition,
+            );
 
             if pickup.collected_by == *player_entity {
                 gamelog.entries.push(format!(
                     "You pick up a {}.",
-                    names.get(pickup.item).unwrap().name
+                    dungeon_master.item_name(
+                        &names.get(pickup.item).unwrap().name,
+                        enchantments.get(pickup.item)
+                    )
                 ));
             }
         }
@@ -47,322 +88,179 @@ impl<'a> System<'a> for ItemCollectionSystem {
     }
 }
 
+/// Puts `item` into `owner`'s backpack. Ammunition goes onto a stack of the same thing if there's
+/// one in there already, and the item merged into it is deleted.
+pub fn add_to_backpack(
+    entities: &Entities,
+    owner: Entity,
+    item: Entity,
+    names: &ReadStorage<Name>,
+    backpack: &mut WriteStorage<InBackpack>,
+    ammunition: &mut WriteStorage<Ammunition>,
+) {
+    let item_name = &names.get(item).unwrap().name;
+    let stack = (entities, &*backpack, names, &*ammunition)
+        .join()
+        .find(|(_entity, pack, name, _ammo)| pack.owner == owner && name.name == *item_name)
+        .map(|(entity, _pack, _name, _ammo)| entity);
+    let added = ammunition.get(item).map(|ammo| ammo.count);
+    match (stack, added) {
+        (Some(stack), Some(count)) => {
+            ammunition.get_mut(stack).unwrap().count += count;
+            entities
+                .delete(item)
+                .expect("Unable to delete merged ammunition");
+        }
+        _ => {
+            backpack
+                .insert(item, InBackpack { owner })
+                .expect("Unable to insert backpack entry");
+        }
+    }
+}
+
 pub struct ItemUseSystem {}
 
 impl<'a> System<'a> for ItemUseSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         ReadExpect<'a, Entity>,
-        WriteExpect<'a, Map>,
-        WriteExpect<'a, RunState>,
+        ReadExpect<'a, Map>,
         WriteExpect<'a, GameLog>,
         Entities<'a>,
         WriteStorage<'a, WantsToUseItem>,
         ReadStorage<'a, Name>,
-        ReadStorage<'a, Consumable>,
-        ReadStorage<'a, MagicMapper>,
-        ReadStorage<'a, ProvidesHealing>,
-        ReadStorage<'a, ProvidesManaRestore>,
-        WriteStorage<'a, CombatStats>,
-        WriteStorage<'a, MagicStats>,
-        ReadStorage<'a, InflictsDamage>,
-        WriteStorage<'a, SufferDamage>,
         ReadStorage<'a, AreaOfEffect>,
-        WriteStorage<'a, Confusion>,
         ReadStorage<'a, Equippable>,
         WriteStorage<'a, Equipped>,
         WriteStorage<'a, InBackpack>,
-        WriteExpect<'a, ParticleBuilder>,
-        ReadStorage<'a, Position>,
-        WriteStorage<'a, HungerClock>,
-        ReadStorage<'a, ProvidesFood>,
+        WriteExpect<'a, EffectQueue>,
+        ReadExpect<'a, MasterDungeonMap>,
+        WriteStorage<'a, Enchantment>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             player_entity,
             map,
-            mut runstate,
             mut gamelog,
             entities,
             mut useitem,
             names,
-            consumables,
-            magic_mapper,
-            healing,
-            mana_restoring,
-            mut combat_stats,
-            mut magic_stats,
-            inflict_damage,
-            mut suffer_damage,
             aoe,
-            mut confused,
             equippable,
             mut equipped,
             mut backpack,
-            mut particle_builder,
-            positions,
-            mut hunger_clocks,
-            provides_food,
+            mut effect_queue,
+            dungeon_master,
+            mut enchantments,
         ) = data;
 
-        // Targeting
         for (entity, useitem) in (&entities, &useitem).join() {
-            let mut targets: Vec<Entity> = Vec::new();
-            match useitem.target {
-                None => {
-                    targets.push(*player_entity);
-                }
-                Some(target) => {
-                    let area_effect = aoe.get(useitem.item);
-                    match area_effect {
-                        None => {
-                            // Single target in tile
-                            let idx = map.xy_idx(target.x, target.y);
-                            for mob in map.tile_content[idx].iter() {
-                                targets.push(*mob);
-                            }
-                        }
-                        Some(area_effect) => {
-                            // AoE
-                            let mut blast_tiles =
-                                rltk::field_of_view(target, area_effect.radius, &*map);
-                            blast_tiles.retain(|p| {
-                                p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1
-                            });
-                            for tile_idx in blast_tiles.iter() {
-                                let idx = map.xy_idx(tile_idx.x, tile_idx.y);
-                                for mob in map.tile_content[idx].iter() {
-                                    targets.push(*mob);
-                                }
-
-                                particle_builder.request(
-                                    tile_idx.x,
-                                    tile_idx.y,
-                                    rltk::RGB::named(rltk::ORANGE),
-                                    rltk::RGB::named(rltk::BLACK),
-                                    rltk::to_cp437('░'),
-                                    200.0,
-                                );
-                            }
-                        }
-                    }
-                }
-            }
+            // Targeting
+            let targets = match useitem.target {
+                None => Targets::Single { target: entity },
+                Some(target) => match aoe.get(useitem.item) {
+                    None => Targets::Tile {
+                        tile_idx: map.xy_idx(target.x, target.y),
+                    },
+                    Some(area_effect) => Targets::Area {
+                        center: target,
+                        radius: area_effect.radius,
+                    },
+                },
+            };
 
             // If it is equippable then we want to equip it. And unequip whatever else was equipped
-            let can_equip = equippable.get(useitem.item);
-            if can_equip.is_some() {
-                let target_slot = can_equip.unwrap().slot;
-                let target = targets[0];
+            if let Some(can_equip) = equippable.get(useitem.item) {
+                let target_slot = can_equip.slot;
+                let target_slots = can_equip.slots();
 
-                // Remove any items the target has in the item's slot
+                // Remove any items the user has in the slots the item needs. Two-handed weapons
+                // clash with shields and the other way round. Cursed ones won't come off.
                 let mut to_unequip: Vec<Entity> = Vec::new();
-                for (item_entity, already_equipped, name) in (&entities, &equipped, &names).join() {
-                    if already_equipped.owner == target && already_equipped.slot == target_slot {
-                        to_unequip.push(item_entity);
-                        if target == *player_entity {
-                            gamelog.entries.push(format!("You unequip {}.", name.name));
+                let mut stuck: Option<Entity> = None;
+                for (item_entity, already_equipped) in (&entities, &equipped).join() {
+                    let occupied = equippable
+                        .get(item_entity)
+                        .map_or(vec![already_equipped.slot], |e| e.slots());
+                    if already_equipped.owner == entity
+                        && occupied.iter().any(|slot| target_slots.contains(slot))
+                    {
+                        if enchantments.get(item_entity).is_some_and(|e| e.cursed) {
+                            stuck = Some(item_entity);
                         }
+                        to_unequip.push(item_entity);
+                    }
+                }
+
+                if let Some(stuck) = stuck {
+                    if let Some(enchantment) = enchantments.get_mut(stuck) {
+                        enchantment.known = true;
                     }
+                    if entity == *player_entity {
+                        gamelog.entries.push(format!(
+                            "You can't take off the {}!",
+                            dungeon_master.item_name(
+                                &names.get(stuck).unwrap().name,
+                                enchantments.get(stuck)
+                            )
+                        ));
+                    }
+                    continue;
                 }
 
                 for item in to_unequip.iter() {
                     equipped.remove(*item);
                     backpack
-                        .insert(*item, InBackpack { owner: target })
+                        .insert(*item, InBackpack { owner: entity })
                         .expect("Unable to insert backpack entry");
+                    if entity == *player_entity {
+                        gamelog.entries.push(format!(
+                            "You unequip {}.",
+                            dungeon_master.item_name(
+                                &names.get(*item).unwrap().name,
+                                enchantments.get(*item)
+                            )
+                        ));
+                    }
                 }
 
-                // Wield the item
+                // Wield the item, finding out how good (or bad) it is
                 equipped
                     .insert(
                         useitem.item,
                         Equipped {
-                            owner: target,
+                            owner: entity,
                             slot: target_slot,
                         },
                     )
                     .expect("Unable to insert equipped component");
                 backpack.remove(useitem.item);
-                if target == *player_entity {
-                    gamelog.entries.push(format!(
-                        "You equip {}.",
-                        names.get(useitem.item).unwrap().name
-                    ));
-                }
-            }
-
-            // Use consumables
-            // Maybe Healing?
-            if let Some(healer) = healing.get(useitem.item) {
-                for target in targets.iter() {
-                    let stats = combat_stats.get_mut(*target);
-                    if let Some(stats) = stats {
-                        stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
-                        if entity == *player_entity {
-                            gamelog.entries.push(format!(
-                                "You drink the {}, healing {} hp",
-                                names.get(useitem.item).unwrap().name,
-                                healer.heal_amount
-                            ));
-                        }
-
-                        if let Some(pos) = positions.get(*target) {
-                            particle_builder.request(
-                                pos.x,
-                                pos.y,
-                                rltk::RGB::named(rltk::GREEN),
-                                rltk::RGB::named(rltk::BLACK),
-                                rltk::to_cp437('♥'),
-                                200.0,
-                            );
-                        }
-                    }
-                }
-            }
-
-            // Maybe Mana Restoring?
-            if let Some(mana_restorer) = mana_restoring.get(useitem.item) {
-                for target in targets.iter() {
-                    let stats = magic_stats.get_mut(*target);
-                    if let Some(stats) = stats {
-                        stats.mana =
-                            i32::min(stats.max_mana, stats.mana + mana_restorer.mana_amount);
-                        if entity == *player_entity {
-                            gamelog.entries.push(format!(
-                                "You drink the {}, restoring {} mana",
-                                names.get(useitem.item).unwrap().name,
-                                mana_restorer.mana_amount
-                            ));
-                        }
-
-                        if let Some(pos) = positions.get(*target) {
-                            particle_builder.request(
-                                pos.x,
-                                pos.y,
-                                rltk::RGB::named(rltk::BLUE),
-                                rltk::RGB::named(rltk::BLACK),
-                                rltk::to_cp437('♥'),
-                                200.0,
-                            );
-                        }
-                    }
+                if let Some(enchantment) = enchantments.get_mut(useitem.item) {
+                    enchantment.known = true;
                 }
-            }
-
-            // if it's edible, eat it
-            if provides_food.get(useitem.item).is_some() {
-                let target = targets[0];
-                if let Some(hunger_clock) = hunger_clocks.get_mut(target) {
+                if entity == *player_entity {
+                    let enchantment = enchantments.get(useitem.item);
                     gamelog.entries.push(format!(
-                        "You eat the {}.",
-                        names.get(useitem.item).unwrap().name
+                        "You equip {}.",
+                        dungeon_master
+                            .item_name(&names.get(useitem.item).unwrap().name, enchantment)
                     ));
-
-                    // This is sort of filling a hunger bar
-                    hunger_clock.duration += 150;
-                    if hunger_clock.duration > 200 {
-                        match hunger_clock.state {
-                            HungerState::WellFed => {
-                                hunger_clock.duration = 30;
-                            }
-                            HungerState::Normal => {
-                                hunger_clock.duration = i32::min(hunger_clock.duration - 200, 30);
-                                hunger_clock.state = HungerState::WellFed;
-                            }
-                            HungerState::Hungry => {
-                                hunger_clock.duration = hunger_clock.duration - 200;
-                                hunger_clock.state = HungerState::Normal;
-                            }
-                            HungerState::Starving => {
-                                hunger_clock.duration = hunger_clock.duration - 200;
-                                hunger_clock.state = HungerState::Hungry;
-                            }
-                        }
-                    }
-                }
-            }
-
-            // Deals Damage?
-            if let Some(damage) = inflict_damage.get(useitem.item) {
-                for mob in targets.iter() {
-                    SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage);
-                    if entity == *player_entity {
-                        let mob_name = names.get(*mob).unwrap();
-                        let item_name = names.get(useitem.item).unwrap();
-                        gamelog.entries.push(format!(
-                            "You use {} on {}, inflicting {} hp.",
-                            item_name.name, mob_name.name, damage.damage
-                        ));
-                    }
-
-                    if let Some(pos) = positions.get(*mob) {
-                        particle_builder.request(
-                            pos.x,
-                            pos.y,
-                            rltk::RGB::named(rltk::RED),
-                            rltk::RGB::named(rltk::BLACK),
-                            rltk::to_cp437('‼'),
-                            200.0,
-                        );
-                    }
-                }
-            }
-
-            let mut add_confusion = Vec::new();
-            {
-                // Applies Confusion?
-                if let Some(confusion) = confused.get(useitem.item) {
-                    for mob in targets.iter() {
-                        add_confusion.push((*mob, confusion.turns));
-                        if entity == *player_entity {
-                            let mob_name = names.get(*mob).unwrap();
-                            let item_name = names.get(useitem.item).unwrap();
-                            gamelog.entries.push(format!(
-                                "You use {} on {}, confusing them.",
-                                item_name.name, mob_name.name
-                            ));
-
-                            if let Some(pos) = positions.get(*mob) {
-                                particle_builder.request(
-                                    pos.x,
-                                    pos.y,
-                                    rltk::RGB::named(rltk::MAGENTA),
-                                    rltk::RGB::named(rltk::BLACK),
-                                    rltk::to_cp437('?'),
-                                    200.0,
-                                );
-                            }
-                        }
+                    if enchantment.is_some_and(|e| e.cursed) {
+                        gamelog
+                            .entries
+                            .push("It's cursed! You can't take it off.".to_string());
                     }
                 }
             }
 
-            for mob in add_confusion.iter() {
-                confused
-                    .insert(mob.0, Confusion { turns: mob.1 })
-                    .expect("Unable to insert status");
-            }
-
-            // Consume consumables
-            let consumable = consumables.get(useitem.item);
-            if consumable.is_some() {
-                entities.delete(useitem.item).expect("Delete Failed");
-            }
-
-            // Maybe reveal map
-            let is_mapper = magic_mapper.get(useitem.item);
-            match is_mapper {
-                None => {}
-                Some(MagicMapper { power }) => {
-                    *runstate = RunState::MagicMapReveal {
-                        remaining_power: *power,
-                        offset: 1,
-                    };
-                    gamelog.entries.push("Magic Mapper Activate!".to_string());
-                }
-            }
+            // Whatever else the item does is up to the effects queue
+            effect_queue.add(
+                Some(entity),
+                EffectType::ItemUse { item: useitem.item },
+                targets,
+            );
         }
 
         useitem.clear();
@@ -375,23 +273,27 @@ impl<'a> System<'a> for ItemDropSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
         ReadExpect<'a, Entity>,
+        ReadExpect<'a, MasterDungeonMap>,
         WriteExpect<'a, GameLog>,
         Entities<'a>,
         WriteStorage<'a, WantsToDropItem>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, Enchantment>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             player_entity,
+            dungeon_master,
             mut gamelog,
             entities,
             mut wants_drop,
             names,
             mut positions,
             mut backpack,
+            enchantments,
         ) = data;
 
         for (entity, to_drop) in (&entities, &wants_drop).join() {
@@ -415,7 +317,10 @@ impl<'a> System<'a> for ItemDropSystem {
             if entity == *player_entity {
                 gamelog.entries.push(format!(
                     "You drop the {}.",
-                    names.get(to_drop.item).unwrap().name
+                    dungeon_master.item_name(
+                        &names.get(to_drop.item).unwrap().name,
+                        enchantments.get(to_drop.item)
+                    )
                 ));
             }
         }
@@ -429,16 +334,48 @@ pub struct ItemRemoveSystem {}
 impl<'a> System<'a> for ItemRemoveSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, MasterDungeonMap>,
+        WriteExpect<'a, GameLog>,
         Entities<'a>,
         WriteStorage<'a, WantsToRemoveItem>,
+        ReadStorage<'a, Name>,
         WriteStorage<'a, Equipped>,
         WriteStorage<'a, InBackpack>,
+        WriteStorage<'a, Enchantment>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut wants_remove, mut equipped, mut backpack) = data;
+        let (
+            player_entity,
+            dungeon_master,
+            mut gamelog,
+            entities,
+            mut wants_remove,
+            names,
+            mut equipped,
+            mut backpack,
+            mut enchantments,
+        ) = data;
 
         for (entity, to_remove) in (&entities, &wants_remove).join() {
+            // Cursed items stay on until the curse is lifted
+            if let Some(enchantment) = enchantments.get_mut(to_remove.item) {
+                if enchantment.cursed {
+                    enchantment.known = true;
+                    if entity == *player_entity {
+                        gamelog.entries.push(format!(
+                            "You can't take off the {}!",
+                            dungeon_master.item_name(
+                                &names.get(to_remove.item).unwrap().name,
+                                Some(enchantment)
+                            )
+                        ));
+                    }
+                    continue;
+                }
+            }
+
             equipped.remove(to_remove.item);
             backpack
                 .insert(to_remove.item, InBackpack { owner: entity })
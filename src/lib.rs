@@ -0,0 +1,887 @@
+extern crate serde;
+#[macro_use]
+extern crate lazy_static;
+use particle_system::ParticleSpawnSystem;
+use rltk::{GameState, Point, Rltk, RGB};
+use specs::prelude::*;
+use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
+
+// Module Imports
+mod components;
+pub use components::*;
+mod map;
+pub use map::*;
+mod camera;
+pub mod map_builders;
+mod player;
+pub use player::PlayerCommand;
+use player::*;
+mod rect;
+pub use rect::*;
+mod gamelog;
+mod gui;
+mod rex_assets;
+pub use gamelog::GameLog;
+mod random_table;
+mod raws;
+pub mod run_seed;
+use run_seed::RunSeed;
+mod spawner;
+use random_table::*;
+
+mod visibility_system;
+use visibility_system::VisibilitySystem;
+mod monster_ai_system;
+use monster_ai_system::MonsterAI;
+mod map_indexing_system;
+use map_indexing_system::MapIndexingSystem;
+mod melee_combat_system;
+use melee_combat_system::MeleeCombatSystem;
+mod ranged_combat_system;
+use ranged_combat_system::RangedCombatSystem;
+mod combat;
+mod damage_system;
+mod experience;
+pub use experience::Improvement;
+mod door_system;
+use door_system::DoorSystem;
+pub mod dijkstra_map_system;
+use dijkstra_map_system::{DijkstraMapSystem, DijkstraMaps};
+mod dungeon;
+use dungeon::MasterDungeonMap;
+mod effects;
+use effects::EffectQueue;
+mod inventory_system;
+use inventory_system::{ItemCollectionSystem, ItemDropSystem, ItemRemoveSystem, ItemUseSystem};
+mod particle_system;
+pub mod saveload_system;
+mod shop;
+pub use particle_system::ParticleBuilder;
+mod encumbrance_system;
+pub mod headless;
+use encumbrance_system::EncumbranceSystem;
+mod hunger_system;
+use hunger_system::HungerSystem;
+mod initiative_system;
+use initiative_system::{GameClock, InitiativeSystem};
+mod lighting_system;
+use lighting_system::LightingSystem;
+mod spell_system;
+use spell_system::SpellCastSystem;
+mod status_effect_system;
+use status_effect_system::StatusEffectSystem;
+mod trigger_system;
+use trigger_system::TriggerSystem;
+
+const SHOW_MAPGEN_VISUALIZER: bool = true;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum RunState {
+    AwaitingInput,
+    PreRun,
+    /// Resolves what the player just did
+    PlayerTurn,
+    /// Runs the clock (and everyone else's turns) until it's the player's turn again
+    Ticking,
+    ShowInventory,
+    ShowDropItem,
+    ShowTargeting {
+        range: i32,
+        item: Entity,
+    },
+    /// Picking a tile to shoot (or throw) at
+    ShowFiring {
+        range: i32,
+    },
+    MagicMapReveal {
+        remaining_power: i32,
+        offset: i32,
+    },
+    MainMenu {
+        menu_selection: gui::MainMenuSelection,
+    },
+    LoadMenu {
+        menu_selection: gui::LoadMenuSelection,
+    },
+    SeedEntry,
+    NextLevel,
+    PreviousLevel,
+    ShowRemoveItem,
+    ShowEquipment,
+    /// Picking a known spell to cast
+    ShowSpells,
+    /// Trading with a vendor the player bumped into
+    ShowVendor {
+        vendor: Entity,
+        mode: gui::VendorMode,
+    },
+    /// The player levelled up and picks an improvement
+    LevelUp,
+    MapGeneration,
+    GameOver,
+}
+
+pub struct State {
+    pub ecs: World,
+    mapgen_next_state: Option<RunState>,
+    mapgen_history: Vec<Map>,
+    mapgen_index: usize,
+    mapgen_timer: f32,
+    seed_input: String,
+}
+
+impl State {
+    fn run_systems(&mut self) {
+        let mut encumbrance = EncumbranceSystem {};
+        encumbrance.run_now(&self.ecs);
+        let mut initiative = InitiativeSystem {};
+        initiative.run_now(&self.ecs);
+        let mut status_effects = StatusEffectSystem {};
+        status_effects.run_now(&self.ecs);
+        let mut lighting = LightingSystem {};
+        lighting.run_now(&self.ecs);
+        let mut vis = VisibilitySystem {};
+        vis.run_now(&self.ecs);
+        let mut dijkstra_maps = DijkstraMapSystem {};
+        dijkstra_maps.run_now(&self.ecs);
+        let mut mob = MonsterAI {};
+        mob.run_now(&self.ecs);
+        let mut doors = DoorSystem {};
+        doors.run_now(&self.ecs);
+        let mut map_index = MapIndexingSystem {};
+        map_index.run_now(&self.ecs);
+        let mut melee_system = MeleeCombatSystem {};
+        melee_system.run_now(&self.ecs);
+        let mut ranged_system = RangedCombatSystem {};
+        ranged_system.run_now(&self.ecs);
+        let mut pickup = ItemCollectionSystem {};
+        pickup.run_now(&self.ecs);
+        let mut potions = ItemUseSystem {};
+        potions.run_now(&self.ecs);
+        let mut spells = SpellCastSystem {};
+        spells.run_now(&self.ecs);
+        let mut drop_items = ItemDropSystem {};
+        drop_items.run_now(&self.ecs);
+        let mut unequip_items = ItemRemoveSystem {};
+        unequip_items.run_now(&self.ecs);
+        let mut hunger_system = HungerSystem {};
+        hunger_system.run_now(&self.ecs);
+        let mut trigger_system = TriggerSystem {};
+        trigger_system.run_now(&self.ecs);
+        effects::run_effects_queue(&mut self.ecs);
+        let mut particle_system = ParticleSpawnSystem {};
+        particle_system.run_now(&self.ecs);
+
+        self.ecs.maintain();
+    }
+}
+
+impl GameState for State {
+    fn tick(&mut self, ctx: &mut Rltk) {
+        let mut newrunstate;
+        {
+            let runstate = self.ecs.fetch::<RunState>();
+            newrunstate = *runstate;
+        }
+
+        ctx.cls();
+        particle_system::tick_and_cull_dead_particles(&mut self.ecs, ctx);
+
+        match newrunstate {
+            RunState::MainMenu { .. } | RunState::SeedEntry => {}
+            _ => {
+                camera::render_camera(&self.ecs, ctx);
+                gui::draw_ui(&self.ecs, ctx);
+            }
+        }
+
+        match newrunstate {
+            RunState::MapGeneration => {
+                // Levels restored from the dungeon master have no history to show
+                if !SHOW_MAPGEN_VISUALIZER || self.mapgen_history.is_empty() {
+                    newrunstate = self.mapgen_next_state.unwrap();
+                } else {
+                    ctx.cls();
+                    camera::render_debug_map(&self.mapgen_history[self.mapgen_index], ctx);
+
+                    self.mapgen_timer += ctx.frame_time_ms;
+                    // change index every 150ms
+                    if self.mapgen_timer > 100.0 {
+                        self.mapgen_timer = 0.0;
+                        self.mapgen_index += 1;
+                        if self.mapgen_index >= self.mapgen_history.len() {
+                            newrunstate = self.mapgen_next_state.unwrap();
+                        }
+                    }
+                }
+            }
+            RunState::PreRun
+            | RunState::PlayerTurn
+            | RunState::Ticking
+            | RunState::NextLevel
+            | RunState::PreviousLevel
+            | RunState::MagicMapReveal { .. } => {
+                newrunstate = self.advance_simulation(newrunstate);
+            }
+            RunState::AwaitingInput => {
+                newrunstate = player_input(self, ctx);
+            }
+            RunState::ShowInventory => match gui::show_inventory(self, ctx) {
+                (gui::ItemMenuResult::Cancel, _) => newrunstate = RunState::AwaitingInput,
+                (gui::ItemMenuResult::NoResponse, _) => {}
+                (gui::ItemMenuResult::Selected, entity) => {
+                    let entity = entity.unwrap();
+                    let range = self
+                        .ecs
+                        .read_storage::<Ranged>()
+                        .get(entity)
+                        .map(|r| r.range);
+                    if let Some(range) = range {
+                        newrunstate = RunState::ShowTargeting {
+                            range,
+                            item: entity,
+                        };
+                    } else {
+                        newrunstate = perform_command(
+                            &mut self.ecs,
+                            PlayerCommand::UseItem {
+                                item: entity,
+                                target: None,
+                            },
+                        );
+                    }
+                }
+            },
+            RunState::ShowDropItem => match gui::drop_item_menu(self, ctx) {
+                (gui::ItemMenuResult::Cancel, _) => newrunstate = RunState::AwaitingInput,
+                (gui::ItemMenuResult::NoResponse, _) => {}
+                (gui::ItemMenuResult::Selected, entity) => {
+                    let entity = entity.unwrap();
+                    newrunstate =
+                        perform_command(&mut self.ecs, PlayerCommand::DropItem { item: entity });
+                }
+            },
+            RunState::ShowTargeting { range, item } => match gui::ranged_target(self, ctx, range) {
+                (gui::ItemMenuResult::Cancel, _) => newrunstate = RunState::AwaitingInput,
+                (gui::ItemMenuResult::NoResponse, _) => {}
+                (gui::ItemMenuResult::Selected, entity) => {
+                    // Spells share the targeting with items
+                    let command = if self.ecs.read_storage::<SpellTemplate>().contains(item) {
+                        PlayerCommand::CastSpell {
+                            spell: item,
+                            target: entity,
+                        }
+                    } else {
+                        PlayerCommand::UseItem {
+                            item,
+                            target: entity,
+                        }
+                    };
+                    newrunstate = perform_command(&mut self.ecs, command);
+                }
+            },
+            RunState::ShowFiring { range } => match gui::ranged_target(self, ctx, range) {
+                (gui::ItemMenuResult::Cancel, _) => newrunstate = RunState::AwaitingInput,
+                (gui::ItemMenuResult::NoResponse, _) => {}
+                (gui::ItemMenuResult::Selected, target) => {
+                    newrunstate = perform_command(
+                        &mut self.ecs,
+                        PlayerCommand::Fire {
+                            target: target.unwrap(),
+                        },
+                    );
+                }
+            },
+            RunState::MainMenu { .. } => match gui::main_menu(self, ctx) {
+                gui::MainMenuResult::NoSelection { selected } => {
+                    newrunstate = RunState::MainMenu {
+                        menu_selection: selected,
+                    };
+                }
+                gui::MainMenuResult::Selected {
+                    selected: gui::MainMenuSelection::SaveGame,
+                } => {
+                    saveload_system::save_game(&mut self.ecs, "some_saved_game");
+                    newrunstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::LoadGame,
+                    };
+                }
+                gui::MainMenuResult::Selected {
+                    selected: gui::MainMenuSelection::NewGame,
+                } => {
+                    newrunstate = self.start_new_game();
+                }
+                gui::MainMenuResult::Selected {
+                    selected: gui::MainMenuSelection::ChangeSeed,
+                } => {
+                    self.seed_input.clear();
+                    newrunstate = RunState::SeedEntry;
+                }
+                gui::MainMenuResult::Selected {
+                    selected: gui::MainMenuSelection::LoadGame,
+                } => {
+                    newrunstate = RunState::LoadMenu {
+                        menu_selection: gui::LoadMenuSelection::Selecting(0),
+                    };
+                }
+                gui::MainMenuResult::Selected {
+                    selected: gui::MainMenuSelection::Quit,
+                } => {
+                    ::std::process::exit(0);
+                }
+            },
+            RunState::LoadMenu { .. } => match gui::load_menu(self, ctx) {
+                gui::LoadMenuResult::NoSelection { selected } => {
+                    newrunstate = RunState::LoadMenu {
+                        menu_selection: selected,
+                    };
+                }
+                gui::LoadMenuResult::Selected {
+                    selected: gui::LoadMenuSelection::Selecting(selected),
+                } => {
+                    let saved_files = saveload_system::list_save_files();
+                    saveload_system::load_game(&mut self.ecs, &saved_files[selected as usize]);
+                    *self.ecs.write_resource::<DijkstraMaps>() = DijkstraMaps::default();
+                    newrunstate = RunState::AwaitingInput;
+                    saveload_system::delete_save(&saved_files[selected as usize]);
+                }
+                gui::LoadMenuResult::Selected {
+                    selected: gui::LoadMenuSelection::Quit,
+                } => {
+                    newrunstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::NewGame,
+                    };
+                }
+            },
+            RunState::SeedEntry => match gui::seed_entry(self, ctx) {
+                gui::SeedEntryResult::NoResponse => {}
+                gui::SeedEntryResult::Cancel => {
+                    newrunstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::ChangeSeed,
+                    };
+                }
+                gui::SeedEntryResult::Selected(seed) => {
+                    self.ecs.insert(RunSeed::new(seed));
+                    newrunstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::NewGame,
+                    };
+                }
+            },
+            RunState::ShowRemoveItem => match gui::remove_item_menu(self, ctx) {
+                (gui::ItemMenuResult::Cancel, _) => newrunstate = RunState::AwaitingInput,
+                (gui::ItemMenuResult::NoResponse, _) => {}
+                (gui::ItemMenuResult::Selected, item_entity) => {
+                    let item_entity = item_entity.unwrap();
+                    newrunstate = perform_command(
+                        &mut self.ecs,
+                        PlayerCommand::RemoveItem { item: item_entity },
+                    );
+                }
+            },
+            RunState::ShowEquipment => match gui::show_equipment(self, ctx) {
+                (gui::ItemMenuResult::Cancel, _) => newrunstate = RunState::AwaitingInput,
+                (gui::ItemMenuResult::NoResponse, _) => {}
+                (gui::ItemMenuResult::Selected, item_entity) => {
+                    let item_entity = item_entity.unwrap();
+                    newrunstate = perform_command(
+                        &mut self.ecs,
+                        PlayerCommand::RemoveItem { item: item_entity },
+                    );
+                }
+            },
+            RunState::ShowSpells => match gui::show_spells(self, ctx) {
+                (gui::ItemMenuResult::Cancel, _) => newrunstate = RunState::AwaitingInput,
+                (gui::ItemMenuResult::NoResponse, _) => {}
+                (gui::ItemMenuResult::Selected, spell) => {
+                    let spell = spell.unwrap();
+                    let range = self
+                        .ecs
+                        .read_storage::<Ranged>()
+                        .get(spell)
+                        .map(|r| r.range);
+                    if let Some(range) = range {
+                        newrunstate = RunState::ShowTargeting { range, item: spell };
+                    } else {
+                        newrunstate = perform_command(
+                            &mut self.ecs,
+                            PlayerCommand::CastSpell {
+                                spell,
+                                target: None,
+                            },
+                        );
+                    }
+                }
+            },
+            RunState::ShowVendor { vendor, mode } => {
+                match gui::show_vendor(self, ctx, vendor, mode) {
+                    gui::VendorResult::NoResponse => {}
+                    gui::VendorResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::VendorResult::SwitchMode => {
+                        let mode = match mode {
+                            gui::VendorMode::Buy => gui::VendorMode::Sell,
+                            gui::VendorMode::Sell => gui::VendorMode::Buy,
+                        };
+                        newrunstate = RunState::ShowVendor { vendor, mode };
+                    }
+                    gui::VendorResult::Buy(item) => {
+                        newrunstate =
+                            perform_command(&mut self.ecs, PlayerCommand::Buy { vendor, item });
+                    }
+                    gui::VendorResult::Sell(item) => {
+                        newrunstate =
+                            perform_command(&mut self.ecs, PlayerCommand::Sell { vendor, item });
+                    }
+                }
+            }
+            RunState::LevelUp => match gui::level_up_menu(self, ctx) {
+                gui::LevelUpResult::NoSelection => {}
+                gui::LevelUpResult::Selected(improvement) => {
+                    newrunstate = perform_command(
+                        &mut self.ecs,
+                        PlayerCommand::ChooseImprovement { improvement },
+                    );
+                }
+            },
+            RunState::GameOver => match gui::game_over(ctx) {
+                gui::GameOverResult::NoSelection => {}
+                gui::GameOverResult::QuitToMenu => {
+                    newrunstate = RunState::MainMenu {
+                        menu_selection: gui::MainMenuSelection::NewGame,
+                    };
+                    self.ecs.insert(RunSeed::random());
+                    self.game_over_cleanup();
+                }
+            },
+        }
+
+        {
+            let mut runstatewriter = self.ecs.write_resource::<RunState>();
+            *runstatewriter = newrunstate;
+        }
+
+        damage_system::delete_the_dead(&mut self.ecs);
+    }
+}
+
+impl State {
+    /// Creates the game world (components, resources and the player) sitting on the main menu
+    pub fn new(run_seed: RunSeed) -> State {
+        // Initialize Game State
+        let mut gs = State {
+            ecs: World::new(),
+            mapgen_next_state: Some(RunState::MainMenu {
+                menu_selection: gui::MainMenuSelection::NewGame,
+            }),
+            mapgen_index: 0,
+            mapgen_history: Vec::new(),
+            mapgen_timer: 0.0,
+            seed_input: String::new(),
+        };
+
+        // Register Components
+        gs.ecs.register::<Position>();
+        gs.ecs.register::<Renderable>();
+        gs.ecs.register::<Player>();
+        gs.ecs.register::<Viewshed>();
+        gs.ecs.register::<Monster>();
+        gs.ecs.register::<Name>();
+        gs.ecs.register::<BlocksTile>();
+        gs.ecs.register::<CombatStats>();
+        gs.ecs.register::<MagicStats>();
+        gs.ecs.register::<Experience>();
+        gs.ecs.register::<WantsToMelee>();
+        gs.ecs.register::<WantsToPickupItem>();
+        gs.ecs.register::<WantsToDropItem>();
+        gs.ecs.register::<Item>();
+        gs.ecs.register::<ProvidesHealing>();
+        gs.ecs.register::<ProvidesManaRestore>();
+        gs.ecs.register::<InBackpack>();
+        gs.ecs.register::<WantsToUseItem>();
+        gs.ecs.register::<Consumable>();
+        gs.ecs.register::<Ranged>();
+        gs.ecs.register::<InflictsDamage>();
+        gs.ecs.register::<AreaOfEffect>();
+        gs.ecs.register::<SimpleMarker<SerializeMe>>();
+        gs.ecs.register::<SerializationHelper>();
+        gs.ecs.register::<Equippable>();
+        gs.ecs.register::<Equipped>();
+        gs.ecs.register::<MeleePowerBonus>();
+        gs.ecs.register::<MeleeWeapon>();
+        gs.ecs.register::<Attributes>();
+        gs.ecs.register::<Skills>();
+        gs.ecs.register::<DefenseBonus>();
+        gs.ecs.register::<WantsToRemoveItem>();
+        gs.ecs.register::<ParticleLifetime>();
+        gs.ecs.register::<HungerClock>();
+        gs.ecs.register::<ProvidesFood>();
+        gs.ecs.register::<MagicMapper>();
+        gs.ecs.register::<Teleporter>();
+        gs.ecs.register::<Hidden>();
+        gs.ecs.register::<EntryTrigger>();
+        gs.ecs.register::<EntityMoved>();
+        gs.ecs.register::<SingleActivation>();
+        gs.ecs.register::<OtherLevelPosition>();
+        gs.ecs.register::<MonsterAIState>();
+        gs.ecs.register::<Faction>();
+        gs.ecs.register::<Initiative>();
+        gs.ecs.register::<MyTurn>();
+        gs.ecs.register::<ActionCost>();
+        gs.ecs.register::<StatusEffects>();
+        gs.ecs.register::<InflictsStatus>();
+        gs.ecs.register::<RangedWeapon>();
+        gs.ecs.register::<Ammunition>();
+        gs.ecs.register::<WantsToShoot>();
+        gs.ecs.register::<SpellTemplate>();
+        gs.ecs.register::<KnownSpells>();
+        gs.ecs.register::<WantsToCastSpell>();
+        gs.ecs.register::<TeachesSpell>();
+        gs.ecs.register::<Identifies>();
+        gs.ecs.register::<Enchantment>();
+        gs.ecs.register::<RemovesCurse>();
+        gs.ecs.register::<Weight>();
+        gs.ecs.register::<Encumbrance>();
+        gs.ecs.register::<ItemValue>();
+        gs.ecs.register::<Gold>();
+        gs.ecs.register::<Vendor>();
+        gs.ecs.register::<BlocksVisibility>();
+        gs.ecs.register::<Door>();
+        gs.ecs.register::<WantsToOpenDoor>();
+        gs.ecs.register::<WantsToCloseDoor>();
+        gs.ecs.register::<LightSource>();
+        gs.ecs.register::<Looter>();
+
+        gs.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+
+        // Derive the gameplay RNG from the run seed
+        gs.ecs.insert(run_seed.gameplay_rng());
+        gs.ecs.insert(run_seed);
+
+        // Insert placeholder values for map and player positions
+        gs.ecs
+            .insert(Map::new(1, camera::VIEW_WIDTH, camera::VIEW_HEIGHT));
+        gs.ecs.insert(MasterDungeonMap::new());
+        gs.ecs.insert(GameClock::default());
+        gs.ecs.insert(DijkstraMaps::default());
+        gs.ecs.insert(Point::new(0, 0));
+
+        let player_entity = spawner::spawn_player(&mut gs.ecs, 0, 0);
+        gs.ecs.insert(player_entity);
+
+        // Turn RunState into a resource
+        gs.ecs.insert(RunState::MainMenu {
+            menu_selection: gui::MainMenuSelection::NewGame,
+        });
+
+        // Add gamelog as a resource
+        gs.ecs.insert(GameLog {
+            entries: vec!["Welcome to Rusty Roguelike".to_string()],
+        });
+
+        // Add Particle System as a service/resource
+        gs.ecs.insert(particle_system::ParticleBuilder::new());
+
+        // Add the effects queue as a resource
+        gs.ecs.insert(EffectQueue::new());
+
+        // Add Rex assets as a resource
+        gs.ecs.insert(rex_assets::RexAssets::new());
+
+        // Load entity templates from the raw files
+        raws::load_raws();
+
+        gs
+    }
+
+    /// Advances the states that don't wait on the player, shared by the rltk loop and the
+    /// headless runner. Any other state is returned untouched.
+    fn advance_simulation(&mut self, runstate: RunState) -> RunState {
+        let mut newrunstate = runstate;
+
+        match runstate {
+            RunState::PreRun => {
+                self.run_systems();
+                self.ecs.maintain();
+                newrunstate = RunState::AwaitingInput;
+            }
+            RunState::PlayerTurn => {
+                self.run_systems();
+                self.ecs.maintain();
+                match *self.ecs.fetch::<RunState>() {
+                    RunState::MagicMapReveal {
+                        remaining_power,
+                        offset,
+                    } => {
+                        newrunstate = RunState::MagicMapReveal {
+                            remaining_power,
+                            offset,
+                        }
+                    }
+                    _ => newrunstate = RunState::Ticking,
+                };
+            }
+            RunState::Ticking => {
+                self.run_systems();
+                self.ecs.maintain();
+                let player_entity = *self.ecs.fetch::<Entity>();
+                if self.ecs.read_storage::<MyTurn>().contains(player_entity) {
+                    newrunstate = if experience::has_pending_improvements(&self.ecs, player_entity)
+                    {
+                        RunState::LevelUp
+                    } else {
+                        RunState::AwaitingInput
+                    };
+                }
+            }
+            RunState::NextLevel => {
+                self.goto_level(1);
+                newrunstate = RunState::MapGeneration {};
+                self.mapgen_next_state = Some(RunState::PreRun);
+            }
+            RunState::PreviousLevel => {
+                self.goto_level(-1);
+                newrunstate = RunState::MapGeneration {};
+                self.mapgen_next_state = Some(RunState::PreRun);
+            }
+            RunState::MagicMapReveal {
+                remaining_power,
+                offset,
+            } => {
+                let player_pos = self.ecs.fetch::<Point>();
+                let mut map = self.ecs.fetch_mut::<Map>();
+
+                // first row
+                let top_row = player_pos.y - offset;
+
+                if top_row >= 0 {
+                    for x in (player_pos.x - offset)..(player_pos.x + offset) {
+                        if x < 0 || x >= (map.width - 1) {
+                            continue;
+                        }
+                        let idx = map.xy_idx(x, top_row);
+                        map.revealed_tiles[idx] = true;
+                    }
+                }
+
+                // bottom row
+                let bottom_row = player_pos.y + offset;
+
+                if bottom_row < (map.height - 1) {
+                    for x in (player_pos.x - offset)..(player_pos.x + offset) {
+                        if x < 0 || x >= (map.width - 1) {
+                            continue;
+                        }
+                        let idx = map.xy_idx(x, bottom_row);
+                        map.revealed_tiles[idx] = true;
+                    }
+                }
+
+                // left col
+                let left_col = player_pos.x - offset;
+
+                if left_col >= 0 {
+                    for y in (player_pos.y - offset)..(player_pos.y + offset) {
+                        if y < 0 || y >= (map.height - 1) {
+                            continue;
+                        }
+                        let idx = map.xy_idx(left_col, y);
+                        map.revealed_tiles[idx] = true;
+                    }
+                }
+
+                // right col
+                let right_col = player_pos.x + offset;
+
+                if right_col < (map.width - 1) {
+                    for y in (player_pos.y - offset)..(player_pos.y + offset) {
+                        if y < 0 || y >= (map.height - 1) {
+                            continue;
+                        }
+                        let idx = map.xy_idx(right_col, y);
+                        map.revealed_tiles[idx] = true;
+                    }
+                }
+
+                if remaining_power as usize == 0 {
+                    newrunstate = RunState::Ticking;
+                } else {
+                    newrunstate = RunState::MagicMapReveal {
+                        remaining_power: remaining_power - 1,
+                        offset: offset + 1,
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        newrunstate
+    }
+
+    /// Moves the player to `new_depth`, arriving from the level `offset` levels away. Levels that
+    /// were already visited are restored as they were left, the rest get generated.
+    fn generate_world_map(&mut self, new_depth: i32, offset: i32) {
+        self.mapgen_index = 0;
+        self.mapgen_timer = 0.0;
+        self.mapgen_history.clear();
+
+        let stored_map = self.ecs.fetch::<MasterDungeonMap>().get_map(new_depth);
+        let player_pos = match stored_map {
+            Some(map) => {
+                // Arrive on the stairs that lead back where we came from
+                let arrival_tile = if offset < 0 {
+                    TileType::DownStairs
+                } else {
+                    TileType::UpStairs
+                };
+                let arrival_idx = map
+                    .tiles
+                    .iter()
+                    .position(|tile| *tile == arrival_tile)
+                    .expect("a visited level is missing its stairs");
+                let arrival = Position {
+                    x: arrival_idx as i32 % map.width,
+                    y: arrival_idx as i32 / map.width,
+                };
+
+                *self.ecs.write_resource::<Map>() = map;
+                dungeon::thaw_level_entities(&mut self.ecs);
+
+                arrival
+            }
+            None => self.build_new_level(new_depth),
+        };
+
+        // Place the player and update resources
+        let mut ppos = self.ecs.write_resource::<rltk::Point>();
+        *ppos = rltk::Point::new(player_pos.x, player_pos.y);
+        let mut position_components = self.ecs.write_storage::<Position>();
+        let player_entity = self.ecs.fetch::<Entity>();
+        let player_pos_comp = position_components.get_mut(*player_entity);
+        if let Some(player_pos_comp) = player_pos_comp {
+            player_pos_comp.x = player_pos.x;
+            player_pos_comp.y = player_pos.y;
+        }
+
+        // Mark the player's visibility as dirty
+        let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
+        if let Some(vs) = viewshed_components.get_mut(*player_entity) {
+            vs.dirty = true;
+        }
+
+        // The monsters' maps were made for the level left behind
+        *self.ecs.write_resource::<DijkstraMaps>() = DijkstraMaps::default();
+    }
+
+    /// Runs the map builders for a level never visited before and returns where the player starts
+    fn build_new_level(&mut self, new_depth: i32) -> Position {
+        // Map generation gets its own generator so each depth only depends on the run seed
+        let depth_seed = self.ecs.fetch::<RunSeed>().for_depth(new_depth);
+        let mut rng = rltk::RandomNumberGenerator::seeded(depth_seed);
+        let mut builder = map_builders::level_builder(new_depth, &mut rng);
+        builder.build_map(&mut rng);
+        self.mapgen_history = builder.build_data.history.clone();
+
+        let player_pos = *builder
+            .build_data
+            .starting_position
+            .as_ref()
+            .expect("the builder chain did not set a starting position");
+
+        {
+            let mut worldmap_resource = self.ecs.write_resource::<Map>();
+            *worldmap_resource = builder.build_data.map.clone();
+
+            // Every level but the town has a way back up where the player arrives
+            if new_depth > 0 {
+                let up_stairs_idx = worldmap_resource.xy_idx(player_pos.x, player_pos.y);
+                worldmap_resource.tiles[up_stairs_idx] = TileType::UpStairs;
+            }
+        }
+
+        // Spawn room
+        builder.spawn_entities(&mut self.ecs, &mut rng);
+
+        player_pos
+    }
+
+    /// Leaves the current level (keeping it and its entities around) for the one `offset` levels
+    /// below it, or above it when negative
+    fn goto_level(&mut self, offset: i32) {
+        dungeon::freeze_level_entities(&mut self.ecs);
+
+        let current_depth;
+        {
+            let worldmap_resource = self.ecs.fetch::<Map>();
+            let mut dungeon_master = self.ecs.write_resource::<MasterDungeonMap>();
+            dungeon_master.store_map(&worldmap_resource);
+            current_depth = worldmap_resource.depth;
+        }
+
+        self.generate_world_map(current_depth + offset, offset);
+
+        let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+        if offset < 0 {
+            gamelog
+                .entries
+                .push("You ascend to the previous level.".to_string());
+            return;
+        }
+
+        // Notify the player and give them some health
+        gamelog
+            .entries
+            .push("You descend to the next level, and take a moment to heal.".to_string());
+        let mut player_health_store = self.ecs.write_storage::<CombatStats>();
+        let player_entity = self.ecs.fetch::<Entity>();
+        if let Some(health) = player_health_store.get_mut(*player_entity) {
+            health.hp = i32::max(health.hp, health.max_hp / 2);
+        }
+    }
+
+    /// Throws away the current run and generates the town a new one starts in
+    fn start_new_game(&mut self) -> RunState {
+        self.game_over_cleanup();
+        self.mapgen_next_state = Some(RunState::PreRun);
+        self.generate_world_map(0, 0);
+
+        RunState::MapGeneration {}
+    }
+
+    fn game_over_cleanup(&mut self) {
+        // Delete everything
+        let to_delete: Vec<_> = self.ecs.entities().join().collect();
+        for del in to_delete.iter() {
+            self.ecs
+                .delete_entity(*del)
+                .expect("Entity deletion failed");
+        }
+
+        // Clear the log
+        {
+            let mut gamelog = self.ecs.fetch_mut::<gamelog::GameLog>();
+            gamelog.entries.clear();
+        }
+
+        // Forget the levels (and time spent) of the previous run, and disguise its items anew
+        let mut dungeon_master = MasterDungeonMap::new();
+        dungeon_master.item_appearances = raws::item_appearances(
+            &raws::RAWS.lock().unwrap(),
+            &mut self.ecs.fetch::<RunSeed>().appearance_rng(),
+        );
+        self.ecs.insert(dungeon_master);
+        self.ecs.insert(GameClock::default());
+
+        // Restart the gameplay rolls so the run replays the same way for the same seed
+        let gameplay_rng = self.ecs.fetch::<RunSeed>().gameplay_rng();
+        self.ecs.insert(gameplay_rng);
+
+        // Spawn a new player
+        {
+            let player_entity = spawner::spawn_player(&mut self.ecs, 0, 0);
+            let mut player_entity_writer = self.ecs.write_resource::<Entity>();
+            *player_entity_writer = player_entity;
+        }
+
+        // Spells are templates living outside of any level
+        raws::spawn_all_spells(&raws::RAWS.lock().unwrap(), &mut self.ecs);
+    }
+}
@@ -0,0 +1,68 @@
+use rltk::{field_of_view, Point, RGB};
+use specs::prelude::*;
+
+use super::{LightSource, Map, Position, Viewshed};
+
+/// Light everything gets on the first level below the town, which is always in daylight
+const SHALLOWEST_AMBIENT_LIGHT: f32 = 0.45;
+/// Ambient light lost with every level further down, until there's none left at all
+const AMBIENT_LIGHT_PER_DEPTH: f32 = 0.1;
+
+/// Light everywhere on the level at `depth`, the deeper the darker
+pub fn ambient_light(depth: i32) -> f32 {
+    if depth <= 0 {
+        return 1.0;
+    }
+    f32::max(
+        0.0,
+        SHALLOWEST_AMBIENT_LIGHT - (depth - 1) as f32 * AMBIENT_LIGHT_PER_DEPTH,
+    )
+}
+
+/// Adds up the ambient light and the light of every light source, which fades with the distance,
+/// into the light of each tile of the map. The player's view is refreshed whenever it changes.
+pub struct LightingSystem {}
+
+impl<'a> System<'a> for LightingSystem {
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadExpect<'a, Entity>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, LightSource>,
+        WriteStorage<'a, Viewshed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, player_entity, positions, light_sources, mut viewsheds) = data;
+
+        let ambient = ambient_light(map.depth);
+        let mut light = vec![RGB::from_f32(ambient, ambient, ambient); map.tiles.len()];
+
+        for (pos, source) in (&positions, &light_sources).join() {
+            let origin = Point::new(pos.x, pos.y);
+            let range = source.range as f32;
+            // Sorted, so the light adds up the same way (down to the rounding) every run
+            let mut lit_tiles = field_of_view(origin, source.range, &*map);
+            lit_tiles.sort_by_key(|p| (p.y, p.x));
+            for tile in lit_tiles {
+                if tile.x < 0 || tile.x >= map.width || tile.y < 0 || tile.y >= map.height {
+                    continue;
+                }
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(origin, tile);
+                let intensity = (range - distance) / range;
+                if intensity > 0.0 {
+                    // Colors clamp themselves, so lights add up to white at most
+                    let idx = map.xy_idx(tile.x, tile.y);
+                    light[idx] = light[idx] + source.color * intensity;
+                }
+            }
+        }
+
+        if light != map.light {
+            map.light = light;
+            if let Some(viewshed) = viewsheds.get_mut(*player_entity) {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
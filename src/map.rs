@@ -1,19 +1,57 @@
-use rltk::{Rltk, RGB};
+use rltk::RGB;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
 #[derive(PartialEq, Eq, Hash, Copy, Clone, Serialize, Deserialize)]
 pub enum TileType {
     Wall,
+    Stalactite,
     Floor,
     VisitedFloor,
     DownStairs,
+    UpStairs,
+    Road,
+    Grass,
+    Gravel,
+    Tree,
+    ShallowWater,
+    DeepWater,
+    Lava,
+    Bridge,
     Debug(char),
 }
 
-pub const MAP_WIDTH: usize = 80;
-pub const MAP_HEIGHT: usize = 43;
-pub const MAP_COUNT: usize = MAP_HEIGHT * MAP_WIDTH;
+impl TileType {
+    /// Whether anyone can stand on the tile
+    pub fn is_walkable(self) -> bool {
+        !matches!(
+            self,
+            TileType::Wall
+                | TileType::Stalactite
+                | TileType::Tree
+                | TileType::DeepWater
+                | TileType::Lava
+        )
+    }
+
+    /// Whether the tile hides what's behind it (and stops whatever is shot through it)
+    pub fn is_opaque(self) -> bool {
+        matches!(self, TileType::Wall | TileType::Stalactite | TileType::Tree)
+    }
+
+    /// How much walking onto the tile costs compared to plain floor, paths prefer the cheap ones
+    pub fn cost(self) -> f32 {
+        match self {
+            TileType::Road | TileType::Bridge => 0.8,
+            TileType::Grass | TileType::Gravel => 1.1,
+            TileType::ShallowWater => 1.5,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Tiles with less light than this are too dark to see anything on them
+const MIN_VISIBLE_LIGHT: f32 = 0.2;
 
 #[derive(Default, Serialize, Deserialize, Clone)]
 pub struct Map {
@@ -25,6 +63,12 @@ pub struct Map {
     pub blocked: Vec<bool>,
     pub depth: i32,
     pub bloodstains: HashSet<usize>,
+    /// Tiles something stands on that can't be seen through, like a closed door
+    pub view_blocked: HashSet<usize>,
+    /// Tiles with a closed door, blocked but still worth pathing through since doors open
+    pub closed_doors: HashSet<usize>,
+    /// Light reaching every tile, kept up to date by the lighting system
+    pub light: Vec<RGB>,
 
     #[serde(skip_serializing)]
     #[serde(skip_deserializing)]
@@ -32,20 +76,31 @@ pub struct Map {
 }
 
 impl Map {
-    pub fn new(new_depth: i32) -> Map {
+    /// A level of walls, `width` by `height` tiles
+    pub fn new(new_depth: i32, width: i32, height: i32) -> Map {
+        let tile_count = (width * height) as usize;
         Map {
-            tiles: vec![TileType::Wall; MAP_COUNT],
-            width: MAP_WIDTH as i32,
-            height: MAP_HEIGHT as i32,
-            revealed_tiles: vec![false; MAP_COUNT],
-            visible_tiles: vec![false; MAP_COUNT],
-            blocked: vec![false; MAP_COUNT],
-            tile_content: vec![Vec::new(); MAP_COUNT],
+            tiles: vec![TileType::Wall; tile_count],
+            width,
+            height,
+            revealed_tiles: vec![false; tile_count],
+            visible_tiles: vec![false; tile_count],
+            blocked: vec![false; tile_count],
+            tile_content: vec![Vec::new(); tile_count],
             depth: new_depth,
             bloodstains: HashSet::new(),
+            view_blocked: HashSet::new(),
+            closed_doors: HashSet::new(),
+            light: vec![RGB::from_f32(0.0, 0.0, 0.0); tile_count],
         }
     }
 
+    /// Whether there's enough light on the tile to make out what's on it
+    pub fn is_lit(&self, idx: usize) -> bool {
+        let light = self.light[idx];
+        f32::max(light.r, f32::max(light.g, light.b)) >= MIN_VISIBLE_LIGHT
+    }
+
     #[inline]
     pub fn xy_idx(&self, x: i32, y: i32) -> usize {
         (y as usize * self.width as usize) + x as usize
@@ -56,41 +111,33 @@ impl Map {
             return false;
         }
         let idx = self.xy_idx(x, y);
-        !self.blocked[idx]
-    }
-
-    pub fn populate_blocked(&mut self) {
-        for (i, tile) in self.tiles.iter_mut().enumerate() {
-            self.blocked[i] = *tile == TileType::Wall;
-        }
-    }
-
-    pub fn clear_content_index(&mut self) {
-        for content in self.tile_content.iter_mut() {
-            content.clear();
-        }
-    }
-}
-
-// RLTK's traits implementation
-impl rltk::Algorithm2D for Map {
-    fn dimensions(&self) -> rltk::Point {
-        rltk::Point::new(self.width, self.height)
-    }
-}
-
-impl rltk::BaseMap for Map {
-    fn is_opaque(&self, idx: usize) -> bool {
-        self.tiles[idx as usize] == TileType::Wall
-    }
-
-    fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        !self.blocked[idx] || self.closed_doors.contains(&idx)
+    }
+
+    /// Like [rltk::BaseMap::get_available_exits] but only the terrain counts, whoever stands on
+    /// the tiles around is ignored
+    pub fn terrain_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        self.exits(idx, |x, y| {
+            x >= 1
+                && x < self.width
+                && y >= 1
+                && y < self.height
+                && self.tiles[self.xy_idx(x, y)].is_walkable()
+        })
+    }
+
+    /// Exits from `idx` to the tiles around it that `valid` lets through, with the cost of
+    /// stepping onto each of them
+    fn exits(
+        &self,
+        idx: usize,
+        valid: impl Fn(i32, i32) -> bool,
+    ) -> rltk::SmallVec<[(usize, f32); 10]> {
         let mut exits = rltk::SmallVec::new();
         let x = idx as i32 % self.width;
         let y = idx as i32 / self.width;
         let w = self.width as usize;
 
-        // Cardinal directions
         let directions = [
             // Cardinal directions
             (x - 1, y, idx - 1, 1.0), // left
@@ -105,112 +152,66 @@ impl rltk::BaseMap for Map {
         ];
 
         for (xdir, ydir, dir_idx, dist) in directions {
-            if self.is_exit_valid(xdir, ydir) {
-                exits.push((dir_idx, dist))
+            if valid(xdir, ydir) {
+                exits.push((dir_idx, dist * self.tiles[dir_idx].cost()))
             }
         }
         exits
     }
 
-    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
-        let w = self.width as usize;
-        let p1 = rltk::Point::new(idx1 % w, idx1 / w);
-        let p2 = rltk::Point::new(idx2 % w, idx2 / w);
-        rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
-    }
-}
-
-pub fn draw_map(map: &Map, ctx: &mut Rltk) {
-    let mut y = 0;
-    let mut x = 0;
-    for (idx, tile) in map.tiles.iter().enumerate() {
-        // Render a tile depending upon the tile type
-
-        if map.revealed_tiles[idx] {
-            let glyph;
-            let mut fg;
-            let mut bg = RGB::from_f32(0., 0., 0.);
-            match tile {
-                TileType::Floor => {
-                    glyph = rltk::to_cp437('.');
-                    fg = RGB::from_f32(0.0, 0.5, 0.5);
-                }
-                TileType::VisitedFloor => {
-                    glyph = rltk::to_cp437('.');
-                    fg = RGB::from_f32(1.0, 0.0, 1.0);
-                }
-                TileType::Wall => {
-                    fg = RGB::from_f32(0.0, 1.0, 0.0);
-                    glyph = wall_glyph(&*map, x, y);
-                }
-                TileType::DownStairs => {
-                    fg = RGB::from_f32(0., 1.0, 1.0);
-                    glyph = rltk::to_cp437('>');
-                }
-                TileType::Debug(dbg_glyph) => {
-                    fg = RGB::from_f32(1.0, 1.0, 1.0);
-                    glyph = rltk::to_cp437(*dbg_glyph);
-                }
+    /// Tiles a projectile flies through from `start` towards `end`. It stops on the first tile
+    /// with something blocking the way, on `end` or right before an opaque tile.
+    pub fn projectile_path(&self, start: rltk::Point, end: rltk::Point) -> Vec<rltk::Point> {
+        let mut path = Vec::new();
+        for point in rltk::line2d(rltk::LineAlg::Bresenham, start, end) {
+            if point == start {
+                continue;
             }
-
-            if map.bloodstains.contains(&idx) {
-                bg = RGB::from_f32(0.75, 0., 0.);
+            let idx = self.xy_idx(point.x, point.y);
+            if self.tiles[idx].is_opaque() {
+                break;
             }
-            if !map.visible_tiles[idx] {
-                fg = fg.to_greyscale()
+            path.push(point);
+            if self.blocked[idx] || point == end {
+                break;
             }
-            ctx.set(x, y, fg, bg, glyph);
-        }
-        // move coordinates
-        x += 1;
-        if x > (MAP_WIDTH - 1) as i32 {
-            x = 0;
-            y += 1;
         }
+        path
     }
-}
 
-fn wall_glyph(map: &Map, x: i32, y: i32) -> rltk::FontCharType {
-    if x < 1 || x > map.width - 2 || y < 1 || y > map.height - 2 {
-        return 35;
+    pub fn populate_blocked(&mut self) {
+        for (i, tile) in self.tiles.iter().enumerate() {
+            self.blocked[i] = !tile.is_walkable();
+        }
     }
-    let mut mask: u8 = 0;
 
-    if is_revealed_and_wall(map, x, y - 1) {
-        mask += 1;
-    }
-    if is_revealed_and_wall(map, x, y + 1) {
-        mask += 2;
+    pub fn clear_content_index(&mut self) {
+        for content in self.tile_content.iter_mut() {
+            content.clear();
+        }
     }
-    if is_revealed_and_wall(map, x - 1, y) {
-        mask += 4;
+}
+
+// RLTK's traits implementation
+impl rltk::Algorithm2D for Map {
+    fn dimensions(&self) -> rltk::Point {
+        rltk::Point::new(self.width, self.height)
     }
-    if is_revealed_and_wall(map, x + 1, y) {
-        mask += 8;
+}
+
+impl rltk::BaseMap for Map {
+    fn is_opaque(&self, idx: usize) -> bool {
+        self.tiles[idx].is_opaque() || self.view_blocked.contains(&idx)
     }
 
-    match mask {
-        0 => 9,    // Pillar because we can't see neighbors
-        1 => 186,  // Wall only to the north
-        2 => 186,  // Wall only to the south
-        3 => 186,  // Wall to the north and south
-        4 => 205,  // Wall only to the west
-        5 => 188,  // Wall to the north and west
-        6 => 187,  // Wall to the south and west
-        7 => 185,  // Wall to the north, south and west
-        8 => 205,  // Wall only to the east
-        9 => 200,  // Wall to the north and east
-        10 => 201, // Wall to the south and east
-        11 => 204, // Wall to the north, south and east
-        12 => 205, // Wall to the east and west
-        13 => 202, // Wall to the east, west, and south
-        14 => 203, // Wall to the east, west, and north
-        15 => 206, // ╬ Wall on all sides
-        _ => 35,   // We missed one?
+    fn get_available_exits(&self, idx: usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        self.exits(idx, |x, y| self.is_exit_valid(x, y))
     }
-}
 
-fn is_revealed_and_wall(map: &Map, x: i32, y: i32) -> bool {
-    let idx = map.xy_idx(x, y);
-    map.tiles[idx] == TileType::Wall && map.revealed_tiles[idx]
+    fn get_pathing_distance(&self, idx1: usize, idx2: usize) -> f32 {
+        let w = self.width as usize;
+        let p1 = rltk::Point::new(idx1 % w, idx1 / w);
+        let p2 = rltk::Point::new(idx2 % w, idx2 / w);
+        rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
+    }
 }
@@ -0,0 +1,78 @@
+use rltk::RandomNumberGenerator;
+
+use crate::Position;
+
+use super::{BuilderMap, MetaMapBuilder};
+
+#[allow(dead_code)]
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum XStart {
+    Left,
+    Center,
+    Right,
+}
+
+#[allow(dead_code)]
+#[derive(PartialEq, Eq, Copy, Clone)]
+pub enum YStart {
+    Top,
+    Center,
+    Bottom,
+}
+
+/// Sets the starting position at the floor tile closest to the requested area of the map
+pub struct AreaStartingPosition {
+    x: XStart,
+    y: YStart,
+}
+
+impl MetaMapBuilder for AreaStartingPosition {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let seed_x = match self.x {
+            XStart::Left => 1,
+            XStart::Center => build_data.map.width / 2,
+            XStart::Right => build_data.map.width - 2,
+        };
+
+        let seed_y = match self.y {
+            YStart::Top => 1,
+            YStart::Center => build_data.map.height / 2,
+            YStart::Bottom => build_data.map.height - 2,
+        };
+
+        let seed = rltk::Point::new(seed_x, seed_y);
+        let closest_floor = build_data
+            .map
+            .tiles
+            .iter()
+            .enumerate()
+            .filter(|(_, tile)| tile.is_walkable())
+            .map(|(idx, _)| {
+                let tile_point = rltk::Point::new(
+                    idx as i32 % build_data.map.width,
+                    idx as i32 / build_data.map.width,
+                );
+                (
+                    tile_point,
+                    rltk::DistanceAlg::PythagorasSquared.distance2d(tile_point, seed),
+                )
+            })
+            .min_by(|(_, distance1), (_, distance2)| {
+                distance1
+                    .partial_cmp(distance2)
+                    .expect("Should be able to compare since both are supposed to be non-Nan")
+            })
+            .expect("The map should have at least one floor tile");
+
+        build_data.starting_position = Some(Position {
+            x: closest_floor.0.x,
+            y: closest_floor.0.y,
+        });
+    }
+}
+
+impl AreaStartingPosition {
+    pub fn new(x: XStart, y: YStart) -> Box<AreaStartingPosition> {
+        Box::new(AreaStartingPosition { x, y })
+    }
+}
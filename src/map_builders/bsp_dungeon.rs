@@ -1,39 +1,25 @@
 use rltk::RandomNumberGenerator;
 
-use crate::{spawner, Map, Position, Rect, TileType, SHOW_MAPGEN_VISUALIZER};
+use crate::{Rect, TileType};
 
-use super::{common::apply_room_to_map, MapBuilder};
+use super::{common::apply_room_to_map, BuilderMap, InitialMapBuilder};
 
 pub struct BspDungeonBuilder {
-    map: Map,
-    starting_position: Position,
-    depth: i32,
-    rooms: Vec<Rect>,
-    history: Vec<Map>,
     rects: Vec<Rect>,
 }
 
-impl MapBuilder for BspDungeonBuilder {
-    fn get_map(&mut self) -> Map {
-        self.map.clone()
-    }
-
-    fn get_starting_position(&mut self) -> Position {
-        self.starting_position.clone()
-    }
-
-    fn get_snapshot_history(&self) -> Vec<Map> {
-        self.history.clone()
-    }
-
-    fn build_map(&mut self) {
-        let mut rng = RandomNumberGenerator::new();
-
+impl InitialMapBuilder for BspDungeonBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let mut rooms: Vec<Rect> = Vec::new();
         self.rects.clear();
 
         // Start with a single rectangle that takes the whole screen
-        self.rects
-            .push(Rect::new(2, 2, self.map.width - 5, self.map.height - 5));
+        self.rects.push(Rect::new(
+            2,
+            2,
+            build_data.map.width - 5,
+            build_data.map.height - 5,
+        ));
         let first_room = self.rects[0];
 
         // divide the first room
@@ -44,73 +30,40 @@ impl MapBuilder for BspDungeonBuilder {
         // it to the rooms list.
         let mut n_rooms = 0;
         while n_rooms < 240 {
-            let rect = self.get_random_rect(&mut rng);
-            let candidate = self.get_random_sub_rect(rect, &mut rng);
+            let rect = self.get_random_rect(rng);
+            let candidate = self.get_random_sub_rect(rect, rng);
 
-            if self.is_possible(candidate) {
-                apply_room_to_map(&mut self.map, &candidate);
-                self.rooms.push(candidate);
+            if self.is_possible(candidate, build_data) {
+                apply_room_to_map(&mut build_data.map, &candidate);
+                rooms.push(candidate);
                 self.add_subrects(rect);
-                self.take_snapshot();
+                build_data.take_snapshot();
             }
 
             n_rooms += 1;
         }
 
-        let start = self.rooms[0].center();
-        self.starting_position = Position {
-            x: start.0,
-            y: start.1,
-        };
-
         // Adding corridors
-        self.rooms.sort_by(|a, b| a.x1.cmp(&b.x1));
+        rooms.sort_by_key(|room| room.x1);
 
-        for i in 0..self.rooms.len() - 1 {
-            let room = self.rooms[i];
-            let next_room = self.rooms[i + 1];
+        for i in 0..rooms.len() - 1 {
+            let room = rooms[i];
+            let next_room = rooms[i + 1];
             let start_x = room.x1 + rng.roll_dice(1, i32::abs(room.x1 - room.x2));
             let start_y = room.y1 + rng.roll_dice(1, i32::abs(room.y1 - room.y2));
             let end_x = next_room.x1 + rng.roll_dice(1, i32::abs(next_room.x1 - next_room.x2));
             let end_y = next_room.y1 + rng.roll_dice(1, i32::abs(next_room.y1 - next_room.y2));
-            self.draw_corridor(start_x, start_y, end_x, end_y);
-            self.take_snapshot();
+            self.draw_corridor(build_data, start_x, start_y, end_x, end_y);
+            build_data.take_snapshot();
         }
 
-        // Add the exit
-        let stairs_position = self.rooms[self.rooms.len() - 1].center();
-        let stairs_idx = self.map.xy_idx(stairs_position.0, stairs_position.1);
-        self.map.tiles[stairs_idx] = TileType::DownStairs;
-    }
-
-    fn spawn_entities(&mut self, ecs: &mut specs::World) {
-        for room in self.rooms.iter().skip(1) {
-            spawner::spawn_room(ecs, room, self.depth);
-        }
-    }
-
-    fn take_snapshot(&mut self) {
-        if SHOW_MAPGEN_VISUALIZER {
-            // stores a copy of the map while making all tiles visible
-            let mut snapshot = self.map.clone();
-            for v in snapshot.revealed_tiles.iter_mut() {
-                *v = true;
-            }
-            self.history.push(snapshot);
-        }
+        build_data.rooms = Some(rooms);
     }
 }
 
 impl BspDungeonBuilder {
-    pub fn new(new_depth: i32) -> BspDungeonBuilder {
-        BspDungeonBuilder {
-            map: Map::new(new_depth),
-            starting_position: Position { x: 0, y: 0 },
-            depth: new_depth,
-            rooms: Vec::new(),
-            history: Vec::new(),
-            rects: Vec::new(),
-        }
+    pub fn new() -> Box<BspDungeonBuilder> {
+        Box::new(BspDungeonBuilder { rects: Vec::new() })
     }
 
     fn add_subrects(&mut self, rect: Rect) {
@@ -165,7 +118,7 @@ impl BspDungeonBuilder {
         result
     }
 
-    fn is_possible(&self, rect: Rect) -> bool {
+    fn is_possible(&self, rect: Rect, build_data: &BuilderMap) -> bool {
         // We use `expanded` to make sure we are not overlaping with other rooms.
         let mut expanded = rect;
 
@@ -179,10 +132,10 @@ impl BspDungeonBuilder {
         for y in expanded.y1..=expanded.y2 {
             for x in expanded.x1..=expanded.x2 {
                 // check out of bounds
-                if x > self.map.width - 2 {
+                if x > build_data.map.width - 2 {
                     can_build = false;
                 }
-                if y > self.map.height - 2 {
+                if y > build_data.map.height - 2 {
                     can_build = false;
                 }
                 if x < 1 {
@@ -193,8 +146,8 @@ impl BspDungeonBuilder {
                 }
 
                 if can_build {
-                    let idx = self.map.xy_idx(x, y);
-                    if self.map.tiles[idx] != TileType::Wall {
+                    let idx = build_data.map.xy_idx(x, y);
+                    if build_data.map.tiles[idx] != TileType::Wall {
                         can_build = false;
                     }
                 }
@@ -204,7 +157,7 @@ impl BspDungeonBuilder {
         can_build
     }
 
-    fn draw_corridor(&mut self, x1: i32, y1: i32, x2: i32, y2: i32) {
+    fn draw_corridor(&mut self, build_data: &mut BuilderMap, x1: i32, y1: i32, x2: i32, y2: i32) {
         let mut x = x1;
         let mut y = y1;
 
@@ -219,8 +172,8 @@ impl BspDungeonBuilder {
                 y -= 1;
             }
 
-            let idx = self.map.xy_idx(x, y);
-            self.map.tiles[idx] = TileType::Floor;
+            let idx = build_data.map.xy_idx(x, y);
+            build_data.map.tiles[idx] = TileType::Floor;
         }
     }
 }
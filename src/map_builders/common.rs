@@ -1,7 +1,7 @@
 use super::{Map, Rect, TileType};
 use std::{
     cmp::{max, min},
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
 };
 
 #[derive(PartialEq, Eq, Copy, Clone)]
@@ -47,7 +47,7 @@ pub fn cull_unreachables_and_return_most_distant_tile(map: &mut Map, start_idx:
     let dijkstra_map = rltk::DijkstraMap::new(map.width, map.height, &map_starts, map, 200.0);
     let mut exit_tile = (start_idx, 0.0f32);
     for (i, tile) in map.tiles.iter_mut().enumerate() {
-        if *tile == TileType::Floor {
+        if tile.is_walkable() {
             let distance_to_start = dijkstra_map.map[i];
             if distance_to_start == std::f32::MAX {
                 *tile = TileType::Wall;
@@ -66,8 +66,8 @@ pub fn cull_unreachables_and_return_most_distant_tile(map: &mut Map, start_idx:
 pub fn generate_voronoi_spawn_regions(
     map: &Map,
     rng: &mut rltk::RandomNumberGenerator,
-) -> HashMap<i32, Vec<usize>> {
-    let mut noise_areas: HashMap<i32, Vec<usize>> = HashMap::new();
+) -> BTreeMap<i32, Vec<usize>> {
+    let mut noise_areas: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
     let mut noise = rltk::FastNoise::seeded(rng.roll_dice(1, 65536) as u64);
     noise.set_noise_type(rltk::NoiseType::Cellular);
     noise.set_frequency(0.08);
@@ -76,7 +76,7 @@ pub fn generate_voronoi_spawn_regions(
     for y in 1..map.height - 1 {
         for x in 1..map.width - 1 {
             let idx = map.xy_idx(x, y);
-            if map.tiles[idx] == TileType::Floor {
+            if map.tiles[idx].is_walkable() {
                 // On the tutorial it uses 10240.0 but using it results in ~1k areas being
                 // created instead of the 20-30 it claims
                 let cell_value_f = noise.get_noise(x as f32, y as f32) * 15.0;
@@ -94,6 +94,132 @@ pub fn generate_voronoi_spawn_regions(
     noise_areas
 }
 
+/// Noise levels past which [paint_pools] turns floor into the edge of a pool, and into its depths
+const POOL_EDGE: f32 = 0.3;
+const POOL_DEPTHS: f32 = 0.5;
+
+/// Floods the floor of the map with noise shaped pools, `depths` in their middle and always ringed
+/// by `edge`: lakes of shallow and deep water, lava with scorched gravel around it, etc. The edge
+/// has to be walkable, a pool whose depths would cut off part of the level is all edge instead.
+pub fn paint_pools(
+    map: &mut Map,
+    rng: &mut rltk::RandomNumberGenerator,
+    edge: TileType,
+    depths: TileType,
+) {
+    let regions = walkable_regions(map);
+
+    let mut noise = rltk::FastNoise::seeded(rng.roll_dice(1, 65536) as u64);
+    noise.set_noise_type(rltk::NoiseType::Simplex);
+    noise.set_frequency(0.06);
+
+    let mut deep_tiles: BTreeSet<usize> = BTreeSet::new();
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] != TileType::Floor {
+                continue;
+            }
+            let level = noise.get_noise(x as f32, y as f32);
+            if level > POOL_EDGE {
+                map.tiles[idx] = edge;
+            }
+            if level > POOL_DEPTHS {
+                deep_tiles.insert(idx);
+            }
+        }
+    }
+
+    // Fill in the depths one pool at a time, checking nothing got cut off
+    while let Some(first) = deep_tiles.pop_first() {
+        let mut pool = vec![first];
+        let mut open = vec![first];
+        while let Some(idx) = open.pop() {
+            for next in neighbours(map, idx) {
+                if deep_tiles.remove(&next) {
+                    pool.push(next);
+                    open.push(next);
+                }
+            }
+        }
+
+        for idx in pool.iter() {
+            map.tiles[*idx] = depths;
+        }
+        if splits_regions(&regions, &walkable_regions(map)) {
+            for idx in pool.iter() {
+                map.tiles[*idx] = edge;
+            }
+        }
+    }
+}
+
+/// The tiles around `idx`, diagonals included, that are on the map
+fn neighbours(map: &Map, idx: usize) -> impl Iterator<Item = usize> + '_ {
+    let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+    (-1..=1)
+        .flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+        .filter(|(nx, ny)| *nx >= 0 && *nx < map.width && *ny >= 0 && *ny < map.height)
+        .map(|(nx, ny)| map.xy_idx(nx, ny))
+}
+
+/// Whether tiles that could reach each other according to `before` no longer can in `after`
+fn splits_regions(before: &[Option<usize>], after: &[Option<usize>]) -> bool {
+    let mut seen: HashMap<usize, usize> = HashMap::new();
+    before.iter().zip(after).any(|pair| match pair {
+        (Some(before), Some(after)) => *seen.entry(*before).or_insert(*after) != *after,
+        _ => false,
+    })
+}
+
+/// Labels every walkable tile with the region it belongs to: the tiles that can be reached from
+/// each other, diagonals included
+fn walkable_regions(map: &Map) -> Vec<Option<usize>> {
+    let mut regions: Vec<Option<usize>> = vec![None; map.tiles.len()];
+    let mut next_region = 0;
+    for start in 0..map.tiles.len() {
+        if regions[start].is_some() || !map.tiles[start].is_walkable() {
+            continue;
+        }
+        regions[start] = Some(next_region);
+        let mut open = vec![start];
+        while let Some(idx) = open.pop() {
+            for next in neighbours(map, idx) {
+                if regions[next].is_none() && map.tiles[next].is_walkable() {
+                    regions[next] = Some(next_region);
+                    open.push(next);
+                }
+            }
+        }
+        next_region += 1;
+    }
+    regions
+}
+
+/// Turns about one in `one_in` of the `ground` tiles into a `pillar` (stalactites, trees...). Only
+/// tiles with nothing but walkable tiles around them are picked, so no passage gets closed off.
+pub fn scatter_pillars(
+    map: &mut Map,
+    rng: &mut rltk::RandomNumberGenerator,
+    ground: TileType,
+    pillar: TileType,
+    one_in: i32,
+) {
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx] != ground || rng.roll_dice(1, one_in) != 1 {
+                continue;
+            }
+            let surrounded_by_ground = (-1..=1)
+                .all(|dy| (-1..=1).all(|dx| map.tiles[map.xy_idx(x + dx, y + dy)].is_walkable()));
+            if surrounded_by_ground {
+                map.tiles[idx] = pillar;
+            }
+        }
+    }
+}
+
 pub fn paint(map: &mut Map, symmetry_mode: Symmetry, brush_size: i32, x: i32, y: i32) -> usize {
     let mut painted_count = 0;
     match symmetry_mode {
@@ -0,0 +1,24 @@
+use rltk::RandomNumberGenerator;
+
+use super::{common, BuilderMap, MetaMapBuilder};
+
+/// Turns into walls every floor tile that can't be reached from the starting position
+pub struct CullUnreachable {}
+
+impl MetaMapBuilder for CullUnreachable {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let starting_pos = build_data
+            .starting_position
+            .expect("CullUnreachable requires a starting position");
+        let start_idx = build_data.map.xy_idx(starting_pos.x, starting_pos.y);
+
+        common::cull_unreachables_and_return_most_distant_tile(&mut build_data.map, start_idx);
+        build_data.take_snapshot();
+    }
+}
+
+impl CullUnreachable {
+    pub fn new() -> Box<CullUnreachable> {
+        Box::new(CullUnreachable {})
+    }
+}
@@ -0,0 +1,30 @@
+use rltk::RandomNumberGenerator;
+
+use crate::TileType;
+
+use super::{common, BuilderMap, MetaMapBuilder};
+
+/// Places the down stairs on the reachable tile that is the furthest away from the start
+pub struct DistantExit {}
+
+impl MetaMapBuilder for DistantExit {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let starting_pos = build_data
+            .starting_position
+            .expect("DistantExit requires a starting position");
+        let start_idx = build_data.map.xy_idx(starting_pos.x, starting_pos.y);
+
+        let exit_tile_idx =
+            common::cull_unreachables_and_return_most_distant_tile(&mut build_data.map, start_idx);
+
+        // Place the stairs
+        build_data.map.tiles[exit_tile_idx] = TileType::DownStairs;
+        build_data.take_snapshot();
+    }
+}
+
+impl DistantExit {
+    pub fn new() -> Box<DistantExit> {
+        Box::new(DistantExit {})
+    }
+}
@@ -0,0 +1,93 @@
+use rltk::RandomNumberGenerator;
+
+use crate::TileType;
+
+use super::{BuilderMap, MetaMapBuilder};
+
+/// Puts a door wherever a corridor (or a gap in a wall) opens into a room: a floor tile squeezed
+/// between two walls, with open ground spreading sideways on at least one side of it
+pub struct DoorPlacement {}
+
+impl MetaMapBuilder for DoorPlacement {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.doors(build_data);
+    }
+}
+
+impl DoorPlacement {
+    pub fn new() -> Box<DoorPlacement> {
+        Box::new(DoorPlacement {})
+    }
+
+    fn doors(&mut self, build_data: &mut BuilderMap) {
+        let map = &build_data.map;
+        let start_idx = build_data
+            .starting_position
+            .as_ref()
+            .map(|pos| map.xy_idx(pos.x, pos.y));
+
+        let mut doors: Vec<usize> = Vec::new();
+        for y in 2..map.height - 2 {
+            for x in 2..map.width - 2 {
+                let idx = map.xy_idx(x, y);
+                if !self.is_doorway(build_data, x, y)
+                    || Some(idx) == start_idx
+                    || build_data.spawn_list.iter().any(|(spawn, _)| *spawn == idx)
+                {
+                    continue;
+                }
+                // One door per doorway, even when the gap is more than a tile deep
+                let next_to_door = doors.iter().any(|door| {
+                    let (dx, dy) = (*door as i32 % map.width, *door as i32 / map.width);
+                    i32::abs(dx - x) <= 1 && i32::abs(dy - y) <= 1
+                });
+                if !next_to_door {
+                    doors.push(idx);
+                }
+            }
+        }
+
+        for door in doors {
+            build_data.spawn_list.push((door, "Door".to_string()));
+        }
+    }
+
+    fn is_floor(build_data: &BuilderMap, x: i32, y: i32) -> bool {
+        build_data.map.tiles[build_data.map.xy_idx(x, y)] == TileType::Floor
+    }
+
+    fn is_open(build_data: &BuilderMap, x: i32, y: i32) -> bool {
+        build_data.map.tiles[build_data.map.xy_idx(x, y)].is_walkable()
+    }
+
+    fn is_wall(build_data: &BuilderMap, x: i32, y: i32) -> bool {
+        build_data.map.tiles[build_data.map.xy_idx(x, y)] == TileType::Wall
+    }
+
+    /// Whether the floor at (x, y) is walled in on two opposite sides and opens onto a room (a
+    /// tile with no walls on either side across the passage) on one of the other two
+    fn is_doorway(&self, build_data: &BuilderMap, x: i32, y: i32) -> bool {
+        if !Self::is_floor(build_data, x, y) {
+            return false;
+        }
+        let opens_wide = |x: i32, y: i32, dx: i32, dy: i32| {
+            Self::is_open(build_data, x, y)
+                && Self::is_open(build_data, x + dy, y + dx)
+                && Self::is_open(build_data, x - dy, y - dx)
+        };
+
+        // Walls north and south, the way through runs east-west
+        let east_west = Self::is_wall(build_data, x, y - 1)
+            && Self::is_wall(build_data, x, y + 1)
+            && Self::is_open(build_data, x - 1, y)
+            && Self::is_open(build_data, x + 1, y)
+            && (opens_wide(x - 1, y, 1, 0) || opens_wide(x + 1, y, 1, 0));
+        // Walls west and east, the way through runs north-south
+        let north_south = Self::is_wall(build_data, x - 1, y)
+            && Self::is_wall(build_data, x + 1, y)
+            && Self::is_open(build_data, x, y - 1)
+            && Self::is_open(build_data, x, y + 1)
+            && (opens_wide(x, y - 1, 0, 1) || opens_wide(x, y + 1, 0, 1));
+        east_west || north_south
+    }
+}
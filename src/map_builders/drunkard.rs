@@ -1,12 +1,10 @@
-use std::collections::HashMap;
-
 use rltk::RandomNumberGenerator;
 
-use crate::{spawner, Map, Position, TileType, SHOW_MAPGEN_VISUALIZER};
+use crate::{Position, TileType};
 
 use super::{
-    common::{self, paint, Symmetry},
-    MapBuilder,
+    common::{paint, Symmetry},
+    BuilderMap, InitialMapBuilder,
 };
 
 /// Defines whether the drunkards are spawned from the center of the map or a random position.
@@ -31,124 +29,68 @@ pub struct DrunkardSettings {
 }
 
 pub struct DrunkardsWalkBuilder {
-    map: Map,
-    starting_position: Position,
-    depth: i32,
-    history: Vec<Map>,
-    noise_areas: HashMap<i32, Vec<usize>>,
     settings: DrunkardSettings,
 }
 
-impl MapBuilder for DrunkardsWalkBuilder {
-    fn get_map(&mut self) -> Map {
-        self.map.clone()
-    }
-
-    fn get_starting_position(&mut self) -> Position {
-        self.starting_position.clone()
-    }
-
-    fn get_snapshot_history(&self) -> Vec<Map> {
-        self.history.clone()
-    }
-
-    fn build_map(&mut self) {
-        self.build();
-    }
-
-    fn spawn_entities(&mut self, ecs: &mut specs::World) {
-        for area in self.noise_areas.iter() {
-            spawner::spawn_region(ecs, area.1, self.depth);
-        }
-    }
-
-    fn take_snapshot(&mut self) {
-        if SHOW_MAPGEN_VISUALIZER {
-            // stores a copy of the map while making all tiles visible
-            let mut snapshot = self.map.clone();
-            for v in snapshot.revealed_tiles.iter_mut() {
-                *v = true;
-            }
-            self.history.push(snapshot);
-        }
+impl InitialMapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build(rng, build_data);
     }
 }
 
 impl DrunkardsWalkBuilder {
-    pub fn new(new_depth: i32, settings: DrunkardSettings) -> DrunkardsWalkBuilder {
-        DrunkardsWalkBuilder {
-            map: Map::new(new_depth),
-            starting_position: Position { x: 0, y: 0 },
-            depth: new_depth,
-            history: Vec::new(),
-            noise_areas: HashMap::new(),
-            settings,
-        }
+    pub fn new(settings: DrunkardSettings) -> Box<DrunkardsWalkBuilder> {
+        Box::new(DrunkardsWalkBuilder { settings })
     }
 
-    pub fn open_area(new_depth: i32) -> DrunkardsWalkBuilder {
-        Self::new(
-            new_depth,
-            DrunkardSettings {
-                spawn_mode: DrunkSpawnMode::StartingPoint,
-                drunken_lifetime: 400,
-                floor_percent: 0.5,
-                brush_size: 1,
-                symmetry: Symmetry::None,
-            },
-        )
+    pub fn open_area() -> Box<DrunkardsWalkBuilder> {
+        Self::new(DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::StartingPoint,
+            drunken_lifetime: 400,
+            floor_percent: 0.5,
+            brush_size: 1,
+            symmetry: Symmetry::None,
+        })
     }
 
-    pub fn open_halls(new_depth: i32) -> DrunkardsWalkBuilder {
-        Self::new(
-            new_depth,
-            DrunkardSettings {
-                spawn_mode: DrunkSpawnMode::Random,
-                drunken_lifetime: 400,
-                floor_percent: 0.5,
-                brush_size: 1,
-                symmetry: Symmetry::None,
-            },
-        )
+    pub fn open_halls() -> Box<DrunkardsWalkBuilder> {
+        Self::new(DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::Random,
+            drunken_lifetime: 400,
+            floor_percent: 0.5,
+            brush_size: 1,
+            symmetry: Symmetry::None,
+        })
     }
 
-    pub fn widening_passages(new_depth: i32) -> DrunkardsWalkBuilder {
-        Self::new(
-            new_depth,
-            DrunkardSettings {
-                spawn_mode: DrunkSpawnMode::Random,
-                drunken_lifetime: 100,
-                floor_percent: 0.4,
-                brush_size: 1,
-                symmetry: Symmetry::None,
-            },
-        )
+    pub fn widening_passages() -> Box<DrunkardsWalkBuilder> {
+        Self::new(DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::Random,
+            drunken_lifetime: 100,
+            floor_percent: 0.4,
+            brush_size: 1,
+            symmetry: Symmetry::None,
+        })
     }
 
-    pub fn wider_passages(new_depth: i32) -> DrunkardsWalkBuilder {
-        Self::new(
-            new_depth,
-            DrunkardSettings {
-                spawn_mode: DrunkSpawnMode::Random,
-                drunken_lifetime: 100,
-                floor_percent: 0.4,
-                brush_size: 2,
-                symmetry: Symmetry::None,
-            },
-        )
+    pub fn wider_passages() -> Box<DrunkardsWalkBuilder> {
+        Self::new(DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::Random,
+            drunken_lifetime: 100,
+            floor_percent: 0.4,
+            brush_size: 2,
+            symmetry: Symmetry::None,
+        })
     }
 
-    pub fn fearful_symmetry(new_depth: i32) -> DrunkardsWalkBuilder {
-        Self::new(
-            new_depth,
-            DrunkardSettings {
-                spawn_mode: DrunkSpawnMode::Random,
-                drunken_lifetime: 100,
-                floor_percent: 0.4,
-                brush_size: 1,
-                symmetry: Symmetry::Both,
-            },
-        )
+    pub fn fearful_symmetry() -> Box<DrunkardsWalkBuilder> {
+        Self::new(DrunkardSettings {
+            spawn_mode: DrunkSpawnMode::Random,
+            drunken_lifetime: 100,
+            floor_percent: 0.4,
+            brush_size: 1,
+            symmetry: Symmetry::Both,
+        })
     }
 
     /// The basic idea behind the algorithm is simple:
@@ -164,21 +106,19 @@ impl DrunkardsWalkBuilder {
     ///             If we rolled a 4, move the drunkard West.
     ///         The tile on which the drunkard landed becomes a floor.
     #[allow(clippy::map_entry)]
-    fn build(&mut self) {
-        let mut rng = RandomNumberGenerator::new();
-
+    fn build(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
         // Set a central starting point
-        self.starting_position = Position {
-            x: self.map.width / 2,
-            y: self.map.height / 2,
+        let starting_position = Position {
+            x: build_data.map.width / 2,
+            y: build_data.map.height / 2,
         };
-        let start_idx = self
+        let start_idx = build_data
             .map
-            .xy_idx(self.starting_position.x, self.starting_position.y);
+            .xy_idx(starting_position.x, starting_position.y);
 
-        self.map.tiles[start_idx] = TileType::Floor;
+        build_data.map.tiles[start_idx] = TileType::Floor;
 
-        let total_tiles = self.map.width * self.map.height;
+        let total_tiles = build_data.map.width * build_data.map.height;
         let desired_floor_tiles = (total_tiles as f32 * self.settings.floor_percent) as usize;
         let mut floor_tile_count = 1; // The starting position one
         let mut digger_count = 0;
@@ -192,38 +132,38 @@ impl DrunkardsWalkBuilder {
 
             match self.settings.spawn_mode {
                 DrunkSpawnMode::StartingPoint => {
-                    drunk_x = self.starting_position.x;
-                    drunk_y = self.starting_position.y;
+                    drunk_x = starting_position.x;
+                    drunk_y = starting_position.y;
                 }
                 DrunkSpawnMode::Random => {
-                    drunk_x = rng.roll_dice(1, self.map.width - 3) + 1;
-                    drunk_y = rng.roll_dice(1, self.map.height - 3) + 1;
+                    drunk_x = rng.roll_dice(1, build_data.map.width - 3) + 1;
+                    drunk_y = rng.roll_dice(1, build_data.map.height - 3) + 1;
                 }
             }
 
             while drunk_lifetime > 0 {
-                let drunk_pos_id = self.map.xy_idx(drunk_x, drunk_y);
-                if self.map.tiles[drunk_pos_id] == TileType::Wall {
+                let drunk_pos_id = build_data.map.xy_idx(drunk_x, drunk_y);
+                if build_data.map.tiles[drunk_pos_id] == TileType::Wall {
                     did_something = true;
                 }
 
                 // We keep the double draw so the Downstairs symbol still shows the drunkard's
                 // path, while applying the symmetry feature
                 paint(
-                    &mut self.map,
+                    &mut build_data.map,
                     self.settings.symmetry,
                     self.settings.brush_size,
                     drunk_x,
                     drunk_y,
                 );
-                self.map.tiles[drunk_pos_id] = TileType::DownStairs;
+                build_data.map.tiles[drunk_pos_id] = TileType::DownStairs;
 
                 let rolled_direction = rng.roll_dice(1, 4);
                 match rolled_direction {
                     1 if drunk_x > 2 => drunk_x -= 1,
-                    2 if drunk_x < self.map.width - 2 => drunk_x += 1,
+                    2 if drunk_x < build_data.map.width - 2 => drunk_x += 1,
                     3 if drunk_y > 2 => drunk_y -= 1,
-                    4 if drunk_y < self.map.height - 2 => drunk_y += 1,
+                    4 if drunk_y < build_data.map.height - 2 => drunk_y += 1,
                     _ => {}
                 }
 
@@ -231,17 +171,17 @@ impl DrunkardsWalkBuilder {
             }
 
             if did_something {
-                self.take_snapshot();
+                build_data.take_snapshot();
                 active_digger_count += 1;
             }
 
             digger_count += 1;
-            for t in self.map.tiles.iter_mut() {
+            for t in build_data.map.tiles.iter_mut() {
                 if *t == TileType::DownStairs {
                     *t = TileType::Floor;
                 }
             }
-            floor_tile_count = self
+            floor_tile_count = build_data
                 .map
                 .tiles
                 .iter()
@@ -252,16 +192,5 @@ impl DrunkardsWalkBuilder {
             "{} dwarves gave up their sobriety, of whom {} actually found a wall.",
             digger_count, active_digger_count
         ));
-
-        let exit_tile_idx =
-            common::cull_unreachables_and_return_most_distant_tile(&mut self.map, start_idx);
-        self.take_snapshot();
-
-        // Place the stairs
-        self.map.tiles[exit_tile_idx] = TileType::DownStairs;
-        self.take_snapshot();
-
-        // Now build a noise map for use later when spawning entities
-        self.noise_areas = common::generate_voronoi_spawn_regions(&self.map, &mut rng);
     }
 }
@@ -1,54 +1,14 @@
-use std::collections::HashMap;
+use crate::Position;
 
-use crate::{spawner, Position, SHOW_MAPGEN_VISUALIZER};
-
-use super::common::cull_unreachables_and_return_most_distant_tile;
-use super::common::generate_voronoi_spawn_regions;
-use super::MapBuilder;
-use super::{Map, TileType};
+use super::TileType;
+use super::{BuilderMap, InitialMapBuilder};
 use rltk::RandomNumberGenerator;
-use specs::World;
-
-pub struct MazeBuilder {
-    map: Map,
-    starting_position: Position,
-    depth: i32,
-    history: Vec<Map>,
-    noise_areas: HashMap<i32, Vec<usize>>,
-}
-
-impl MapBuilder for MazeBuilder {
-    fn build_map(&mut self) {
-        self.build();
-    }
-
-    fn spawn_entities(&mut self, ecs: &mut World) {
-        for area in self.noise_areas.iter().skip(1) {
-            spawner::spawn_region(ecs, area.1, self.depth);
-        }
-    }
-
-    fn get_map(&mut self) -> Map {
-        self.map.clone()
-    }
 
-    fn get_starting_position(&mut self) -> Position {
-        self.starting_position.clone()
-    }
+pub struct MazeBuilder {}
 
-    fn get_snapshot_history(&self) -> Vec<Map> {
-        self.history.clone()
-    }
-
-    fn take_snapshot(&mut self) {
-        if SHOW_MAPGEN_VISUALIZER {
-            // stores a copy of the map while making all tiles visible
-            let mut snapshot = self.map.clone();
-            for v in snapshot.revealed_tiles.iter_mut() {
-                *v = true;
-            }
-            self.history.push(snapshot);
-        }
+impl InitialMapBuilder for MazeBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        MazeGrid::new_from_map(build_data, rng);
     }
 }
 
@@ -60,38 +20,8 @@ const EAST: u8 = 0b00001000;
 const CELL_COUNT_BEFORE_SNAPSHOT: u64 = 20;
 
 impl MazeBuilder {
-    pub fn new(new_depth: i32) -> MazeBuilder {
-        MazeBuilder {
-            map: Map::new(new_depth),
-            starting_position: Position { x: 0, y: 0 },
-            depth: new_depth,
-            history: Vec::new(),
-            noise_areas: HashMap::new(),
-        }
-    }
-
-    fn build(&mut self) {
-        let mut rng = RandomNumberGenerator::new();
-
-        MazeGrid::new_from_map(self, &mut rng);
-
-        // Since we start at (0, 0) in the grid which is half our map size, we start at 2 * (0, 0) + (1, 1) = (1, 1) in
-        // the actual map
-        self.starting_position = Position { x: 1, y: 1 };
-        let start_idx = self
-            .map
-            .xy_idx(self.starting_position.x, self.starting_position.y);
-
-        // // Find all tiles we can reach from the starting point
-        let exit_tile = cull_unreachables_and_return_most_distant_tile(&mut self.map, start_idx);
-        self.take_snapshot();
-
-        // // Place the stairs
-        self.map.tiles[exit_tile] = TileType::DownStairs;
-        self.take_snapshot();
-
-        // // Now build a noise map for use in spawning entities later
-        self.noise_areas = generate_voronoi_spawn_regions(&self.map, &mut rng);
+    pub fn new() -> Box<MazeBuilder> {
+        Box::new(MazeBuilder {})
     }
 }
 
@@ -110,9 +40,9 @@ impl MazeGrid {
     // The following maze generation assumes that walls are tiles that surround the actual
     // tiles. So we'll run it on a grid half the size of the actual map and we then double the
     // resolution assuming all other tiles are walls.
-    fn new_from_map(builder: &mut MazeBuilder, rng: &mut RandomNumberGenerator) -> Self {
-        let grid_width = (builder.map.width - 2) / 2;
-        let grid_height = (builder.map.height - 2) / 2;
+    fn new_from_map(build_data: &mut BuilderMap, rng: &mut RandomNumberGenerator) -> Self {
+        let grid_width = (build_data.map.width - 2) / 2;
+        let grid_height = (build_data.map.height - 2) / 2;
         let grid_size = (grid_width * grid_height) as usize;
 
         let walls: Vec<u8> = vec![0; grid_size];
@@ -124,7 +54,7 @@ impl MazeGrid {
             is_visited,
         };
 
-        grid.generate(builder, rng)
+        grid.generate(build_data, rng)
     }
 
     #[inline]
@@ -211,7 +141,7 @@ impl MazeGrid {
         self.is_visited = vec![false; grid_size];
     }
 
-    fn generate(mut self, builder: &mut MazeBuilder, rng: &mut RandomNumberGenerator) -> Self {
+    fn generate(mut self, build_data: &mut BuilderMap, rng: &mut RandomNumberGenerator) -> Self {
         self.reset();
         let mut cell_count: u64 = 0;
         let start_pos = Position { x: 0, y: 0 };
@@ -226,28 +156,28 @@ impl MazeGrid {
                 self.connect_tiles(current_pos, neigh);
 
                 // add 1 for padding so we don't write over borders
-                let map_idx = builder
+                let map_idx = build_data
                     .map
                     .xy_idx(current_pos.x * 2 + 1, current_pos.y * 2 + 1);
 
-                builder.map.tiles[map_idx] = TileType::Floor;
+                build_data.map.tiles[map_idx] = TileType::Floor;
                 // account for tore down walls
                 if self.walls[idx as usize] & NORTH != 0 {
-                    builder.map.tiles[map_idx - builder.map.width as usize] = TileType::Floor;
+                    build_data.map.tiles[map_idx - build_data.map.width as usize] = TileType::Floor;
                 }
                 if self.walls[idx as usize] & SOUTH != 0 {
-                    builder.map.tiles[map_idx + builder.map.width as usize] = TileType::Floor;
+                    build_data.map.tiles[map_idx + build_data.map.width as usize] = TileType::Floor;
                 }
                 if self.walls[idx as usize] & WEST != 0 {
-                    builder.map.tiles[map_idx - 1] = TileType::Floor;
+                    build_data.map.tiles[map_idx - 1] = TileType::Floor;
                 }
                 if self.walls[idx as usize] & EAST != 0 {
-                    builder.map.tiles[map_idx + 1] = TileType::Floor;
+                    build_data.map.tiles[map_idx + 1] = TileType::Floor;
                 }
 
                 cell_count += 1;
                 if cell_count % CELL_COUNT_BEFORE_SNAPSHOT == 0 {
-                    builder.take_snapshot();
+                    build_data.take_snapshot();
                 }
             } else {
                 stack.pop();
@@ -1,4 +1,4 @@
-use crate::Position;
+use crate::{spawner, Position, SHOW_MAPGEN_VISUALIZER};
 
 use super::{Map, Rect, TileType};
 
@@ -19,41 +19,263 @@ mod maze;
 use maze::MazeBuilder;
 mod voronoi_cell;
 use voronoi_cell::VoronoiCellBuilder;
+mod town;
+use town::TownBuilder;
 mod wave_function_collapse;
 use specs::World;
 use wave_function_collapse::WaveFunctionCollapseBuilder;
 mod common;
 
-pub trait MapBuilder {
-    fn build_map(&mut self);
-    fn spawn_entities(&mut self, ecs: &mut World);
-    fn get_map(&mut self) -> Map;
-    fn get_starting_position(&mut self) -> Position;
-    fn get_snapshot_history(&self) -> Vec<Map>;
-    fn take_snapshot(&mut self);
-}
-
-pub fn random_builder(new_depth: i32) -> Box<dyn MapBuilder> {
-    let mut rng = RandomNumberGenerator::new();
-    // let builder = rng.roll_dice(1, 14);
-    // match builder {
-    //     1 => Box::new(BspDungeonBuilder::new(new_depth)),
-    //     2 => Box::new(BspInteriorBuilder::new(new_depth)),
-    //     3 => Box::new(CellularAutomataBuilder::new(new_depth)),
-    //     4 => Box::new(DrunkardsWalkBuilder::open_area(new_depth)),
-    //     5 => Box::new(DrunkardsWalkBuilder::open_halls(new_depth)),
-    //     6 => Box::new(DrunkardsWalkBuilder::widening_passages(new_depth)),
-    //     7 => Box::new(DrunkardsWalkBuilder::wider_passages(new_depth)),
-    //     8 => Box::new(DrunkardsWalkBuilder::fearful_symmetry(new_depth)),
-    //     9 => Box::new(MazeBuilder::new(new_depth)),
-    //     10 => Box::new(DLABuilder::walk_inwards(new_depth)),
-    //     11 => Box::new(DLABuilder::walk_outwards(new_depth)),
-    //     12 => Box::new(DLABuilder::central_attractor(new_depth)),
-    //     13 => Box::new(DLABuilder::insectoid(new_depth)),
-    //     14 => Box::new(VoronoiCellBuilder::pythagoras(new_depth)),
-    //     15 => Box::new(VoronoiCellBuilder::manhattan(new_depth)),
-    //     16 => Box::new(VoronoiCellBuilder::chebyshev(new_depth)),
-    //     _ => Box::new(SimpleMapBuilder::new(new_depth))
-    // }
-    Box::new(WaveFunctionCollapseBuilder::new(new_depth))
+// Meta builders
+mod area_starting_points;
+use area_starting_points::{AreaStartingPosition, XStart, YStart};
+mod cull_unreachable;
+use cull_unreachable::CullUnreachable;
+mod distant_exit;
+use distant_exit::DistantExit;
+mod door_placement;
+use door_placement::DoorPlacement;
+mod room_based_spawner;
+use room_based_spawner::RoomBasedSpawner;
+mod room_based_stairs;
+use room_based_stairs::RoomBasedStairs;
+mod room_based_starting_position;
+use room_based_starting_position::RoomBasedStartingPosition;
+mod voronoi_spawning;
+use voronoi_spawning::VoronoiSpawning;
+
+/// The state shared by every step of a [BuilderChain]
+pub struct BuilderMap {
+    /// Map index and name of every entity to spawn once the map is built
+    pub spawn_list: Vec<(usize, String)>,
+    pub map: Map,
+    pub starting_position: Option<Position>,
+    /// Only set by room based builders
+    pub rooms: Option<Vec<Rect>>,
+    pub history: Vec<Map>,
+}
+
+impl BuilderMap {
+    fn take_snapshot(&mut self) {
+        if SHOW_MAPGEN_VISUALIZER {
+            // stores a copy of the map while making all tiles visible
+            let mut snapshot = self.map.clone();
+            for v in snapshot.revealed_tiles.iter_mut() {
+                *v = true;
+            }
+            self.history.push(snapshot);
+        }
+    }
+}
+
+/// Builds a map by running one [InitialMapBuilder] followed by any number of [MetaMapBuilder]s,
+/// all of them sharing the same [BuilderMap].
+pub struct BuilderChain {
+    starter: Option<Box<dyn InitialMapBuilder>>,
+    builders: Vec<Box<dyn MetaMapBuilder>>,
+    pub build_data: BuilderMap,
+}
+
+impl BuilderChain {
+    /// A chain building a `width` by `height` level at `new_depth`
+    pub fn new(new_depth: i32, width: i32, height: i32) -> BuilderChain {
+        BuilderChain {
+            starter: None,
+            builders: Vec::new(),
+            build_data: BuilderMap {
+                spawn_list: Vec::new(),
+                map: Map::new(new_depth, width, height),
+                starting_position: None,
+                rooms: None,
+                history: Vec::new(),
+            },
+        }
+    }
+
+    pub fn start_with(&mut self, starter: Box<dyn InitialMapBuilder>) {
+        match self.starter {
+            None => self.starter = Some(starter),
+            Some(_) => panic!("You can only have one starting builder."),
+        };
+    }
+
+    pub fn with(&mut self, metabuilder: Box<dyn MetaMapBuilder>) {
+        self.builders.push(metabuilder);
+    }
+
+    pub fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        match &mut self.starter {
+            None => panic!("Cannot run a map builder chain without a starting build system"),
+            Some(starter) => {
+                // Build the starting map
+                starter.build_map(rng, &mut self.build_data);
+            }
+        }
+
+        // Build additional layers in turn
+        for metabuilder in self.builders.iter_mut() {
+            metabuilder.build_map(rng, &mut self.build_data);
+        }
+    }
+
+    pub fn spawn_entities(&mut self, ecs: &mut World, rng: &mut RandomNumberGenerator) {
+        for entity in self.build_data.spawn_list.iter() {
+            spawner::spawn_entity(ecs, &(&entity.0, &entity.1), rng);
+        }
+    }
+}
+
+/// Creates the map from scratch, it's always the first step of a [BuilderChain]
+pub trait InitialMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap);
+}
+
+/// Modifies an already built map (placing stairs, spawning entities, etc.)
+pub trait MetaMapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap);
+}
+
+/// Room based builders get their start, exit and spawns from the generated rooms, and doors where
+/// the corridors reach them
+fn room_builder_chain(chain: &mut BuilderChain, starter: Box<dyn InitialMapBuilder>) {
+    chain.start_with(starter);
+    chain.with(RoomBasedSpawner::new());
+    chain.with(RoomBasedStartingPosition::new());
+    chain.with(RoomBasedStairs::new());
+    chain.with(DoorPlacement::new());
+}
+
+/// Area based builders start close to the given point, drop whatever can't be reached from there,
+/// put the exit as far away as they can and spawn entities on noise generated regions
+fn area_builder_chain(
+    chain: &mut BuilderChain,
+    starter: Box<dyn InitialMapBuilder>,
+    x_start: XStart,
+    y_start: YStart,
+) {
+    chain.start_with(starter);
+    chain.with(AreaStartingPosition::new(x_start, y_start));
+    chain.with(CullUnreachable::new());
+    chain.with(DistantExit::new());
+    chain.with(VoronoiSpawning::new());
+}
+
+/// Size of the levels that fit the screen exactly
+const STANDARD_SIZE: (i32, i32) = (80, 43);
+/// Caverns sprawl in every direction and mazes run long, the camera scrolls over both
+const CAVERN_SIZE: (i32, i32) = (120, 80);
+const MAZE_SIZE: (i32, i32) = (160, 43);
+
+/// The builder chain for the level at `new_depth`: the town on top, random dungeons below it
+pub fn level_builder(new_depth: i32, rng: &mut RandomNumberGenerator) -> BuilderChain {
+    if new_depth == 0 {
+        let (width, height) = STANDARD_SIZE;
+        let mut builder = BuilderChain::new(new_depth, width, height);
+        builder.start_with(TownBuilder::new());
+        builder.with(DoorPlacement::new());
+        return builder;
+    }
+    random_builder(new_depth, rng)
+}
+
+pub fn random_builder(new_depth: i32, rng: &mut RandomNumberGenerator) -> BuilderChain {
+    let roll = rng.roll_dice(1, 18);
+    let (width, height) = match roll {
+        3..=5 => CAVERN_SIZE,
+        9 => MAZE_SIZE,
+        _ => STANDARD_SIZE,
+    };
+    let mut builder = BuilderChain::new(new_depth, width, height);
+    match roll {
+        1 => room_builder_chain(&mut builder, BspDungeonBuilder::new()),
+        2 => room_builder_chain(&mut builder, BspInteriorBuilder::new()),
+        3 => area_builder_chain(
+            &mut builder,
+            CellularAutomataBuilder::new(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        4 => area_builder_chain(
+            &mut builder,
+            DrunkardsWalkBuilder::open_area(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        5 => area_builder_chain(
+            &mut builder,
+            DrunkardsWalkBuilder::open_halls(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        6 => area_builder_chain(
+            &mut builder,
+            DrunkardsWalkBuilder::widening_passages(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        7 => area_builder_chain(
+            &mut builder,
+            DrunkardsWalkBuilder::wider_passages(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        8 => area_builder_chain(
+            &mut builder,
+            DrunkardsWalkBuilder::fearful_symmetry(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        9 => area_builder_chain(&mut builder, MazeBuilder::new(), XStart::Left, YStart::Top),
+        10 => area_builder_chain(
+            &mut builder,
+            DLABuilder::walk_inwards(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        11 => area_builder_chain(
+            &mut builder,
+            DLABuilder::walk_outwards(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        12 => area_builder_chain(
+            &mut builder,
+            DLABuilder::central_attractor(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        13 => area_builder_chain(
+            &mut builder,
+            DLABuilder::insectoid(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        14 => area_builder_chain(
+            &mut builder,
+            VoronoiCellBuilder::pythagoras(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        15 => area_builder_chain(
+            &mut builder,
+            VoronoiCellBuilder::manhattan(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        16 => area_builder_chain(
+            &mut builder,
+            VoronoiCellBuilder::chebyshev(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        17 => area_builder_chain(
+            &mut builder,
+            WaveFunctionCollapseBuilder::new(),
+            XStart::Center,
+            YStart::Center,
+        ),
+        _ => room_builder_chain(&mut builder, SimpleMapBuilder::new()),
+    }
+
+    builder
 }
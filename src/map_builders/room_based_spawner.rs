@@ -0,0 +1,33 @@
+use rltk::RandomNumberGenerator;
+
+use crate::spawner;
+
+use super::{BuilderMap, MetaMapBuilder};
+
+/// Spawns entities in every room but the first one (where the player starts)
+pub struct RoomBasedSpawner {}
+
+impl MetaMapBuilder for RoomBasedSpawner {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let rooms = build_data
+            .rooms
+            .as_ref()
+            .expect("Room Based Spawning only works after rooms have been created");
+
+        for room in rooms.iter().skip(1) {
+            spawner::spawn_room(
+                &build_data.map,
+                rng,
+                room,
+                build_data.map.depth,
+                &mut build_data.spawn_list,
+            );
+        }
+    }
+}
+
+impl RoomBasedSpawner {
+    pub fn new() -> Box<RoomBasedSpawner> {
+        Box::new(RoomBasedSpawner {})
+    }
+}
@@ -0,0 +1,28 @@
+use rltk::RandomNumberGenerator;
+
+use crate::TileType;
+
+use super::{BuilderMap, MetaMapBuilder};
+
+/// Places the down stairs at the center of the last room
+pub struct RoomBasedStairs {}
+
+impl MetaMapBuilder for RoomBasedStairs {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let rooms = build_data
+            .rooms
+            .as_ref()
+            .expect("Room Based Stairs only works after rooms have been created");
+
+        let stairs_position = rooms[rooms.len() - 1].center();
+        let stairs_idx = build_data.map.xy_idx(stairs_position.0, stairs_position.1);
+        build_data.map.tiles[stairs_idx] = TileType::DownStairs;
+        build_data.take_snapshot();
+    }
+}
+
+impl RoomBasedStairs {
+    pub fn new() -> Box<RoomBasedStairs> {
+        Box::new(RoomBasedStairs {})
+    }
+}
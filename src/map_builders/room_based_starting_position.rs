@@ -0,0 +1,29 @@
+use rltk::RandomNumberGenerator;
+
+use crate::Position;
+
+use super::{BuilderMap, MetaMapBuilder};
+
+/// Sets the starting position at the center of the first room
+pub struct RoomBasedStartingPosition {}
+
+impl MetaMapBuilder for RoomBasedStartingPosition {
+    fn build_map(&mut self, _rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let rooms = build_data
+            .rooms
+            .as_ref()
+            .expect("Room Based Starting Position only works after rooms have been created");
+
+        let start_pos = rooms[0].center();
+        build_data.starting_position = Some(Position {
+            x: start_pos.0,
+            y: start_pos.1,
+        });
+    }
+}
+
+impl RoomBasedStartingPosition {
+    pub fn new() -> Box<RoomBasedStartingPosition> {
+        Box::new(RoomBasedStartingPosition {})
+    }
+}
@@ -0,0 +1,245 @@
+use rltk::RandomNumberGenerator;
+
+use crate::Position;
+
+use super::{common, BuilderMap, InitialMapBuilder, Rect, TileType};
+
+/// Half the width of the road running from the west edge of the town to the dungeon entrance
+const ROAD_HALF_WIDTH: i32 = 2;
+/// Tiles kept clear of buildings at both ends of the road, where the player arrives and leaves
+const TOWN_GATE_SPACE: i32 = 6;
+const MAX_BUILDINGS: usize = 12;
+const BUILDING_ATTEMPTS: i32 = 200;
+const MIN_BUILDING_WIDTH: i32 = 7;
+const MAX_BUILDING_WIDTH: i32 = 14;
+const MIN_BUILDING_HEIGHT: i32 = 5;
+const MAX_BUILDING_HEIGHT: i32 = 9;
+/// Townsfolk strolling around the streets
+const STREET_WALKERS: i32 = 4;
+/// About one in this many of the grass tiles grows a tree
+const TREE_ONE_IN: i32 = 12;
+
+/// What a building of the town is used for, the biggest one is always the tavern
+#[derive(PartialEq, Copy, Clone)]
+enum BuildingKind {
+    Tavern,
+    Blacksmith,
+    Alchemist,
+    House,
+}
+
+/// The town above the dungeon (depth 0), where a new game starts: an east-west road bridging a
+/// river, with buildings on both sides of it and the dungeon entrance at its eastern end. Only
+/// townsfolk live there, so none of the dungeon spawns happen.
+pub struct TownBuilder {}
+
+impl InitialMapBuilder for TownBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        self.build_town(rng, build_data);
+    }
+}
+
+impl TownBuilder {
+    pub fn new() -> Box<TownBuilder> {
+        Box::new(TownBuilder {})
+    }
+
+    fn build_town(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let width = build_data.map.width;
+        let height = build_data.map.height;
+        let road_y = height / 2;
+
+        // Grass everywhere but the town walls, and the road across
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let idx = build_data.map.xy_idx(x, y);
+                build_data.map.tiles[idx] = if i32::abs(y - road_y) <= ROAD_HALF_WIDTH {
+                    TileType::Road
+                } else {
+                    TileType::Grass
+                };
+            }
+        }
+        build_data.take_snapshot();
+
+        self.dig_river(rng, build_data, road_y);
+        let buildings = self.place_buildings(rng, build_data, road_y);
+        common::scatter_pillars(
+            &mut build_data.map,
+            rng,
+            TileType::Grass,
+            TileType::Tree,
+            TREE_ONE_IN,
+        );
+        build_data.take_snapshot();
+
+        // The player walks in from the west, the dungeon waits at the other end of the road
+        build_data.starting_position = Some(Position { x: 2, y: road_y });
+        let exit_idx = build_data.map.xy_idx(width - 3, road_y);
+        build_data.map.tiles[exit_idx] = TileType::DownStairs;
+        build_data.take_snapshot();
+
+        self.spawn_townsfolk(rng, build_data, &buildings, road_y);
+    }
+
+    /// Runs a river from the north edge of the town to the south one, somewhere in its middle third.
+    /// It's too deep to wade across but for its banks, so the road crosses it over a bridge.
+    fn dig_river(
+        &mut self,
+        rng: &mut RandomNumberGenerator,
+        build_data: &mut BuilderMap,
+        road_y: i32,
+    ) {
+        let width = build_data.map.width;
+        let height = build_data.map.height;
+        let mut river_x = rng.range(width / 3, width * 2 / 3);
+
+        for y in 1..height - 1 {
+            for dx in -2..=2 {
+                let idx = build_data.map.xy_idx(river_x + dx, y);
+                build_data.map.tiles[idx] = if i32::abs(y - road_y) <= ROAD_HALF_WIDTH {
+                    TileType::Bridge
+                } else if i32::abs(dx) == 2 {
+                    TileType::ShallowWater
+                } else {
+                    TileType::DeepWater
+                };
+            }
+            river_x = i32::clamp(river_x + rng.range(-1, 2), width / 3, width * 2 / 3);
+        }
+        build_data.take_snapshot();
+    }
+
+    /// Puts up buildings on the grass away from the road, each with its door facing it. They're
+    /// returned biggest first.
+    fn place_buildings(
+        &mut self,
+        rng: &mut RandomNumberGenerator,
+        build_data: &mut BuilderMap,
+        road_y: i32,
+    ) -> Vec<Rect> {
+        let width = build_data.map.width;
+        let height = build_data.map.height;
+        let mut buildings: Vec<Rect> = Vec::new();
+
+        for _attempt in 0..BUILDING_ATTEMPTS {
+            if buildings.len() == MAX_BUILDINGS {
+                break;
+            }
+            let w = rng.range(MIN_BUILDING_WIDTH, MAX_BUILDING_WIDTH);
+            let h = rng.range(MIN_BUILDING_HEIGHT, MAX_BUILDING_HEIGHT);
+            let x = rng.range(TOWN_GATE_SPACE, width - TOWN_GATE_SPACE - w);
+            let north_of_road = rng.range(0, 2) == 0;
+            let y = if north_of_road {
+                rng.range(2, road_y - ROAD_HALF_WIDTH - h)
+            } else {
+                rng.range(road_y + ROAD_HALF_WIDTH + 2, height - 2 - h)
+            };
+            let building = Rect::new(x, y, w, h);
+
+            // Leave a street between buildings (and the river) so every door can be reached
+            let footprint = Rect::new(x - 2, y - 2, w + 4, h + 4);
+            if buildings.iter().any(|other| footprint.intersect(other)) {
+                continue;
+            }
+            let on_dry_land = (footprint.y1..=footprint.y2).all(|fy| {
+                (footprint.x1..=footprint.x2).all(|fx| {
+                    let tile = build_data.map.tiles[build_data.map.xy_idx(fx, fy)];
+                    tile == TileType::Grass || tile == TileType::Road
+                })
+            });
+            if !on_dry_land {
+                continue;
+            }
+
+            for by in building.y1..=building.y2 {
+                for bx in building.x1..=building.x2 {
+                    let idx = build_data.map.xy_idx(bx, by);
+                    let edge = bx == building.x1
+                        || bx == building.x2
+                        || by == building.y1
+                        || by == building.y2;
+                    build_data.map.tiles[idx] = if edge {
+                        TileType::Wall
+                    } else {
+                        TileType::Floor
+                    };
+                }
+            }
+            let door_y = if north_of_road {
+                building.y2
+            } else {
+                building.y1
+            };
+            let door_idx = build_data.map.xy_idx(building.center().0, door_y);
+            build_data.map.tiles[door_idx] = TileType::Floor;
+
+            buildings.push(building);
+            build_data.take_snapshot();
+        }
+
+        let area = |b: &Rect| (b.x2 - b.x1) * (b.y2 - b.y1);
+        buildings.sort_by_key(|b| std::cmp::Reverse(area(b)));
+        buildings
+    }
+
+    /// Who lives where: the tavern gets its barkeep and patrons, the shops their owner and the
+    /// houses maybe someone at home
+    fn spawn_townsfolk(
+        &mut self,
+        rng: &mut RandomNumberGenerator,
+        build_data: &mut BuilderMap,
+        buildings: &[Rect],
+        road_y: i32,
+    ) {
+        for (i, building) in buildings.iter().enumerate() {
+            let kind = match i {
+                0 => BuildingKind::Tavern,
+                1 => BuildingKind::Blacksmith,
+                2 => BuildingKind::Alchemist,
+                _ => BuildingKind::House,
+            };
+            let mut residents: Vec<&str> = match kind {
+                BuildingKind::Tavern => vec!["Barkeep"],
+                BuildingKind::Blacksmith => vec!["Blacksmith"],
+                BuildingKind::Alchemist => vec!["Alchemist"],
+                BuildingKind::House if rng.range(0, 2) == 0 => vec!["Townsperson"],
+                BuildingKind::House => Vec::new(),
+            };
+            if kind == BuildingKind::Tavern {
+                for _i in 0..rng.roll_dice(1, 3) + 1 {
+                    residents.push("Patron");
+                }
+            }
+
+            let mut free_tiles: Vec<usize> = Vec::new();
+            for y in building.y1 + 1..building.y2 {
+                for x in building.x1 + 1..building.x2 {
+                    free_tiles.push(build_data.map.xy_idx(x, y));
+                }
+            }
+            for resident in residents {
+                if free_tiles.is_empty() {
+                    break;
+                }
+                let idx = free_tiles.remove(rng.range(0, free_tiles.len()));
+                build_data.spawn_list.push((idx, resident.to_string()));
+            }
+        }
+
+        // A few more out on the road
+        let width = build_data.map.width;
+        for _i in 0..STREET_WALKERS {
+            let x = rng.range(TOWN_GATE_SPACE, width - TOWN_GATE_SPACE);
+            let y = rng.range(road_y - ROAD_HALF_WIDTH, road_y + ROAD_HALF_WIDTH + 1);
+            let idx = build_data.map.xy_idx(x, y);
+            if build_data
+                .spawn_list
+                .iter()
+                .all(|(spawn_idx, _name)| *spawn_idx != idx)
+            {
+                build_data.spawn_list.push((idx, "Townsperson".to_string()));
+            }
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use rltk::RandomNumberGenerator;
+
+use crate::{spawner, TileType};
+
+use super::{common, BuilderMap, MetaMapBuilder};
+
+/// Splits the map in noise generated regions and spawns entities on each of them, except on the
+/// start and the stairs down
+pub struct VoronoiSpawning {}
+
+impl MetaMapBuilder for VoronoiSpawning {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator, build_data: &mut BuilderMap) {
+        let map = &build_data.map;
+        let start_idx = build_data
+            .starting_position
+            .as_ref()
+            .map(|pos| map.xy_idx(pos.x, pos.y));
+        let noise_areas = common::generate_voronoi_spawn_regions(map, rng);
+
+        for area in noise_areas.iter() {
+            let tiles: Vec<usize> = area
+                .1
+                .iter()
+                .copied()
+                .filter(|idx| Some(*idx) != start_idx && map.tiles[*idx] != TileType::DownStairs)
+                .collect();
+            spawner::spawn_region(rng, &tiles, map.depth, &mut build_data.spawn_list);
+        }
+    }
+}
+
+impl VoronoiSpawning {
+    pub fn new() -> Box<VoronoiSpawning> {
+        Box::new(VoronoiSpawning {})
+    }
+}
@@ -1,3 +1,4 @@
+
 use std::collections::HashSet;
 
 use crate::{Map, TileType};
@@ -110,8 +111,9 @@ pub fn build_patterns(
             "Pre de-duplication, there are {} patterns",
             patterns.len()
         ));
-        let patterns_set: HashSet<Vec<TileType>> = patterns.drain(..).collect();
-        patterns.extend(patterns_set.into_iter());
+        // Keeps the first occurrence of each pattern so the order doesn't depend on hashing
+        let mut seen: HashSet<Vec<TileType>> = HashSet::new();
+        patterns.retain(|pattern| seen.insert(pattern.clone()));
         rltk::console::log(format!(
             "Post de-duplication, there are {} patterns",
             patterns.len()
@@ -2,9 +2,14 @@ use rltk::XpFile;
 
 use crate::{Map, TileType};
 
-/// Loads a RexPaint file, and converts it to our [Map] format
+/// Loads a RexPaint file, and converts it to our [Map] format. The map is as big as the file's
+/// first layer.
 pub fn load_rex_map(new_depth: i32, xp_file: &XpFile) -> Map {
-    let mut map: Map = Map::new(new_depth);
+    let (width, height) = xp_file
+        .layers
+        .first()
+        .map_or((0, 0), |layer| (layer.width as i32, layer.height as i32));
+    let mut map: Map = Map::new(new_depth, width, height);
 
     for layer in &xp_file.layers {
         for y in 0..layer.height {
@@ -1,4 +1,4 @@
-use super::{BlocksTile, Map, Position};
+use super::{BlocksTile, BlocksVisibility, Door, Map, Position};
 use specs::prelude::*;
 
 pub struct MapIndexingSystem {}
@@ -8,19 +8,31 @@ impl<'a> System<'a> for MapIndexingSystem {
         WriteExpect<'a, Map>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, BlocksTile>,
+        ReadStorage<'a, BlocksVisibility>,
+        ReadStorage<'a, Door>,
         Entities<'a>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, position, blockers, entities) = data;
+        let (mut map, position, blockers, view_blockers, doors, entities) = data;
 
         map.populate_blocked();
         map.clear_content_index();
+        map.view_blocked.clear();
+        map.closed_doors.clear();
         for (entity, position) in (&entities, &position).join() {
             let idx = map.xy_idx(position.x, position.y);
 
-            // If they block, update the blocking list
-            map.blocked[idx] = blockers.get(entity).is_some();
+            // If they block, update the blocking list (without unblocking what others blocked)
+            if blockers.get(entity).is_some() {
+                map.blocked[idx] = true;
+            }
+            if view_blockers.get(entity).is_some() {
+                map.view_blocked.insert(idx);
+            }
+            if doors.get(entity).is_some_and(|door| !door.open) {
+                map.closed_doors.insert(idx);
+            }
 
             map.tile_content[idx].push(entity);
         }
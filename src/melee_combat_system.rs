@@ -1,10 +1,15 @@
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
-use crate::{HungerClock, HungerState};
+use crate::combat::{
+    armor_class, roll_to_hit, roll_weapon_damage, weapon_attribute_bonus, AttackRoll, UNARMED,
+};
+use crate::effects::{particle, EffectQueue, EffectType, Targets};
+use crate::{Attributes, Enchantment, HungerClock, HungerState, MeleeWeapon, Skills};
 
 use super::{
-    CombatStats, DefenseBonus, Equipped, GameLog, MeleePowerBonus, Name, ParticleBuilder, Position,
-    SufferDamage, WantsToMelee,
+    raws::{faction_reaction, Reaction, RAWS},
+    CombatStats, DefenseBonus, Equipped, Faction, GameLog, MeleePowerBonus, Name, WantsToMelee,
 };
 
 pub struct MeleeCombatSystem {}
@@ -17,13 +22,17 @@ impl<'a> System<'a> for MeleeCombatSystem {
         WriteStorage<'a, WantsToMelee>,
         ReadStorage<'a, Name>,
         ReadStorage<'a, CombatStats>,
-        WriteStorage<'a, SufferDamage>,
         ReadStorage<'a, MeleePowerBonus>,
         ReadStorage<'a, DefenseBonus>,
         ReadStorage<'a, Equipped>,
-        WriteExpect<'a, ParticleBuilder>,
-        ReadStorage<'a, Position>,
         ReadStorage<'a, HungerClock>,
+        ReadStorage<'a, Faction>,
+        WriteExpect<'a, EffectQueue>,
+        ReadStorage<'a, MeleeWeapon>,
+        ReadStorage<'a, Attributes>,
+        ReadStorage<'a, Skills>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadStorage<'a, Enchantment>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -33,28 +42,60 @@ impl<'a> System<'a> for MeleeCombatSystem {
             mut wants_melee,
             names,
             combat_stats,
-            mut inflict_damage,
             melee_power_bonuses,
             defense_bonuses,
             equipped,
-            mut particle_builder,
-            positions,
             hunger_clocks,
+            factions,
+            mut effect_queue,
+            melee_weapons,
+            attributes,
+            skills,
+            mut rng,
+            enchantments,
         ) = data;
 
+        let raws = RAWS.lock().unwrap();
+
         for (entity, wants_melee, name, stats) in
             (&entities, &wants_melee, &names, &combat_stats).join()
         {
+            // Only go through with attacks on factions we actually want to fight
+            if let (Some(mine), Some(theirs)) =
+                (factions.get(entity), factions.get(wants_melee.target))
+            {
+                if faction_reaction(&mine.name, &theirs.name, &raws) != Reaction::Attack {
+                    continue;
+                }
+            }
+
             if stats.hp > 0 {
                 let target_stats = combat_stats.get(wants_melee.target).unwrap();
                 if target_stats.hp > 0 {
-                    let mut offensive_bonus: i32 = (&entities, &melee_power_bonuses, &equipped)
+                    // The wielded weapon, else the attacker's natural attack, else bare hands
+                    let wielded = (&entities, &melee_weapons, &equipped)
                         .join()
-                        .filter(|(_item_entity, _power_bonus, equipped_by)| {
-                            equipped_by.owner == entity
+                        .find(|(_item_entity, _weapon, equipped_by)| equipped_by.owner == entity);
+                    let weapon = wielded
+                        .map(|(_item_entity, weapon, _equipped_by)| weapon)
+                        .or_else(|| melee_weapons.get(entity))
+                        .unwrap_or(&UNARMED);
+                    let enchantment_bonus = wielded
+                        .and_then(|(item_entity, _weapon, _equipped_by)| {
+                            enchantments.get(item_entity)
                         })
-                        .map(|(_item_entity, power_bonus, _equipped_by)| power_bonus.power)
-                        .sum();
+                        .map_or(0, |enchantment| enchantment.bonus);
+                    let attribute_bonus =
+                        weapon_attribute_bonus(attributes.get(entity), weapon.attribute);
+
+                    let mut offensive_bonus: i32 = enchantment_bonus
+                        + (&entities, &melee_power_bonuses, &equipped)
+                            .join()
+                            .filter(|(_item_entity, _power_bonus, equipped_by)| {
+                                equipped_by.owner == entity
+                            })
+                            .map(|(_item_entity, power_bonus, _equipped_by)| power_bonus.power)
+                            .sum::<i32>();
 
                     if let Some(hunger_clock) = hunger_clocks.get(entity) {
                         if hunger_clock.state == HungerState::WellFed {
@@ -67,37 +108,79 @@ impl<'a> System<'a> for MeleeCombatSystem {
                         .filter(|(_item_entity, _defense_bonus, equipped_by)| {
                             equipped_by.owner == wants_melee.target
                         })
-                        .map(|(_item_entity, defense_bonus, _equipped_by)| defense_bonus.defense)
+                        .map(|(item_entity, defense_bonus, _equipped_by)| {
+                            defense_bonus.defense
+                                + enchantments.get(item_entity).map_or(0, |e| e.bonus)
+                        })
                         .sum();
 
                     let target_name = names.get(wants_melee.target).unwrap();
 
-                    if let Some(pos) = positions.get(wants_melee.target) {
-                        particle_builder.request(
-                            pos.x,
-                            pos.y,
-                            rltk::RGB::named(rltk::ORANGE),
-                            rltk::RGB::named(rltk::BLACK),
-                            rltk::to_cp437('‼'),
-                            150.0,
-                        );
-                    }
-
-                    let damage = i32::max(
-                        0,
-                        (stats.power + offensive_bonus) - (target_stats.defense + defensive_bonus),
+                    let to_hit = attribute_bonus
+                        + weapon.hit_bonus
+                        + enchantment_bonus
+                        + skills.get(entity).map_or(0, |skills| skills.melee);
+                    let target_armor_class = armor_class(
+                        target_stats.defense + defensive_bonus,
+                        attributes.get(wants_melee.target),
+                        skills.get(wants_melee.target),
                     );
-                    if damage == 0 {
-                        log.entries.push(format!(
-                            "{}: Hmm, must have been the wind (Took 0 Damage)",
-                            target_name.name
-                        ));
-                    } else {
-                        log.entries.push(format!(
-                            "{} hits {}, for {} hp.",
-                            &name.name, &target_name.name, damage
-                        ));
-                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+
+                    let roll = roll_to_hit(&mut rng, to_hit, target_armor_class);
+                    match roll {
+                        AttackRoll::Fumble => {
+                            log.entries.push(format!(
+                                "{} fumbles the attack on {}.",
+                                &name.name, &target_name.name
+                            ));
+                        }
+                        AttackRoll::Miss => {
+                            log.entries.push(format!(
+                                "{} attacks {}, but misses.",
+                                &name.name, &target_name.name
+                            ));
+                            effect_queue.add(
+                                Some(entity),
+                                particle(rltk::CYAN, '‼', 150.0),
+                                Targets::Single {
+                                    target: wants_melee.target,
+                                },
+                            );
+                        }
+                        AttackRoll::Hit | AttackRoll::Critical => {
+                            let damage = i32::max(
+                                1,
+                                roll_weapon_damage(&mut rng, weapon, roll)
+                                    + attribute_bonus
+                                    + stats.power
+                                    + offensive_bonus,
+                            );
+                            if roll == AttackRoll::Critical {
+                                log.entries.push(format!(
+                                    "{} lands a critical hit on {}, for {} hp!",
+                                    &name.name, &target_name.name, damage
+                                ));
+                            } else {
+                                log.entries.push(format!(
+                                    "{} hits {}, for {} hp.",
+                                    &name.name, &target_name.name, damage
+                                ));
+                            }
+                            effect_queue.add(
+                                Some(entity),
+                                particle(rltk::ORANGE, '‼', 150.0),
+                                Targets::Single {
+                                    target: wants_melee.target,
+                                },
+                            );
+                            effect_queue.add(
+                                Some(entity),
+                                EffectType::Damage { amount: damage },
+                                Targets::Single {
+                                    target: wants_melee.target,
+                                },
+                            );
+                        }
                     }
                 }
             }
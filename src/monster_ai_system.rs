@@ -1,9 +1,27 @@
-use crate::EntityMoved;
+use crate::{
+    dijkstra_map_system::{downhill, DijkstraMaps},
+    effects::{particle, EffectQueue, Targets},
+    initiative_system::{weapon_cost, ACTION_COST},
+    ranged_combat_system::ranged_loadout,
+    ActionCost, Ammunition, Door, EntityMoved, Equipped, InBackpack, Item, Looter, MeleeWeapon,
+    RangedWeapon, Vendor, WantsToOpenDoor, WantsToPickupItem, WantsToShoot,
+};
 
-use super::{Confusion, Map, Monster, ParticleBuilder, Position, RunState, Viewshed, WantsToMelee};
-use rltk::Point;
+use super::{
+    raws::{faction_reaction, Reaction, RAWS},
+    CombatStats, Faction, Initiative, Map, Monster, MonsterAIState, MonsterBehaviour, MyTurn,
+    Position, RunState, StatusEffectKind, StatusEffects, Viewshed, WantsToMelee,
+};
+use rltk::{Algorithm2D, Point, RandomNumberGenerator};
 use specs::prelude::*;
 
+/// Below this fraction of their max HP monsters run away instead of fighting
+const FLEE_HP_FRACTION: f32 = 0.25;
+/// Monsters with something to shoot back off from enemies closer than this
+const ARCHER_SPACING: f32 = 3.0;
+/// Looters only go after items at most this far away
+const LOOTING_RANGE: f32 = 12.0;
+
 pub struct MonsterAI {}
 
 impl<'a> System<'a> for MonsterAI {
@@ -11,94 +29,326 @@ impl<'a> System<'a> for MonsterAI {
     type SystemData = (
         WriteExpect<'a, Map>,
         ReadExpect<'a, Point>,
-        ReadExpect<'a, Entity>,
         ReadExpect<'a, RunState>,
         Entities<'a>,
         WriteStorage<'a, Viewshed>,
         ReadStorage<'a, Monster>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
-        WriteStorage<'a, Confusion>,
-        WriteExpect<'a, ParticleBuilder>,
+        ReadStorage<'a, StatusEffects>,
+        WriteExpect<'a, EffectQueue>,
         WriteStorage<'a, EntityMoved>,
+        WriteStorage<'a, MonsterAIState>,
+        ReadStorage<'a, CombatStats>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, MyTurn>,
+        WriteStorage<'a, Initiative>,
+        (
+            ReadStorage<'a, Equipped>,
+            ReadStorage<'a, RangedWeapon>,
+            ReadStorage<'a, InBackpack>,
+            ReadStorage<'a, Ammunition>,
+            ReadStorage<'a, MeleeWeapon>,
+            ReadStorage<'a, ActionCost>,
+        ),
+        WriteStorage<'a, WantsToShoot>,
+        ReadStorage<'a, Vendor>,
+        ReadStorage<'a, Door>,
+        WriteStorage<'a, WantsToOpenDoor>,
+        WriteExpect<'a, DijkstraMaps>,
+        ReadStorage<'a, Looter>,
+        ReadStorage<'a, Item>,
+        WriteStorage<'a, WantsToPickupItem>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             mut map,
             player_pos,
-            player_entity,
             runstate,
             entities,
             mut viewshed,
             monster,
             mut position,
             mut wants_to_melee,
-            mut confused,
-            mut particle_builder,
+            statuses,
+            mut effect_queue,
             mut entity_moved,
+            mut ai_states,
+            combat_stats,
+            mut rng,
+            factions,
+            turns,
+            mut initiatives,
+            (equipped, ranged_weapons, backpacks, ammunition, melee_weapons, action_costs),
+            mut wants_to_shoot,
+            vendors,
+            doors,
+            mut wants_to_open,
+            mut dijkstra_maps,
+            looters,
+            items,
+            mut wants_to_pickup,
         ) = data;
 
-        if *runstate != RunState::MonsterTurn {
+        if *runstate != RunState::Ticking {
             return;
         }
 
-        for (entity, viewshed, _monster, pos) in
-            (&entities, &mut viewshed, &monster, &mut position).join()
+        let raws = RAWS.lock().unwrap();
+        let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+
+        for (entity, viewshed, _monster, pos, ai, _turn, initiative) in (
+            &entities,
+            &mut viewshed,
+            &monster,
+            &mut position,
+            &mut ai_states,
+            &turns,
+            &mut initiatives,
+        )
+            .join()
         {
+            // Whatever the monster does (even nothing) takes a whole action, attacks take as long
+            // as the weapon they're made with
+            let mut cost = ACTION_COST;
+
             let mut can_act = true;
 
-            if let Some(i_am_confused) = confused.get_mut(entity) {
-                i_am_confused.turns -= 1;
-                if i_am_confused.turns < 1 {
-                    confused.remove(entity);
-                }
+            let confused = statuses
+                .get(entity)
+                .is_some_and(|status| status.has(StatusEffectKind::Confusion));
+            if confused {
                 can_act = false;
 
-                particle_builder.request(
-                    pos.x,
-                    pos.y,
-                    rltk::RGB::named(rltk::MAGENTA),
-                    rltk::RGB::named(rltk::BLACK),
-                    rltk::to_cp437('?'),
-                    200.0,
+                effect_queue.add(
+                    Some(entity),
+                    particle(rltk::MAGENTA, '?', 200.0),
+                    Targets::Single { target: entity },
                 );
             }
 
-            if can_act {
-                if viewshed.visible_tiles.contains(&*player_pos) {
-                    let distance = rltk::DistanceAlg::Pythagoras
-                        .distance2d(Point::new(pos.x, pos.y), *player_pos);
-                    if distance < 1.5 {
-                        // Attack goes here
-                        wants_to_melee
+            if !can_act {
+                initiative.current += cost;
+                continue;
+            }
+
+            let badly_hurt = combat_stats
+                .get(entity)
+                .is_some_and(|stats| (stats.hp as f32) < stats.max_hp as f32 * FLEE_HP_FRACTION);
+
+            // Look around for enemies to go after and threats to run away from
+            let my_point = Point::new(pos.x, pos.y);
+            let mut closest_enemy: Option<(Entity, Point, f32)> = None;
+            let mut threats: Vec<usize> = Vec::new();
+            if let Some(my_faction) = factions.get(entity) {
+                for tile in viewshed.visible_tiles.iter() {
+                    let idx = map.xy_idx(tile.x, tile.y);
+                    for other in map.tile_content[idx].iter() {
+                        let their_faction = match factions.get(*other) {
+                            Some(faction) if *other != entity => faction,
+                            _ => continue,
+                        };
+
+                        match faction_reaction(&my_faction.name, &their_faction.name, &raws) {
+                            Reaction::Ignore => {}
+                            Reaction::Flee => threats.push(idx),
+                            Reaction::Attack => {
+                                let distance =
+                                    rltk::DistanceAlg::Pythagoras.distance2d(my_point, *tile);
+                                // Ties go to the lowest tile, then entity, as the visible tiles
+                                // come in no particular order
+                                let closer = closest_enemy.is_none_or(|closest| {
+                                    let closest_idx = map.xy_idx(closest.1.x, closest.1.y);
+                                    (distance, idx, other.id())
+                                        < (closest.2, closest_idx, closest.0.id())
+                                });
+                                if closer {
+                                    closest_enemy = Some((*other, *tile, distance));
+                                }
+                                if badly_hurt {
+                                    threats.push(idx);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            ai.behaviour =
+                next_behaviour(ai, closest_enemy.is_some(), !threats.is_empty(), &mut rng);
+            if let Some((_target, target_pos, _distance)) = closest_enemy {
+                ai.last_seen_target = Some(target_pos);
+            }
+
+            let my_idx = map.xy_idx(pos.x, pos.y);
+            let destination = match ai.behaviour {
+                MonsterBehaviour::Idle => None,
+                // Vendors mind their stall unless they have to run
+                MonsterBehaviour::Wander if vendors.contains(entity) => None,
+                MonsterBehaviour::Wander if looters.contains(entity) => {
+                    let item_here = map.tile_content[my_idx]
+                        .iter()
+                        .find(|other| items.contains(**other));
+                    if let Some(item) = item_here {
+                        wants_to_pickup
                             .insert(
                                 entity,
-                                WantsToMelee {
-                                    target: *player_entity,
+                                WantsToPickupItem {
+                                    collected_by: entity,
+                                    item: *item,
                                 },
                             )
+                            .expect("Unable to pick up item");
+                        None
+                    } else if dijkstra_maps.items[my_idx] <= LOOTING_RANGE {
+                        downhill(&map, &dijkstra_maps.items, my_idx)
+                    } else {
+                        random_step(&map, my_idx, &mut rng)
+                    }
+                }
+                MonsterBehaviour::Wander => random_step(&map, my_idx, &mut rng),
+                MonsterBehaviour::Chase => {
+                    let (target, target_pos, distance) =
+                        closest_enemy.expect("chasing without an enemy in sight");
+                    let loadout = ranged_loadout(
+                        &entities,
+                        entity,
+                        &equipped,
+                        &ranged_weapons,
+                        &backpacks,
+                        &ammunition,
+                    );
+                    // Shooters keep their distance, and only fire when nothing is in the way
+                    let retreat = match loadout {
+                        Some(_) if distance < ARCHER_SPACING => step_away(&map, my_idx, target_pos),
+                        _ => None,
+                    };
+                    let clear_shot = loadout.is_some_and(|loadout| {
+                        distance <= loadout.range as f32
+                            && map.projectile_path(my_point, target_pos).last() == Some(&target_pos)
+                    });
+                    if retreat.is_some() {
+                        retreat
+                    } else if clear_shot && distance >= 1.5 {
+                        wants_to_shoot
+                            .insert(entity, WantsToShoot { target: target_pos })
+                            .expect("Unable to insert shot");
+                        cost = weapon_cost(entity, &equipped, &ranged_weapons, &action_costs);
+                        None
+                    } else if distance < 1.5 {
+                        wants_to_melee
+                            .insert(entity, WantsToMelee { target })
                             .expect("Unable to insert attack");
-                        return;
+                        cost = weapon_cost(entity, &equipped, &melee_weapons, &action_costs);
+                        None
+                    } else if target_pos == *player_pos {
+                        // Everyone after the player shares one map, and they close in from all
+                        // sides rather than lining up behind each other
+                        downhill(&map, &dijkstra_maps.approach, my_idx)
+                    } else {
+                        let target_idx = map.xy_idx(target_pos.x, target_pos.y);
+                        downhill(&map, dijkstra_maps.towards(&map, target_idx), my_idx)
                     }
-
-                    // Path towards the player
-                    let path = rltk::a_star_search(
-                        map.xy_idx(pos.x, pos.y) as i32,
-                        map.xy_idx(player_pos.x, player_pos.y) as i32,
-                        &mut *map,
+                }
+                MonsterBehaviour::Flee if threats.iter().all(|threat| *threat == player_idx) => {
+                    downhill(&map, &dijkstra_maps.flee, my_idx)
+                }
+                MonsterBehaviour::Flee => {
+                    let flee_map = rltk::DijkstraMap::new(
+                        map.width as usize,
+                        map.height as usize,
+                        &threats,
+                        &*map,
+                        100.0,
                     );
-
-                    if path.success && path.steps.len() > 1 {
-                        pos.x = path.steps[1] as i32 % map.width;
-                        pos.y = path.steps[1] as i32 / map.width;
-                        entity_moved
-                            .insert(entity, EntityMoved {})
-                            .expect("Unable to insert EntityMoved marker");
-                        viewshed.dirty = true;
+                    rltk::DijkstraMap::find_highest_exit(&flee_map, my_idx, &*map)
+                }
+                MonsterBehaviour::Search => {
+                    let target = ai
+                        .last_seen_target
+                        .expect("searching without a last seen position");
+                    let target_idx = map.xy_idx(target.x, target.y);
+                    let step = downhill(&map, dijkstra_maps.towards(&map, target_idx), my_idx);
+                    if step.is_none() || step == Some(target_idx) {
+                        // Either we got there or there's no way to, so give up on the search
+                        ai.behaviour = MonsterBehaviour::Wander;
+                        ai.last_seen_target = None;
                     }
+                    step
+                }
+            };
+
+            // Someone else may have stepped in this same turn, in that case just wait. The player
+            // doesn't block their tile so it needs its own check.
+            if let Some(destination) = destination {
+                // A closed door in the way gets opened, which takes the move
+                let door = map.tile_content[destination]
+                    .iter()
+                    .find(|other| doors.get(**other).is_some_and(|door| !door.open));
+                if let Some(door) = door {
+                    wants_to_open
+                        .insert(entity, WantsToOpenDoor { door: *door })
+                        .expect("Unable to insert intent");
+                } else if !map.blocked[destination] && destination != player_idx {
+                    map.blocked[my_idx] = false;
+                    map.blocked[destination] = true;
+                    pos.x = destination as i32 % map.width;
+                    pos.y = destination as i32 / map.width;
+                    entity_moved
+                        .insert(entity, EntityMoved {})
+                        .expect("Unable to insert EntityMoved marker");
+                    viewshed.dirty = true;
                 }
             }
+
+            initiative.current += cost;
         }
     }
 }
+
+/// Picks the behaviour for this turn out of the previous one and what the monster perceives
+fn next_behaviour(
+    ai: &MonsterAIState,
+    sees_enemy: bool,
+    sees_threat: bool,
+    rng: &mut RandomNumberGenerator,
+) -> MonsterBehaviour {
+    if sees_threat {
+        return MonsterBehaviour::Flee;
+    }
+    if sees_enemy {
+        return MonsterBehaviour::Chase;
+    }
+
+    match ai.behaviour {
+        MonsterBehaviour::Chase if ai.last_seen_target.is_some() => MonsterBehaviour::Search,
+        MonsterBehaviour::Search => MonsterBehaviour::Search,
+        MonsterBehaviour::Idle if rng.roll_dice(1, 8) == 1 => MonsterBehaviour::Wander,
+        MonsterBehaviour::Wander if rng.roll_dice(1, 8) == 1 => MonsterBehaviour::Idle,
+        MonsterBehaviour::Wander => MonsterBehaviour::Wander,
+        // Out of sight is good enough for a fleeing monster to calm down
+        _ => MonsterBehaviour::Idle,
+    }
+}
+
+/// A step from `start` that gets further away from `from`, if there's any
+fn step_away(map: &Map, start: usize, from: Point) -> Option<usize> {
+    let here = rltk::DistanceAlg::Pythagoras.distance2d(map.index_to_point2d(start), from);
+    rltk::BaseMap::get_available_exits(map, start)
+        .iter()
+        .map(|exit| {
+            let distance =
+                rltk::DistanceAlg::Pythagoras.distance2d(map.index_to_point2d(exit.0), from);
+            (exit.0, distance)
+        })
+        .filter(|(_idx, distance)| *distance > here)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(idx, _distance)| idx)
+}
+
+fn random_step(map: &Map, start: usize, rng: &mut RandomNumberGenerator) -> Option<usize> {
+    let exits = rltk::BaseMap::get_available_exits(map, start);
+    rng.random_slice_entry(&exits).map(|exit| exit.0)
+}
@@ -54,13 +54,18 @@ impl<'a> System<'a> for ParticleSpawnSystem {
 }
 
 pub fn tick_and_cull_dead_particles(ecs: &mut World, ctx: &Rltk) {
+    cull_dead_particles(ecs, ctx.frame_time_ms);
+}
+
+/// Ages every particle by `elapsed_ms` and deletes the ones that ran out of lifetime
+pub fn cull_dead_particles(ecs: &mut World, elapsed_ms: f32) {
     let mut dead_particles: Vec<Entity> = Vec::new();
 
     {
         let entities = ecs.entities();
         let mut particles = ecs.write_storage::<ParticleLifetime>();
         for (entity, particle) in (&entities, &mut particles).join() {
-            particle.lifetime_ms -= ctx.frame_time_ms;
+            particle.lifetime_ms -= elapsed_ms;
             if particle.lifetime_ms < 0.0 {
                 dead_particles.push(entity);
             }
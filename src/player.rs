@@ -1,13 +1,22 @@
 use super::{
-    CombatStats, GameLog, Item, Map, Monster, Player, Position, RunState, State, TileType,
-    Viewshed, WantsToMelee, WantsToPickupItem,
+    raws::{faction_reaction, Reaction, RAWS},
+    CombatStats, Door, Faction, GameLog, Item, Map, Monster, Player, Position, RunState, State,
+    TileType, Viewshed, WantsToCloseDoor, WantsToDropItem, WantsToMelee, WantsToOpenDoor,
+    WantsToPickupItem, WantsToRemoveItem, WantsToUseItem,
+};
+use crate::initiative_system::{attack_cost, shot_cost, spend_initiative, ACTION_COST};
+use crate::ranged_combat_system::{ranged_loadout, Loadout};
+use crate::{
+    experience, gui, shop, spell_system, ActionCost, EntityMoved, HungerClock, HungerState,
+    Improvement, Ranged, StatusEffectKind, StatusEffects, Vendor, WantsToCastSpell, WantsToShoot,
 };
-use crate::{gui, EntityMoved, HungerClock, HungerState};
 use rltk::{console, Point, Rltk, VirtualKeyCode};
 use specs::prelude::*;
 use std::cmp::{max, min};
 
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// Moves the player (or has them attack whoever is in the way, or open the closed door that is),
+/// returning the vendor they bumped into if they walked up to one
+pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> Option<Entity> {
     let mut positions = ecs.write_storage::<Position>();
     let mut players = ecs.write_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
@@ -19,17 +28,53 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
 
     let mut entity_moved = ecs.write_storage::<EntityMoved>();
+    let factions = ecs.read_storage::<Faction>();
+    let vendors = ecs.read_storage::<Vendor>();
+    let doors = ecs.read_storage::<Door>();
+    let mut wants_to_open = ecs.write_storage::<WantsToOpenDoor>();
+    let mut swap_entities: Vec<(Entity, i32, i32)> = Vec::new();
 
     for (entity, _player, pos, viewshed) in
         (&entities, &mut players, &mut positions, &mut viewsheds).join()
     {
         let (dst_x, dst_y) = (
-            min(79, max(0, pos.x + delta_x)),
-            min(49, max(0, pos.y + delta_y)),
+            min(map.width - 1, max(0, pos.x + delta_x)),
+            min(map.height - 1, max(0, pos.y + delta_y)),
         );
         let destination_idx = map.xy_idx(dst_x, dst_y);
 
         for potential_target in map.tile_content[destination_idx].iter() {
+            // Standing still (a zero delta) shouldn't make the player hit themself
+            if *potential_target == entity {
+                continue;
+            }
+            if doors.get(*potential_target).is_some_and(|door| !door.open) {
+                wants_to_open
+                    .insert(
+                        entity,
+                        WantsToOpenDoor {
+                            door: *potential_target,
+                        },
+                    )
+                    .expect("Unable to insert intent");
+                return None; // opening the door is the whole move
+            }
+            // Bumping into someone we don't want to fight trades places with them
+            let hostile = match (factions.get(entity), factions.get(*potential_target)) {
+                (Some(mine), Some(theirs)) => {
+                    let raws = RAWS.lock().unwrap();
+                    faction_reaction(&mine.name, &theirs.name, &raws) == Reaction::Attack
+                }
+                _ => true,
+            };
+            if !hostile && vendors.contains(*potential_target) {
+                return Some(*potential_target);
+            }
+            if !hostile {
+                swap_entities.push((*potential_target, pos.x, pos.y));
+                continue;
+            }
+
             if let Some(_t) = combat_stats.get(*potential_target) {
                 console::log(&format!("From Hell's Hert, I stab thee!"));
                 wants_to_melee
@@ -40,11 +85,11 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                         },
                     )
                     .expect("Add target failed");
-                return; // don't move after attacking
+                return None; // don't move after attacking
             }
         }
 
-        if !map.blocked[destination_idx] {
+        if !map.blocked[destination_idx] || !swap_entities.is_empty() {
             pos.x = dst_x;
             pos.y = dst_y;
             ppos.x = pos.x;
@@ -54,13 +99,28 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                 .insert(entity, EntityMoved {})
                 .expect("Unable to insert EntityMoved marker");
 
-            if map.tiles[destination_idx] != TileType::DownStairs {
+            if map.tiles[destination_idx] == TileType::Floor {
                 map.tiles[destination_idx] = TileType::VisitedFloor;
             }
 
             viewshed.dirty = true;
         }
     }
+
+    for (other, x, y) in swap_entities.iter() {
+        if let Some(their_pos) = positions.get_mut(*other) {
+            their_pos.x = *x;
+            their_pos.y = *y;
+            entity_moved
+                .insert(*other, EntityMoved {})
+                .expect("Unable to insert EntityMoved marker");
+        }
+        if let Some(their_viewshed) = viewsheds.get_mut(*other) {
+            their_viewshed.dirty = true;
+        }
+    }
+
+    None
 }
 
 fn get_item(ecs: &mut World) {
@@ -113,6 +173,22 @@ pub fn try_next_level(ecs: &mut World) -> bool {
     true
 }
 
+pub fn try_previous_level(ecs: &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+
+    if map.tiles[player_idx] != TileType::UpStairs {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog
+            .entries
+            .push("There is no way up from here.".to_string());
+        return false;
+    }
+
+    true
+}
+
 fn skip_turn(ecs: &mut World) -> RunState {
     let player_ent = ecs.fetch::<Entity>();
     let viewshed_components = ecs.read_storage::<Viewshed>();
@@ -149,58 +225,368 @@ fn skip_turn(ecs: &mut World) -> RunState {
     RunState::PlayerTurn
 }
 
+/// Everything the player can do with their turn, whatever the input comes from (keyboard,
+/// menus or a headless script)
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum PlayerCommand {
+    Move {
+        delta_x: i32,
+        delta_y: i32,
+    },
+    PickUp,
+    UseItem {
+        item: Entity,
+        target: Option<Point>,
+    },
+    DropItem {
+        item: Entity,
+    },
+    RemoveItem {
+        item: Entity,
+    },
+    /// Pick the improvement for a level up
+    ChooseImprovement {
+        improvement: Improvement,
+    },
+    /// Shoot the equipped launcher (or throw something) at a tile
+    Fire {
+        target: Point,
+    },
+    /// Cast one of the known spells (a spell template entity), at a tile if it's ranged
+    CastSpell {
+        spell: Entity,
+        target: Option<Point>,
+    },
+    /// Buy an item out of a vendor's stock
+    Buy {
+        vendor: Entity,
+        item: Entity,
+    },
+    /// Sell an item out of the backpack to a vendor
+    Sell {
+        vendor: Entity,
+        item: Entity,
+    },
+    /// Close an open door next to the player
+    CloseDoor,
+    Descend,
+    Ascend,
+    SkipTurn,
+}
+
+/// Applies `command` on behalf of the player and returns the state the game moves to
+pub fn perform_command(ecs: &mut World, command: PlayerCommand) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut cost = ACTION_COST;
+
+    let newrunstate = match command {
+        PlayerCommand::Move { delta_x, delta_y } => {
+            let (delta_x, delta_y) = if is_confused(ecs, player_entity) {
+                // Stumble off in whatever direction
+                let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+                (rng.roll_dice(1, 3) - 2, rng.roll_dice(1, 3) - 2)
+            } else {
+                (delta_x, delta_y)
+            };
+            if let Some(vendor) = try_move_player(delta_x, delta_y, ecs) {
+                // Browsing the wares takes no time
+                return RunState::ShowVendor {
+                    vendor,
+                    mode: gui::VendorMode::Buy,
+                };
+            }
+            if ecs.read_storage::<WantsToMelee>().contains(player_entity) {
+                cost = attack_cost(ecs, player_entity);
+            }
+            RunState::PlayerTurn
+        }
+        PlayerCommand::PickUp => {
+            get_item(ecs);
+            RunState::PlayerTurn
+        }
+        PlayerCommand::UseItem { item, target } => {
+            if let Some(target) = target {
+                let range = ecs.read_storage::<Ranged>().get(item).map(|r| r.range);
+                if !range.is_some_and(|range| can_target(ecs, target, range)) {
+                    return RunState::AwaitingInput;
+                }
+            }
+            if let Some(item_cost) = ecs.read_storage::<ActionCost>().get(item) {
+                cost = item_cost.cost;
+            }
+            let mut intent = ecs.write_storage::<WantsToUseItem>();
+            intent
+                .insert(player_entity, WantsToUseItem { item, target })
+                .expect("Unable to insert intent");
+            RunState::PlayerTurn
+        }
+        PlayerCommand::DropItem { item } => {
+            let mut intent = ecs.write_storage::<WantsToDropItem>();
+            intent
+                .insert(player_entity, WantsToDropItem { item })
+                .expect("Unable to insert intent");
+            RunState::PlayerTurn
+        }
+        PlayerCommand::RemoveItem { item } => {
+            let mut intent = ecs.write_storage::<WantsToRemoveItem>();
+            intent
+                .insert(player_entity, WantsToRemoveItem { item })
+                .expect("Unable to insert intent");
+            RunState::PlayerTurn
+        }
+        PlayerCommand::ChooseImprovement { improvement } => {
+            // Picking takes no time
+            experience::apply_improvement(ecs, player_entity, improvement);
+            return if experience::has_pending_improvements(ecs, player_entity) {
+                RunState::LevelUp
+            } else {
+                RunState::AwaitingInput
+            };
+        }
+        PlayerCommand::Fire { target } => {
+            let Some(loadout) = player_loadout(ecs) else {
+                ecs.fetch_mut::<GameLog>()
+                    .entries
+                    .push("You have nothing to shoot.".to_string());
+                return RunState::AwaitingInput;
+            };
+            if !can_target(ecs, target, loadout.range) {
+                return RunState::AwaitingInput;
+            }
+            cost = shot_cost(ecs, player_entity);
+            let mut intent = ecs.write_storage::<WantsToShoot>();
+            intent
+                .insert(player_entity, WantsToShoot { target })
+                .expect("Unable to insert intent");
+            RunState::PlayerTurn
+        }
+        PlayerCommand::CastSpell { spell, target } => {
+            if !spell_system::can_afford(ecs, player_entity, spell) {
+                ecs.fetch_mut::<GameLog>()
+                    .entries
+                    .push("You don't have enough mana to cast that!".to_string());
+                return RunState::AwaitingInput;
+            }
+            if let Some(target) = target {
+                let range = ecs.read_storage::<Ranged>().get(spell).map(|r| r.range);
+                if !range.is_some_and(|range| can_target(ecs, target, range)) {
+                    return RunState::AwaitingInput;
+                }
+            }
+            let mut intent = ecs.write_storage::<WantsToCastSpell>();
+            intent
+                .insert(player_entity, WantsToCastSpell { spell, target })
+                .expect("Unable to insert intent");
+            RunState::PlayerTurn
+        }
+        PlayerCommand::Buy { vendor, item } => {
+            // Trading takes no time
+            shop::buy(ecs, player_entity, vendor, item);
+            return RunState::ShowVendor {
+                vendor,
+                mode: gui::VendorMode::Buy,
+            };
+        }
+        PlayerCommand::Sell { vendor, item } => {
+            shop::sell(ecs, player_entity, vendor, item);
+            return RunState::ShowVendor {
+                vendor,
+                mode: gui::VendorMode::Sell,
+            };
+        }
+        PlayerCommand::CloseDoor => match adjacent_open_door(ecs) {
+            Some(door) => {
+                let mut intent = ecs.write_storage::<WantsToCloseDoor>();
+                intent
+                    .insert(player_entity, WantsToCloseDoor { door })
+                    .expect("Unable to insert intent");
+                RunState::PlayerTurn
+            }
+            None => {
+                ecs.fetch_mut::<GameLog>()
+                    .entries
+                    .push("There is no open door next to you.".to_string());
+                return RunState::AwaitingInput;
+            }
+        },
+        PlayerCommand::Descend => {
+            if !try_next_level(ecs) {
+                return RunState::AwaitingInput;
+            }
+            RunState::NextLevel
+        }
+        PlayerCommand::Ascend => {
+            if !try_previous_level(ecs) {
+                return RunState::AwaitingInput;
+            }
+            RunState::PreviousLevel
+        }
+        PlayerCommand::SkipTurn => skip_turn(ecs),
+    };
+
+    spend_initiative(ecs, player_entity, cost);
+
+    newrunstate
+}
+
+/// What the player would shoot with, if they have anything to shoot
+fn player_loadout(ecs: &World) -> Option<Loadout> {
+    ranged_loadout(
+        &ecs.entities(),
+        *ecs.fetch::<Entity>(),
+        &ecs.read_storage(),
+        &ecs.read_storage(),
+        &ecs.read_storage(),
+        &ecs.read_storage(),
+    )
+}
+
+/// Whether the player can aim at `target` from where they stand, which takes the same visible tiles
+/// within `range` the targeting screen offers
+fn can_target(ecs: &World, target: Point, range: i32) -> bool {
+    let player_entity = *ecs.fetch::<Entity>();
+    let player_pos = *ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let on_map = target.x >= 0 && target.x < map.width && target.y >= 0 && target.y < map.height;
+    let in_range = rltk::DistanceAlg::Pythagoras.distance2d(player_pos, target) <= range as f32;
+    let in_sight = ecs
+        .read_storage::<Viewshed>()
+        .get(player_entity)
+        .is_some_and(|viewshed| viewshed.visible_tiles.contains(&target));
+    if on_map && in_range && in_sight {
+        return true;
+    }
+    ecs.fetch_mut::<GameLog>()
+        .entries
+        .push("You can't aim there.".to_string());
+    false
+}
+
+/// An open door on one of the tiles around the player, if there's any
+fn adjacent_open_door(ecs: &World) -> Option<Entity> {
+    let player_pos = *ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let doors = ecs.read_storage::<Door>();
+    for y in player_pos.y - 1..=player_pos.y + 1 {
+        for x in player_pos.x - 1..=player_pos.x + 1 {
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+            let door = map.tile_content[map.xy_idx(x, y)]
+                .iter()
+                .find(|entity| doors.get(**entity).is_some_and(|door| door.open));
+            if let Some(door) = door {
+                return Some(*door);
+            }
+        }
+    }
+    None
+}
+
+fn is_confused(ecs: &World, entity: Entity) -> bool {
+    ecs.read_storage::<StatusEffects>()
+        .get(entity)
+        .is_some_and(|status| status.has(StatusEffectKind::Confusion))
+}
+
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    // Player movement
-    match ctx.key {
+    let command = match ctx.key {
         None => return RunState::AwaitingInput, // Nothing Happened
         Some(key) => match key {
+            // Player movement
             VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
-                try_move_player(-1, 0, &mut gs.ecs)
+                PlayerCommand::Move {
+                    delta_x: -1,
+                    delta_y: 0,
+                }
             }
 
             VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
-                try_move_player(1, 0, &mut gs.ecs)
+                PlayerCommand::Move {
+                    delta_x: 1,
+                    delta_y: 0,
+                }
             }
 
             VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
-                try_move_player(0, -1, &mut gs.ecs)
+                PlayerCommand::Move {
+                    delta_x: 0,
+                    delta_y: -1,
+                }
             }
 
             VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
-                try_move_player(0, 1, &mut gs.ecs)
+                PlayerCommand::Move {
+                    delta_x: 0,
+                    delta_y: 1,
+                }
             }
 
             // Diagonals
-            VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => try_move_player(-1, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad7 | VirtualKeyCode::U => try_move_player(1, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => try_move_player(-1, 1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad1 | VirtualKeyCode::M => try_move_player(1, 1, &mut gs.ecs),
+            VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => PlayerCommand::Move {
+                delta_x: -1,
+                delta_y: -1,
+            },
+
+            VirtualKeyCode::Numpad7 | VirtualKeyCode::U => PlayerCommand::Move {
+                delta_x: 1,
+                delta_y: -1,
+            },
+
+            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => PlayerCommand::Move {
+                delta_x: -1,
+                delta_y: 1,
+            },
+
+            VirtualKeyCode::Numpad1 | VirtualKeyCode::M => PlayerCommand::Move {
+                delta_x: 1,
+                delta_y: 1,
+            },
 
             // Grabbing
-            VirtualKeyCode::G => get_item(&mut gs.ecs),
+            VirtualKeyCode::G => PlayerCommand::PickUp,
 
             // Open Inventory
             VirtualKeyCode::I => return RunState::ShowInventory,
 
+            // Shoot or throw
+            VirtualKeyCode::F => match player_loadout(&gs.ecs) {
+                Some(loadout) => {
+                    return RunState::ShowFiring {
+                        range: loadout.range,
+                    }
+                }
+                None => {
+                    gs.ecs
+                        .fetch_mut::<GameLog>()
+                        .entries
+                        .push("You have nothing to shoot.".to_string());
+                    return RunState::AwaitingInput;
+                }
+            },
+
             // Open Delete Menu
             VirtualKeyCode::D => return RunState::ShowDropItem,
 
             // Level changes
-            VirtualKeyCode::Period => {
-                if try_next_level(&mut gs.ecs) {
-                    return RunState::NextLevel;
-                }
-            }
+            VirtualKeyCode::Period => PlayerCommand::Descend,
+            VirtualKeyCode::Comma => PlayerCommand::Ascend,
 
             // Skip Turn
-            VirtualKeyCode::Numpad5 | VirtualKeyCode::Space => return skip_turn(&mut gs.ecs),
+            VirtualKeyCode::Numpad5 | VirtualKeyCode::Space => PlayerCommand::SkipTurn,
 
             // Drop Item
             VirtualKeyCode::R => return RunState::ShowRemoveItem,
 
+            // Equipment screen
+            VirtualKeyCode::E => return RunState::ShowEquipment,
+
+            // Cast a spell
+            VirtualKeyCode::C => return RunState::ShowSpells,
+
+            // Shut a door
+            VirtualKeyCode::X => PlayerCommand::CloseDoor,
+
             VirtualKeyCode::Escape => {
                 return RunState::MainMenu {
                     menu_selection: gui::MainMenuSelection::NewGame,
@@ -208,6 +594,7 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
             }
             _ => return RunState::AwaitingInput,
         },
-    }
-    RunState::PlayerTurn
+    };
+
+    perform_command(&mut gs.ecs, command)
 }
@@ -40,18 +40,15 @@ impl RandomTable {
         if self.total_weight == 0 {
             return "None".to_string();
         }
+        // Somewhere in 0..total_weight, which always lands on one of the entries
         let mut roll = rng.roll_dice(1, self.total_weight) - 1;
-        let mut index: usize = 0;
-
-        while roll > 0 {
-            if roll < self.entries[index].weight {
-                return self.entries[index].name.clone();
+        for entry in self.entries.iter() {
+            if roll < entry.weight {
+                return entry.name.clone();
             }
-
-            roll -= self.entries[index].weight;
-            index += 1;
+            roll -= entry.weight;
         }
 
-        "None".to_string()
+        unreachable!("the roll is below the total weight")
     }
 }
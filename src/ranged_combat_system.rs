@@ -0,0 +1,286 @@
+use std::ops::Deref;
+
+use rltk::{Point, RandomNumberGenerator};
+use specs::prelude::*;
+use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
+use specs::storage::MaskedStorage;
+
+use super::{
+    combat::{armor_class, roll_to_hit, weapon_attribute_bonus, AttackRoll},
+    effects::{particle, EffectQueue, EffectType, Targets},
+    gamelog::GameLog,
+    Ammunition, Attributes, CombatStats, DefenseBonus, Enchantment, Equipped, InBackpack, Item,
+    Map, Name, Position, RangedWeapon, Renderable, SerializeMe, Skills, WantsToShoot,
+    WeaponAttribute,
+};
+
+/// Kind of ammunition that needs no launcher, it is thrown by hand
+pub const THROWN_AMMO: &str = "thrown";
+/// How far things can be thrown by hand
+const THROW_RANGE: i32 = 4;
+/// One in this many projectiles breaks instead of landing where it stopped
+const BREAK_CHANCE: i32 = 4;
+
+/// What a shooter would fire with right now
+pub struct Loadout {
+    /// The equipped launcher, `None` when throwing
+    pub launcher: Option<Entity>,
+    /// The ammunition stack a shot takes from
+    pub ammo: Entity,
+    pub range: i32,
+    pub damage: i32,
+}
+
+/// The equipped launcher (or bare hands) of `shooter` together with some ammunition it can fire
+/// from their backpack, `None` if they have nothing to shoot
+pub fn ranged_loadout<E, W, B, A>(
+    entities: &Entities,
+    shooter: Entity,
+    equipped: &Storage<Equipped, E>,
+    weapons: &Storage<RangedWeapon, W>,
+    backpacks: &Storage<InBackpack, B>,
+    ammunition: &Storage<Ammunition, A>,
+) -> Option<Loadout>
+where
+    E: Deref<Target = MaskedStorage<Equipped>>,
+    W: Deref<Target = MaskedStorage<RangedWeapon>>,
+    B: Deref<Target = MaskedStorage<InBackpack>>,
+    A: Deref<Target = MaskedStorage<Ammunition>>,
+{
+    let launcher = (entities, equipped, weapons)
+        .join()
+        .find(|(_entity, equipped_by, _weapon)| equipped_by.owner == shooter)
+        .map(|(entity, _equipped_by, weapon)| (entity, weapon));
+    let (ammo_kind, range, power_bonus) = match launcher {
+        Some((_entity, weapon)) => (weapon.ammo.as_str(), weapon.range, weapon.power_bonus),
+        None => (THROWN_AMMO, THROW_RANGE, 0),
+    };
+
+    (entities, backpacks, ammunition)
+        .join()
+        .find(|(_entity, pack, ammo)| {
+            pack.owner == shooter && ammo.ammo == ammo_kind && ammo.count > 0
+        })
+        .map(|(entity, _pack, ammo)| Loadout {
+            launcher: launcher.map(|(launcher, _weapon)| launcher),
+            ammo: entity,
+            range,
+            damage: ammo.damage + power_bonus,
+        })
+}
+
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, WantsToShoot>,
+        WriteStorage<'a, Name>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, DefenseBonus>,
+        ReadStorage<'a, Equipped>,
+        ReadStorage<'a, RangedWeapon>,
+        ReadStorage<'a, InBackpack>,
+        WriteStorage<'a, Ammunition>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Item>,
+        WriteStorage<'a, Renderable>,
+        WriteStorage<'a, SimpleMarker<SerializeMe>>,
+        WriteExpect<'a, SimpleMarkerAllocator<SerializeMe>>,
+        WriteExpect<'a, RandomNumberGenerator>,
+        WriteExpect<'a, EffectQueue>,
+        ReadStorage<'a, Attributes>,
+        ReadStorage<'a, Skills>,
+        ReadStorage<'a, Enchantment>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            map,
+            mut log,
+            mut wants_shoot,
+            mut names,
+            combat_stats,
+            defense_bonuses,
+            equipped,
+            weapons,
+            backpacks,
+            mut ammunition,
+            mut positions,
+            mut items,
+            mut renderables,
+            mut markers,
+            mut marker_allocator,
+            mut rng,
+            mut effect_queue,
+            attributes,
+            skills,
+            enchantments,
+        ) = data;
+
+        let shots: Vec<(Entity, Point)> = (&entities, &wants_shoot)
+            .join()
+            .map(|(entity, shot)| (entity, shot.target))
+            .collect();
+        wants_shoot.clear();
+
+        for (shooter, target) in shots {
+            let loadout = match ranged_loadout(
+                &entities,
+                shooter,
+                &equipped,
+                &weapons,
+                &backpacks,
+                &ammunition,
+            ) {
+                Some(loadout) => loadout,
+                None => continue,
+            };
+            let shooter_pos = match positions.get(shooter) {
+                Some(pos) => Point::new(pos.x, pos.y),
+                None => continue,
+            };
+
+            let path = map.projectile_path(shooter_pos, target);
+            for point in path.iter() {
+                effect_queue.add(
+                    Some(shooter),
+                    particle(rltk::CYAN, '*', 100.0),
+                    Targets::Tile {
+                        tile_idx: map.xy_idx(point.x, point.y),
+                    },
+                );
+            }
+            let landing = *path.last().unwrap_or(&shooter_pos);
+            let landing_idx = map.xy_idx(landing.x, landing.y);
+
+            // Whoever stands where the projectile stops takes the hit
+            let victim = map.tile_content[landing_idx]
+                .iter()
+                .find(|entity| **entity != shooter && combat_stats.get(**entity).is_some())
+                .copied();
+            if let Some(victim) = victim {
+                let defensive_bonus: i32 = (&entities, &defense_bonuses, &equipped)
+                    .join()
+                    .filter(|(_item_entity, _defense_bonus, equipped_by)| {
+                        equipped_by.owner == victim
+                    })
+                    .map(|(item_entity, defense_bonus, _equipped_by)| {
+                        defense_bonus.defense + enchantments.get(item_entity).map_or(0, |e| e.bonus)
+                    })
+                    .sum();
+                let aim_bonus =
+                    weapon_attribute_bonus(attributes.get(shooter), WeaponAttribute::Quickness);
+                let enchantment_bonus = loadout
+                    .launcher
+                    .and_then(|launcher| enchantments.get(launcher))
+                    .map_or(0, |enchantment| enchantment.bonus);
+                let to_hit = aim_bonus
+                    + enchantment_bonus
+                    + skills.get(shooter).map_or(0, |skills| skills.ranged);
+                let victim_armor_class = armor_class(
+                    combat_stats.get(victim).unwrap().defense + defensive_bonus,
+                    attributes.get(victim),
+                    skills.get(victim),
+                );
+
+                let shooter_name = names.get(shooter).unwrap().name.clone();
+                let victim_name = names.get(victim).unwrap().name.clone();
+                let roll = roll_to_hit(&mut rng, to_hit, victim_armor_class);
+                match roll {
+                    AttackRoll::Fumble => {
+                        log.entries
+                            .push(format!("{} fumbles the shot.", shooter_name));
+                    }
+                    AttackRoll::Miss => {
+                        log.entries.push(format!(
+                            "{} shoots at {}, but misses.",
+                            shooter_name, victim_name
+                        ));
+                    }
+                    AttackRoll::Hit | AttackRoll::Critical => {
+                        let mut damage =
+                            i32::max(1, loadout.damage + aim_bonus + enchantment_bonus);
+                        if roll == AttackRoll::Critical {
+                            damage *= 2;
+                            log.entries.push(format!(
+                                "{} lands a critical shot on {}, for {} hp!",
+                                shooter_name, victim_name, damage
+                            ));
+                        } else {
+                            log.entries.push(format!(
+                                "{} shoots {}, for {} hp.",
+                                shooter_name, victim_name, damage
+                            ));
+                        }
+                        effect_queue.add(
+                            Some(shooter),
+                            EffectType::Damage { amount: damage },
+                            Targets::Single { target: victim },
+                        );
+                        effect_queue.add(
+                            Some(shooter),
+                            particle(rltk::ORANGE, '‼', 150.0),
+                            Targets::Single { target: victim },
+                        );
+                    }
+                }
+            }
+
+            // Take the projectile out of the stack, and drop it where it stopped unless it broke
+            let (name, renderable, mut projectile) = {
+                let stack = ammunition.get_mut(loadout.ammo).unwrap();
+                stack.count -= 1;
+                (
+                    names.get(loadout.ammo).unwrap().clone(),
+                    renderables.get(loadout.ammo).cloned(),
+                    stack.clone(),
+                )
+            };
+            if projectile.count == 0 {
+                entities
+                    .delete(loadout.ammo)
+                    .expect("Unable to delete ammunition");
+            }
+            if rng.roll_dice(1, BREAK_CHANCE) == 1 {
+                continue;
+            }
+
+            let pile = map.tile_content[landing_idx]
+                .iter()
+                .copied()
+                .find(|entity| {
+                    positions.get(*entity).is_some()
+                        && names.get(*entity).is_some_and(|n| n.name == name.name)
+                        && ammunition.get(*entity).is_some()
+                });
+            match pile {
+                Some(pile) => ammunition.get_mut(pile).unwrap().count += 1,
+                None => {
+                    projectile.count = 1;
+                    let mut builder = entities
+                        .build_entity()
+                        .with(
+                            Position {
+                                x: landing.x,
+                                y: landing.y,
+                            },
+                            &mut positions,
+                        )
+                        .with(name, &mut names)
+                        .with(Item {}, &mut items)
+                        .with(projectile, &mut ammunition)
+                        .marked(&mut markers, &mut marker_allocator);
+                    if let Some(renderable) = renderable {
+                        builder = builder.with(renderable, &mut renderables);
+                    }
+                    builder.build();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,10 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// How members of `name` react to every other faction. The `Default` key covers the factions
+/// that aren't listed.
+#[derive(Deserialize, Debug)]
+pub struct FactionInfo {
+    pub name: String,
+    pub responses: HashMap<String, String>,
+}
@@ -0,0 +1,85 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug)]
+pub struct Item {
+    pub name: String,
+    pub renderable: Option<Renderable>,
+    pub consumable: Option<Consumable>,
+    pub weapon: Option<Weapon>,
+    pub shield: Option<Shield>,
+    pub wearable: Option<Wearable>,
+    pub ranged_weapon: Option<RangedWeapon>,
+    pub ammunition: Option<Ammunition>,
+    /// In pounds (per projectile for ammunition), weightless when missing
+    pub weight: Option<f32>,
+    /// Gold vendors ask for it, worthless when missing
+    pub base_value: Option<i32>,
+    /// Initiative spent using the item, a standard action when missing
+    pub action_cost: Option<i32>,
+    /// `potion` or `scroll`: the item goes by a random appearance of that kind until the player
+    /// identifies it
+    pub obfuscate: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Renderable {
+    pub glyph: String,
+    pub fg: String,
+    pub bg: String,
+    pub order: i32,
+}
+
+/// Light given off by a mob or prop
+#[derive(Deserialize, Debug)]
+pub struct Light {
+    pub range: i32,
+    pub color: String,
+}
+
+/// `effects` maps an effect name (e.g. `provides_healing`) to its parameter. Effects that take no
+/// parameter (e.g. `food`) use an empty string.
+#[derive(Deserialize, Debug)]
+pub struct Consumable {
+    pub effects: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Weapon {
+    /// `Might` or `Quickness`, `Might` when missing
+    pub attribute: Option<String>,
+    /// Damage dice, e.g. `1d8` or `2d4+1`
+    pub damage: String,
+    pub hit_bonus: Option<i32>,
+    /// Two-handed weapons leave no hand free for a shield
+    pub two_handed: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Shield {
+    pub defense_bonus: i32,
+}
+
+/// Armour and jewellery. `slot` is one of Head, Torso, Legs, Feet, Hands, Ring or Amulet.
+#[derive(Deserialize, Debug)]
+pub struct Wearable {
+    pub slot: String,
+    pub defense_bonus: Option<i32>,
+    pub power_bonus: Option<i32>,
+}
+
+/// A launcher, firing ammunition of the `ammo` kind
+#[derive(Deserialize, Debug)]
+pub struct RangedWeapon {
+    pub range: i32,
+    pub power_bonus: i32,
+    pub ammo: String,
+}
+
+/// A stack of `count` projectiles of the `ammo` kind (`thrown` ones need no launcher)
+#[derive(Deserialize, Debug)]
+pub struct Ammunition {
+    pub ammo: String,
+    pub damage: i32,
+    pub count: i32,
+}
@@ -0,0 +1,66 @@
+use super::{Light, Renderable};
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+pub struct Mob {
+    pub name: String,
+    pub renderable: Option<Renderable>,
+    pub blocks_tile: bool,
+    pub stats: MobStats,
+    pub vision_range: i32,
+    /// Level at the first depth, 1 when missing. Mobs gain levels the deeper they spawn.
+    pub level: Option<i32>,
+    /// Mobs without a faction attack everything
+    pub faction: Option<String>,
+    /// Initiative regained every tick, normal speed when missing
+    pub speed: Option<i32>,
+    /// Items the mob spawns wielding
+    pub equipped: Option<Vec<String>>,
+    /// Items the mob spawns with in its backpack
+    pub carrying: Option<Vec<String>>,
+    /// Attributes missing here (or all of them) are an average 10
+    pub attributes: Option<MobAttributes>,
+    /// Skills missing here (or all of them) are untrained
+    pub skills: Option<MobSkills>,
+    /// Natural attack used when the mob wields nothing, bare hands when missing
+    pub attack: Option<MobAttack>,
+    /// Dice rolled for the gold the mob carries, e.g. `2d6`
+    pub gold: Option<String>,
+    /// Kinds of items the mob sells (`consumables`, `food`, `weapons` or `armour`), it's no vendor
+    /// when missing
+    pub vendor: Option<Vec<String>>,
+    /// Whether the mob goes after the items lying around, it leaves them alone when missing
+    pub looter: Option<bool>,
+    pub light: Option<Light>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MobStats {
+    pub max_hp: i32,
+    pub hp: i32,
+    pub power: i32,
+    pub defense: i32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MobAttributes {
+    pub might: Option<i32>,
+    pub fitness: Option<i32>,
+    pub quickness: Option<i32>,
+    pub intelligence: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MobSkills {
+    pub melee: Option<i32>,
+    pub ranged: Option<i32>,
+    pub defense: Option<i32>,
+}
+
+/// Claws, bites and the like. Same fields as an item's weapon.
+#[derive(Deserialize, Debug)]
+pub struct MobAttack {
+    pub attribute: Option<String>,
+    pub damage: String,
+    pub hit_bonus: Option<i32>,
+}
@@ -0,0 +1,73 @@
+use serde::Deserialize;
+use std::sync::Mutex;
+
+mod item_structs;
+use item_structs::*;
+mod mob_structs;
+use mob_structs::*;
+mod prop_structs;
+use prop_structs::*;
+mod spawn_table_structs;
+use spawn_table_structs::*;
+mod faction_structs;
+use faction_structs::*;
+mod spell_structs;
+use spell_structs::*;
+mod rawmaster;
+pub use rawmaster::*;
+
+const RAW_FILE_PATH: &str = "../../raws/spawns.json";
+/// Where the raws are edited, read again every time the game starts so changes need no rebuild
+const RAW_FILE_ON_DISK: &str = "raws/spawns.json";
+
+rltk::embedded_resource!(RAW_FILE, "../../raws/spawns.json");
+
+lazy_static! {
+    pub static ref RAWS: Mutex<RawMaster> = Mutex::new(RawMaster::empty());
+}
+
+/// Entity templates as they are stored in `raws/spawns.json`
+#[derive(Deserialize, Debug)]
+pub struct Raws {
+    pub items: Vec<Item>,
+    pub mobs: Vec<Mob>,
+    pub props: Vec<Prop>,
+    pub spawn_table: Vec<SpawnTableEntry>,
+    pub faction_table: Vec<FactionInfo>,
+    pub spells: Vec<Spell>,
+}
+
+/// Parses the raw file on disk and makes it available through [RAWS]. When it can't be read or
+/// parsed the copy embedded in the game at build time is used instead.
+pub fn load_raws() {
+    let from_disk = std::fs::read_to_string(RAW_FILE_ON_DISK)
+        .map_err(|error| error.to_string())
+        .and_then(|raw_string| {
+            serde_json::from_str::<Raws>(&raw_string).map_err(|error| error.to_string())
+        });
+    let decoder = match from_disk {
+        Ok(decoder) => decoder,
+        Err(error) => {
+            rltk::console::log(format!(
+                "{}: {}, falling back on the embedded raws",
+                RAW_FILE_ON_DISK, error
+            ));
+            embedded_raws()
+        }
+    };
+
+    RAWS.lock().unwrap().load(decoder);
+}
+
+fn embedded_raws() -> Raws {
+    rltk::link_resource!(RAW_FILE, RAW_FILE_PATH);
+
+    let raw_data = rltk::embedding::EMBED
+        .lock()
+        .get_resource(RAW_FILE_PATH.to_string())
+        .expect("could not find the embedded raw file");
+    let raw_string = std::str::from_utf8(raw_data)
+        .expect("unable to convert the raw file to a valid UTF-8 string");
+    serde_json::from_str(raw_string)
+        .unwrap_or_else(|error| panic!("embedded {}: {}", RAW_FILE_ON_DISK, error))
+}
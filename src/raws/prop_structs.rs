@@ -0,0 +1,23 @@
+use super::{Light, Renderable};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Props are the non-item, non-mob entities of a level: traps, doors and torches.
+#[derive(Deserialize, Debug)]
+pub struct Prop {
+    pub name: String,
+    pub renderable: Option<Renderable>,
+    pub hidden: Option<bool>,
+    pub entry_trigger: Option<EntryTrigger>,
+    pub blocks_tile: Option<bool>,
+    pub blocks_visibility: Option<bool>,
+    /// Makes the prop a door, open or closed to begin with
+    pub door_open: Option<bool>,
+    pub light: Option<Light>,
+}
+
+/// Same format as [super::Consumable] effects, applied to whoever steps on the prop.
+#[derive(Deserialize, Debug)]
+pub struct EntryTrigger {
+    pub effects: HashMap<String, String>,
+}
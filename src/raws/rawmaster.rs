@@ -0,0 +1,792 @@
+use std::collections::HashMap;
+
+use rltk::{RandomNumberGenerator, RGB};
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+
+use super::Raws;
+use crate::components::*;
+use crate::dungeon::{ItemAppearance, MasterDungeonMap};
+use crate::initiative_system::NORMAL_SPEED;
+use crate::random_table::RandomTable;
+use crate::Map;
+
+/// How an entity behaves towards members of another faction
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum Reaction {
+    Ignore,
+    Attack,
+    Flee,
+}
+
+/// Faction for mobs that don't list one
+const DEFAULT_FACTION: &str = "Mindless";
+
+/// Hit points a mob gains for every level it has above its template's
+const MOB_HP_PER_LEVEL: i32 = 4;
+/// Levels it takes a mob to gain a point of power, and of defense
+const MOB_LEVELS_PER_POWER: i32 = 2;
+const MOB_LEVELS_PER_DEFENSE: i32 = 3;
+
+/// Colors unidentified items come in, with the word for them
+const APPEARANCE_COLORS: [(&str, (u8, u8, u8)); 10] = [
+    ("red", rltk::RED),
+    ("blue", rltk::BLUE),
+    ("green", rltk::GREEN),
+    ("yellow", rltk::YELLOW),
+    ("purple", rltk::PURPLE),
+    ("orange", rltk::ORANGE),
+    ("pink", rltk::PINK),
+    ("cyan", rltk::CYAN),
+    ("brown", rltk::BROWN1),
+    ("silver", rltk::SILVER),
+];
+const POTION_ADJECTIVES: [&str; 8] = [
+    "bubbling", "murky", "fizzy", "smoking", "glowing", "cloudy", "oily", "swirling",
+];
+const SCROLL_SYLLABLES: [&str; 12] = [
+    "ZARK", "VOL", "NIX", "THOR", "KLAATU", "BARADA", "NIKTO", "XIXAXA", "MAPIRO", "ELBIB", "LEH",
+    "VAS",
+];
+
+/// Where a spawned entity should be placed
+pub enum SpawnType {
+    AtPosition {
+        x: i32,
+        y: i32,
+    },
+    /// Wielded by `by`, in whatever slot the item goes
+    Equipped {
+        by: Entity,
+    },
+    /// In the backpack of `by`
+    Carried {
+        by: Entity,
+    },
+}
+
+/// Holds the parsed [Raws] plus a name index for each kind of template
+pub struct RawMaster {
+    raws: Raws,
+    item_index: HashMap<String, usize>,
+    mob_index: HashMap<String, usize>,
+    prop_index: HashMap<String, usize>,
+    faction_index: HashMap<String, HashMap<String, Reaction>>,
+}
+
+impl RawMaster {
+    pub fn empty() -> RawMaster {
+        RawMaster {
+            raws: Raws {
+                items: Vec::new(),
+                mobs: Vec::new(),
+                props: Vec::new(),
+                spawn_table: Vec::new(),
+                faction_table: Vec::new(),
+                spells: Vec::new(),
+            },
+            item_index: HashMap::new(),
+            mob_index: HashMap::new(),
+            prop_index: HashMap::new(),
+            faction_index: HashMap::new(),
+        }
+    }
+
+    pub fn load(&mut self, raws: Raws) {
+        self.raws = raws;
+        self.item_index = HashMap::new();
+        self.mob_index = HashMap::new();
+        self.prop_index = HashMap::new();
+        self.faction_index = HashMap::new();
+
+        for (i, item) in self.raws.items.iter().enumerate() {
+            self.item_index.insert(item.name.clone(), i);
+        }
+        for (i, mob) in self.raws.mobs.iter().enumerate() {
+            self.mob_index.insert(mob.name.clone(), i);
+        }
+        for (i, prop) in self.raws.props.iter().enumerate() {
+            self.prop_index.insert(prop.name.clone(), i);
+        }
+
+        for faction in self.raws.faction_table.iter() {
+            let mut reactions: HashMap<String, Reaction> = HashMap::new();
+            for (other, response) in faction.responses.iter() {
+                let reaction = match response.as_str() {
+                    "Attack" => Reaction::Attack,
+                    "Flee" => Reaction::Flee,
+                    "Ignore" => Reaction::Ignore,
+                    _ => {
+                        rltk::console::log(format!(
+                            "WARNING - Unknown reaction {} of faction {}",
+                            response, faction.name
+                        ));
+                        Reaction::Ignore
+                    }
+                };
+                reactions.insert(other.clone(), reaction);
+            }
+            self.faction_index.insert(faction.name.clone(), reactions);
+        }
+
+        for spawn in self.raws.spawn_table.iter() {
+            if !self.item_index.contains_key(&spawn.name)
+                && !self.mob_index.contains_key(&spawn.name)
+                && !self.prop_index.contains_key(&spawn.name)
+            {
+                rltk::console::log(format!(
+                    "WARNING - Spawn table references unspecified entity {}",
+                    spawn.name
+                ));
+            }
+        }
+    }
+}
+
+fn new_marked_entity(ecs: &mut World) -> EntityBuilder<'_> {
+    ecs.create_entity().marked::<SimpleMarker<SerializeMe>>()
+}
+
+fn spawn_position(
+    pos: SpawnType,
+    new_entity: EntityBuilder<'_>,
+    slot: Option<EquipmentSlot>,
+) -> EntityBuilder<'_> {
+    match pos {
+        SpawnType::AtPosition { x, y } => new_entity.with(Position { x, y }),
+        SpawnType::Equipped { by } => match slot {
+            Some(slot) => new_entity.with(Equipped { owner: by, slot }),
+            // Nothing to wield it with, so it goes in the backpack
+            None => new_entity.with(InBackpack { owner: by }),
+        },
+        SpawnType::Carried { by } => new_entity.with(InBackpack { owner: by }),
+    }
+}
+
+/// Slot the item template equips into, `None` if it can't be equipped
+fn equipment_slot(item_template: &super::item_structs::Item) -> Option<EquipmentSlot> {
+    if item_template.weapon.is_some() {
+        Some(EquipmentSlot::Melee)
+    } else if item_template.shield.is_some() {
+        Some(EquipmentSlot::Shield)
+    } else if item_template.ranged_weapon.is_some() {
+        Some(EquipmentSlot::Ranged)
+    } else {
+        item_template
+            .wearable
+            .as_ref()
+            .and_then(|wearable| wearable_slot(&wearable.slot))
+    }
+}
+
+fn wearable_slot(slot_name: &str) -> Option<EquipmentSlot> {
+    match slot_name {
+        "Head" => Some(EquipmentSlot::Head),
+        "Torso" => Some(EquipmentSlot::Torso),
+        "Legs" => Some(EquipmentSlot::Legs),
+        "Feet" => Some(EquipmentSlot::Feet),
+        "Hands" => Some(EquipmentSlot::Hands),
+        "Ring" => Some(EquipmentSlot::Ring),
+        "Amulet" => Some(EquipmentSlot::Amulet),
+        _ => None,
+    }
+}
+
+/// Builds a [MeleeWeapon] out of the raw fields shared by item weapons and mob attacks
+fn melee_weapon(
+    attribute: Option<&str>,
+    damage: &str,
+    hit_bonus: Option<i32>,
+    owner_name: &str,
+) -> MeleeWeapon {
+    let attribute = match attribute {
+        None | Some("Might") => WeaponAttribute::Might,
+        Some("Quickness") => WeaponAttribute::Quickness,
+        Some(other) => {
+            rltk::console::log(format!(
+                "WARNING - Unknown weapon attribute {} (used by {})",
+                other, owner_name
+            ));
+            WeaponAttribute::Might
+        }
+    };
+    let dice = rltk::parse_dice_string(damage).unwrap_or_else(|_| {
+        rltk::console::log(format!(
+            "WARNING - Invalid damage dice {} (used by {})",
+            damage, owner_name
+        ));
+        rltk::DiceType::new(1, 4, 0)
+    });
+
+    MeleeWeapon {
+        attribute,
+        damage_n_dice: dice.n_dice,
+        damage_die_type: dice.die_type,
+        damage_bonus: dice.bonus,
+        hit_bonus: hit_bonus.unwrap_or(0),
+    }
+}
+
+fn get_renderable_component(renderable: &super::item_structs::Renderable) -> Renderable {
+    Renderable {
+        glyph: rltk::to_cp437(renderable.glyph.chars().next().unwrap()),
+        fg: rltk::RGB::from_hex(&renderable.fg).expect("Invalid RGB"),
+        bg: rltk::RGB::from_hex(&renderable.bg).expect("Invalid RGB"),
+        render_order: renderable.order,
+    }
+}
+
+fn get_light_component(light: &super::item_structs::Light) -> LightSource {
+    LightSource {
+        color: rltk::RGB::from_hex(&light.color).expect("Invalid RGB"),
+        range: light.range,
+    }
+}
+
+/// Adds the components described by an effects map (shared by consumables and entry triggers).
+/// Fails on parameters that make no sense for their effect, naming the owner and the effect.
+fn apply_effects<'a>(
+    mut new_entity: EntityBuilder<'a>,
+    effects: &HashMap<String, String>,
+    owner_name: &str,
+) -> Result<EntityBuilder<'a>, String> {
+    let mut statuses: Vec<StatusEffect> = Vec::new();
+
+    for (effect_name, effect_param) in effects.iter() {
+        match effect_name.as_str() {
+            "provides_healing" => {
+                new_entity = new_entity.with(ProvidesHealing {
+                    heal_amount: effect_number(owner_name, effect_name, effect_param)?,
+                })
+            }
+            "provides_mana" => {
+                new_entity = new_entity.with(ProvidesManaRestore {
+                    mana_amount: effect_number(owner_name, effect_name, effect_param)?,
+                })
+            }
+            "ranged" => {
+                new_entity = new_entity.with(Ranged {
+                    range: effect_number(owner_name, effect_name, effect_param)?,
+                })
+            }
+            "damage" => {
+                new_entity = new_entity.with(InflictsDamage {
+                    damage: effect_number(owner_name, effect_name, effect_param)?,
+                })
+            }
+            "area_of_effect" => {
+                new_entity = new_entity.with(AreaOfEffect {
+                    radius: effect_number(owner_name, effect_name, effect_param)?,
+                })
+            }
+            "magic_mapping" => {
+                new_entity = new_entity.with(MagicMapper {
+                    power: effect_number(owner_name, effect_name, effect_param)?,
+                })
+            }
+            "food" => new_entity = new_entity.with(ProvidesFood {}),
+            "teleport" => new_entity = new_entity.with(Teleporter {}),
+            "teach_spell" => {
+                new_entity = new_entity.with(TeachesSpell {
+                    spell: effect_param.clone(),
+                })
+            }
+            "identify" => new_entity = new_entity.with(Identifies {}),
+            "remove_curse" => new_entity = new_entity.with(RemovesCurse {}),
+            "single_activation" => new_entity = new_entity.with(SingleActivation {}),
+            _ => match status_effect_kind(effect_name) {
+                // Status effects take how many turns they last
+                Some(kind) => statuses.push(StatusEffect {
+                    kind,
+                    turns: effect_number(owner_name, effect_name, effect_param)?,
+                }),
+                None => {
+                    rltk::console::log(format!(
+                        "Warning: effect {} not implemented (used by {}).",
+                        effect_name, owner_name
+                    ));
+                }
+            },
+        }
+    }
+
+    if !statuses.is_empty() {
+        // Keep the order stable, the effects map doesn't have one
+        statuses.sort_by_key(|status| status.kind);
+        new_entity = new_entity.with(InflictsStatus { effects: statuses });
+    }
+
+    Ok(new_entity)
+}
+
+/// The number an effect takes as its parameter
+fn effect_number(owner_name: &str, effect_name: &str, effect_param: &str) -> Result<i32, String> {
+    effect_param.parse::<i32>().map_err(|_| {
+        format!(
+            "{}: the {} effect takes a number, not \"{}\"",
+            owner_name, effect_name, effect_param
+        )
+    })
+}
+
+fn status_effect_kind(effect_name: &str) -> Option<StatusEffectKind> {
+    match effect_name {
+        "poison" => Some(StatusEffectKind::Poison),
+        "regeneration" => Some(StatusEffectKind::Regeneration),
+        "slow" => Some(StatusEffectKind::Slow),
+        "haste" => Some(StatusEffectKind::Haste),
+        "blindness" => Some(StatusEffectKind::Blindness),
+        "paralysis" => Some(StatusEffectKind::Paralysis),
+        "confusion" => Some(StatusEffectKind::Confusion),
+        _ => None,
+    }
+}
+
+pub fn spawn_named_item(
+    raws: &RawMaster,
+    new_entity: EntityBuilder,
+    key: &str,
+    pos: SpawnType,
+) -> Option<Entity> {
+    let item_template = &raws.raws.items[*raws.item_index.get(key)?];
+
+    let mut eb = new_entity;
+
+    // Spawn in the specified location
+    eb = spawn_position(pos, eb, equipment_slot(item_template));
+
+    // Renderable
+    if let Some(renderable) = &item_template.renderable {
+        eb = eb.with(get_renderable_component(renderable));
+    }
+
+    eb = eb.with(Name {
+        name: item_template.name.clone(),
+    });
+    eb = eb.with(Item {});
+    if let Some(weight) = item_template.weight {
+        eb = eb.with(Weight { weight });
+    }
+    if let Some(gold) = item_template.base_value {
+        eb = eb.with(ItemValue { gold });
+    }
+
+    if let Some(consumable) = &item_template.consumable {
+        eb = eb.with(Consumable {});
+        eb = apply_effects(eb, &consumable.effects, &item_template.name)
+            .map_err(rltk::console::log)
+            .ok()?;
+    }
+
+    if let Some(weapon) = &item_template.weapon {
+        eb = eb.with(Equippable {
+            slot: EquipmentSlot::Melee,
+            two_handed: weapon.two_handed == Some(true),
+        });
+        eb = eb.with(melee_weapon(
+            weapon.attribute.as_deref(),
+            &weapon.damage,
+            weapon.hit_bonus,
+            &item_template.name,
+        ));
+    }
+
+    if let Some(ranged_weapon) = &item_template.ranged_weapon {
+        eb = eb.with(Equippable {
+            slot: EquipmentSlot::Ranged,
+            two_handed: false,
+        });
+        eb = eb.with(RangedWeapon {
+            range: ranged_weapon.range,
+            power_bonus: ranged_weapon.power_bonus,
+            ammo: ranged_weapon.ammo.clone(),
+        });
+    }
+
+    if let Some(ammunition) = &item_template.ammunition {
+        eb = eb.with(Ammunition {
+            ammo: ammunition.ammo.clone(),
+            damage: ammunition.damage,
+            count: ammunition.count,
+        });
+    }
+
+    if let Some(cost) = item_template.action_cost {
+        eb = eb.with(ActionCost { cost });
+    }
+
+    if let Some(shield) = &item_template.shield {
+        eb = eb.with(Equippable {
+            slot: EquipmentSlot::Shield,
+            two_handed: false,
+        });
+        eb = eb.with(DefenseBonus {
+            defense: shield.defense_bonus,
+        });
+    }
+
+    if let Some(wearable) = &item_template.wearable {
+        match wearable_slot(&wearable.slot) {
+            Some(slot) => {
+                eb = eb.with(Equippable {
+                    slot,
+                    two_handed: false,
+                });
+                if let Some(defense) = wearable.defense_bonus {
+                    eb = eb.with(DefenseBonus { defense });
+                }
+                if let Some(power) = wearable.power_bonus {
+                    eb = eb.with(MeleePowerBonus { power });
+                }
+            }
+            None => rltk::console::log(format!(
+                "WARNING - Unknown equipment slot {} (used by {})",
+                wearable.slot, item_template.name
+            )),
+        }
+    }
+
+    Some(eb.build())
+}
+
+/// Spawns the mob named `key`, levelled up for how far below the first level `depth` is
+pub fn spawn_named_mob(
+    raws: &RawMaster,
+    new_entity: EntityBuilder,
+    key: &str,
+    pos: SpawnType,
+    depth: i32,
+) -> Option<Entity> {
+    let mob_template = &raws.raws.mobs[*raws.mob_index.get(key)?];
+
+    let mut eb = new_entity;
+
+    // Spawn in the specified location
+    eb = spawn_position(pos, eb, None);
+
+    // Renderable
+    if let Some(renderable) = &mob_template.renderable {
+        eb = eb.with(get_renderable_component(renderable));
+    }
+
+    eb = eb.with(Name {
+        name: mob_template.name.clone(),
+    });
+    eb = eb.with(Monster {});
+    eb = eb.with(Faction {
+        name: mob_template
+            .faction
+            .clone()
+            .unwrap_or_else(|| DEFAULT_FACTION.to_string()),
+    });
+    eb = eb.with(MonsterAIState {
+        behaviour: MonsterBehaviour::Idle,
+        last_seen_target: None,
+    });
+    eb = eb.with(Initiative {
+        current: 0,
+        speed: mob_template.speed.unwrap_or(NORMAL_SPEED),
+    });
+
+    if mob_template.blocks_tile {
+        eb = eb.with(BlocksTile {});
+    }
+    if let Some(light) = &mob_template.light {
+        eb = eb.with(get_light_component(light));
+    }
+    if let Some(categories) = &mob_template.vendor {
+        eb = eb.with(Vendor {
+            categories: categories.clone(),
+        });
+    }
+    if mob_template.looter.unwrap_or(false) {
+        eb = eb.with(Looter {});
+    }
+
+    let attributes = mob_template.attributes.as_ref();
+    eb = eb.with(Attributes {
+        might: attributes.and_then(|a| a.might).unwrap_or(10),
+        fitness: attributes.and_then(|a| a.fitness).unwrap_or(10),
+        quickness: attributes.and_then(|a| a.quickness).unwrap_or(10),
+        intelligence: attributes.and_then(|a| a.intelligence).unwrap_or(10),
+    });
+    let skills = mob_template.skills.as_ref();
+    eb = eb.with(Skills {
+        melee: skills.and_then(|s| s.melee).unwrap_or(0),
+        ranged: skills.and_then(|s| s.ranged).unwrap_or(0),
+        defense: skills.and_then(|s| s.defense).unwrap_or(0),
+    });
+    if let Some(attack) = &mob_template.attack {
+        eb = eb.with(melee_weapon(
+            attack.attribute.as_deref(),
+            &attack.damage,
+            attack.hit_bonus,
+            &mob_template.name,
+        ));
+    }
+
+    // Deeper levels breed tougher monsters
+    let extra_levels = i32::max(0, depth - 1);
+    eb = eb.with(Experience {
+        level: mob_template.level.unwrap_or(1) + extra_levels,
+        xp: 0,
+        improvements: 0,
+    });
+    eb = eb.with(CombatStats {
+        max_hp: mob_template.stats.max_hp + extra_levels * MOB_HP_PER_LEVEL,
+        hp: mob_template.stats.hp + extra_levels * MOB_HP_PER_LEVEL,
+        power: mob_template.stats.power + extra_levels / MOB_LEVELS_PER_POWER,
+        defense: mob_template.stats.defense + extra_levels / MOB_LEVELS_PER_DEFENSE,
+    });
+    eb = eb.with(Viewshed {
+        visible_tiles: Vec::new(),
+        range: mob_template.vision_range,
+        dirty: true,
+    });
+
+    Some(eb.build())
+}
+
+pub fn spawn_named_prop(
+    raws: &RawMaster,
+    new_entity: EntityBuilder,
+    key: &str,
+    pos: SpawnType,
+) -> Option<Entity> {
+    let prop_template = &raws.raws.props[*raws.prop_index.get(key)?];
+
+    let mut eb = new_entity;
+
+    // Spawn in the specified location
+    eb = spawn_position(pos, eb, None);
+
+    // Renderable
+    if let Some(renderable) = &prop_template.renderable {
+        eb = eb.with(get_renderable_component(renderable));
+    }
+
+    eb = eb.with(Name {
+        name: prop_template.name.clone(),
+    });
+
+    if prop_template.hidden == Some(true) {
+        eb = eb.with(Hidden {});
+    }
+
+    if let Some(entry_trigger) = &prop_template.entry_trigger {
+        eb = eb.with(EntryTrigger {});
+        eb = apply_effects(eb, &entry_trigger.effects, &prop_template.name)
+            .map_err(rltk::console::log)
+            .ok()?;
+    }
+
+    if prop_template.blocks_tile == Some(true) {
+        eb = eb.with(BlocksTile {});
+    }
+    if prop_template.blocks_visibility == Some(true) {
+        eb = eb.with(BlocksVisibility {});
+    }
+    if let Some(open) = prop_template.door_open {
+        eb = eb.with(Door { open });
+    }
+    if let Some(light) = &prop_template.light {
+        eb = eb.with(get_light_component(light));
+    }
+
+    Some(eb.build())
+}
+
+/// Spawns the template named `key` (item, mob or prop, in that lookup order), returning `None` if
+/// no template uses that name. Whatever is random about it (a mob's gold) is rolled with `rng`.
+pub fn spawn_named_entity(
+    raws: &RawMaster,
+    ecs: &mut World,
+    key: &str,
+    pos: SpawnType,
+    rng: &mut RandomNumberGenerator,
+) -> Option<Entity> {
+    if raws.item_index.contains_key(key) {
+        let item = spawn_named_item(raws, new_marked_entity(ecs), key, pos)?;
+
+        // Unidentified kinds of items look the same for the whole run
+        if let Some(appearance) = ecs.fetch::<MasterDungeonMap>().item_appearances.get(key) {
+            if let Some(renderable) = ecs.write_storage::<Renderable>().get_mut(item) {
+                renderable.fg = appearance.fg;
+            }
+        }
+        return Some(item);
+    } else if raws.mob_index.contains_key(key) {
+        let depth = ecs.fetch::<Map>().depth;
+        let mob = spawn_named_mob(raws, new_marked_entity(ecs), key, pos, depth)?;
+
+        // Hand the mob its starting gear
+        let mob_template = &raws.raws.mobs[raws.mob_index[key]];
+        for item in mob_template.equipped.iter().flatten() {
+            spawn_named_entity(raws, ecs, item, SpawnType::Equipped { by: mob }, rng);
+        }
+        for item in mob_template.carrying.iter().flatten() {
+            spawn_named_entity(raws, ecs, item, SpawnType::Carried { by: mob }, rng);
+        }
+        if let Some(gold) = &mob_template.gold {
+            match rltk::parse_dice_string(gold) {
+                Ok(dice) => {
+                    let amount = rng.roll(dice);
+                    ecs.write_storage::<Gold>()
+                        .insert(mob, Gold { amount })
+                        .expect("Unable to insert gold");
+                }
+                Err(_) => rltk::console::log(format!(
+                    "WARNING - Unable to parse gold dice {} (used by {})",
+                    gold, mob_template.name
+                )),
+            }
+        }
+        return Some(mob);
+    } else if raws.prop_index.contains_key(key) {
+        return spawn_named_prop(raws, new_marked_entity(ecs), key, pos);
+    }
+
+    None
+}
+
+/// Spawns a template entity for every spell in the raws, the ones casting looks up by name
+pub fn spawn_all_spells(raws: &RawMaster, ecs: &mut World) {
+    for spell in raws.raws.spells.iter() {
+        let eb = new_marked_entity(ecs)
+            .with(SpellTemplate {
+                mana_cost: spell.mana_cost,
+            })
+            .with(Name {
+                name: spell.name.clone(),
+            });
+        match apply_effects(eb, &spell.effects, &spell.name) {
+            Ok(eb) => {
+                eb.build();
+            }
+            Err(error) => rltk::console::log(error),
+        }
+    }
+}
+
+/// Disguises every item that needs identifying with an appearance of its kind, a random but
+/// different one for each item
+pub fn item_appearances(
+    raws: &RawMaster,
+    rng: &mut RandomNumberGenerator,
+) -> HashMap<String, ItemAppearance> {
+    let mut appearances: HashMap<String, ItemAppearance> = HashMap::new();
+    for item in raws.raws.items.iter() {
+        let kind = match &item.obfuscate {
+            Some(kind) => kind.as_str(),
+            None => continue,
+        };
+        let appearance = loop {
+            let (color_name, fg) = APPEARANCE_COLORS[rng.range(0, APPEARANCE_COLORS.len())];
+            let name = match kind {
+                "potion" => format!(
+                    "{} {} potion",
+                    POTION_ADJECTIVES[rng.range(0, POTION_ADJECTIVES.len())],
+                    color_name
+                ),
+                "scroll" => format!(
+                    "scroll of {} {}",
+                    SCROLL_SYLLABLES[rng.range(0, SCROLL_SYLLABLES.len())],
+                    SCROLL_SYLLABLES[rng.range(0, SCROLL_SYLLABLES.len())]
+                ),
+                _ => {
+                    rltk::console::log(format!(
+                        "Warning: {} has an unknown kind of appearance {}.",
+                        item.name, kind
+                    ));
+                    break None;
+                }
+            };
+            if appearances.values().all(|other| other.name != name) {
+                break Some(ItemAppearance {
+                    name,
+                    fg: RGB::named(fg),
+                });
+            }
+        };
+        if let Some(appearance) = appearance {
+            appearances.insert(item.name.clone(), appearance);
+        }
+    }
+    appearances
+}
+
+/// The template entity of the spell called `name`, if there's one
+pub fn find_spell_entity(ecs: &World, name: &str) -> Option<Entity> {
+    let templates = ecs.read_storage::<SpellTemplate>();
+    let names = ecs.read_storage::<Name>();
+    (&ecs.entities(), &templates, &names)
+        .join()
+        .find(|(_entity, _template, spell_name)| spell_name.name == name)
+        .map(|(entity, _template, _name)| entity)
+}
+
+/// How members of `my_faction` react to members of `their_faction`. Unknown factions are ignored.
+pub fn faction_reaction(my_faction: &str, their_faction: &str, raws: &RawMaster) -> Reaction {
+    match raws.faction_index.get(my_faction) {
+        None => Reaction::Ignore,
+        Some(reactions) => match reactions.get(their_faction) {
+            Some(reaction) => *reaction,
+            None => *reactions.get("Default").unwrap_or(&Reaction::Ignore),
+        },
+    }
+}
+
+/// Builds the spawn table for `depth` out of the raw file's `spawn_table` entries
+pub fn get_spawn_table_for_depth(raws: &RawMaster, depth: i32) -> RandomTable {
+    raws.raws
+        .spawn_table
+        .iter()
+        .filter(|entry| depth >= entry.min_depth && depth <= entry.max_depth)
+        .fold(RandomTable::new(), |table, entry| {
+            let mut weight = entry.weight;
+            if entry.add_map_depth_to_weight == Some(true) {
+                weight += depth;
+            }
+            table.add(entry.name.clone(), weight)
+        })
+}
+
+/// Builds the table a vendor selling `categories` of items rolls its stock from at `depth`, out of
+/// the items among the raw file's `spawn_table` entries
+pub fn get_vendor_table_for_depth(
+    raws: &RawMaster,
+    depth: i32,
+    categories: &[String],
+) -> RandomTable {
+    raws.raws
+        .spawn_table
+        .iter()
+        .filter(|entry| depth >= entry.min_depth && depth <= entry.max_depth)
+        .filter(|entry| match raws.item_index.get(&entry.name) {
+            Some(idx) => {
+                let item = &raws.raws.items[*idx];
+                categories.iter().any(|category| match category.as_str() {
+                    "consumables" => item.consumable.is_some(),
+                    "food" => item
+                        .consumable
+                        .as_ref()
+                        .is_some_and(|consumable| consumable.effects.contains_key("food")),
+                    "weapons" => {
+                        item.weapon.is_some()
+                            || item.ranged_weapon.is_some()
+                            || item.ammunition.is_some()
+                    }
+                    "armour" => item.shield.is_some() || item.wearable.is_some(),
+                    _ => false,
+                })
+            }
+            None => false,
+        })
+        .fold(RandomTable::new(), |table, entry| {
+            let mut weight = entry.weight;
+            if entry.add_map_depth_to_weight == Some(true) {
+                weight += depth;
+            }
+            table.add(entry.name.clone(), weight)
+        })
+}
@@ -0,0 +1,14 @@
+use serde::Deserialize;
+
+/// A weighted entry of the spawn table, only available for depths in `min_depth..=max_depth`.
+///
+/// When `add_map_depth_to_weight` is set, the current depth is added to `weight` so the entry
+/// gets more common the deeper you go.
+#[derive(Deserialize, Debug)]
+pub struct SpawnTableEntry {
+    pub name: String,
+    pub weight: i32,
+    pub min_depth: i32,
+    pub max_depth: i32,
+    pub add_map_depth_to_weight: Option<bool>,
+}
@@ -0,0 +1,10 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Deserialize, Debug)]
+pub struct Spell {
+    pub name: String,
+    pub mana_cost: i32,
+    /// Same effects (and parameters) as a consumable item's
+    pub effects: HashMap<String, String>,
+}
@@ -0,0 +1,56 @@
+use rltk::RandomNumberGenerator;
+
+/// The seed every map and spawn roll of the current run is derived from.
+///
+/// Each depth gets its own seed (see [RunSeed::for_depth]), so a level always looks the same
+/// for a given run seed no matter what happened on the previous ones.
+#[derive(Clone, Copy)]
+pub struct RunSeed {
+    pub seed: u64,
+}
+
+impl RunSeed {
+    pub fn new(seed: u64) -> RunSeed {
+        RunSeed { seed }
+    }
+
+    /// Picks a seed from the system entropy, used when the player didn't ask for one
+    pub fn random() -> RunSeed {
+        RunSeed::new(RandomNumberGenerator::new().next_u64())
+    }
+
+    /// Seed for the map generation (and spawning) of the given depth
+    pub fn for_depth(&self, depth: i32) -> u64 {
+        // splitmix64 finalizer, so neighboring depths don't end up with similar seeds
+        let mut z = self
+            .seed
+            .wrapping_add((depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1330_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Random number generator for the appearances unidentified items take during the run
+    pub fn appearance_rng(&self) -> RandomNumberGenerator {
+        RandomNumberGenerator::seeded(self.seed ^ 0xA5A5_5A5A_0F0F_F0F0)
+    }
+
+    /// Random number generator for everything that happens during play (combat, AI, etc.)
+    pub fn gameplay_rng(&self) -> RandomNumberGenerator {
+        RandomNumberGenerator::seeded(self.seed)
+    }
+}
+
+/// Reads the seed passed as `--seed <number>` on the command line, if any
+pub fn seed_from_args() -> Option<u64> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let position = args.iter().position(|arg| arg == "--seed")?;
+
+    match args.get(position + 1).map(|value| value.parse::<u64>()) {
+        Some(Ok(seed)) => Some(seed),
+        _ => {
+            rltk::console::log("WARNING - --seed expects a positive number, ignoring it");
+            None
+        }
+    }
+}
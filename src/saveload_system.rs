@@ -1,4 +1,7 @@
 use super::components::*;
+use super::dungeon::MasterDungeonMap;
+use super::initiative_system::GameClock;
+use super::run_seed::RunSeed;
 use specs::error::NoError;
 use specs::prelude::*;
 use specs::saveload::{
@@ -45,9 +48,22 @@ pub fn save_game(ecs: &mut World) {}
 pub fn save_game(ecs: &mut World, save_name: &str) {
     // Create helper
     let mapcopy = ecs.get_mut::<super::map::Map>().unwrap().clone();
+    let seed = ecs.fetch::<RunSeed>().seed;
+    let rngcopy = ecs
+        .get_mut::<rltk::RandomNumberGenerator>()
+        .unwrap()
+        .clone();
+    let dungeon_master = ecs.get_mut::<MasterDungeonMap>().unwrap().clone();
+    let clock = ecs.get_mut::<GameClock>().unwrap().clone();
     let savehelper = ecs
         .create_entity()
-        .with(SerializationHelper { map: mapcopy })
+        .with(SerializationHelper {
+            map: mapcopy,
+            seed,
+            rng: rngcopy,
+            dungeon_master,
+            clock,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
@@ -72,35 +88,67 @@ pub fn save_game(ecs: &mut World, save_name: &str) {
             Name,
             BlocksTile,
             CombatStats,
-            SufferDamage,
             WantsToMelee,
             Item,
             Consumable,
             Ranged,
             InflictsDamage,
             AreaOfEffect,
-            Confusion,
             ProvidesHealing,
             InBackpack,
             WantsToPickupItem,
             WantsToUseItem,
             WantsToDropItem,
             MagicStats,
+            Experience,
             ProvidesManaRestore,
             SerializationHelper,
             Equippable,
             Equipped,
             MeleePowerBonus,
+            MeleeWeapon,
+            Attributes,
+            Skills,
             DefenseBonus,
             WantsToRemoveItem,
             ParticleLifetime,
             HungerClock,
             ProvidesFood,
             MagicMapper,
+            Teleporter,
             Hidden,
             EntryTrigger,
             EntityMoved,
-            SingleActivation
+            SingleActivation,
+            OtherLevelPosition,
+            MonsterAIState,
+            Faction,
+            Initiative,
+            MyTurn,
+            ActionCost,
+            StatusEffects,
+            InflictsStatus,
+            RangedWeapon,
+            Ammunition,
+            WantsToShoot,
+            SpellTemplate,
+            KnownSpells,
+            WantsToCastSpell,
+            TeachesSpell,
+            Identifies,
+            Enchantment,
+            RemovesCurse,
+            Weight,
+            Encumbrance,
+            ItemValue,
+            Gold,
+            Vendor,
+            BlocksVisibility,
+            Door,
+            WantsToOpenDoor,
+            WantsToCloseDoor,
+            LightSource,
+            Looter
         );
     }
 
@@ -176,35 +224,67 @@ pub fn load_game(ecs: &mut World, save_name: &str) {
             Name,
             BlocksTile,
             CombatStats,
-            SufferDamage,
             WantsToMelee,
             Item,
             Consumable,
             Ranged,
             InflictsDamage,
             AreaOfEffect,
-            Confusion,
             ProvidesHealing,
             InBackpack,
             WantsToPickupItem,
             WantsToUseItem,
             WantsToDropItem,
             MagicStats,
+            Experience,
             ProvidesManaRestore,
             SerializationHelper,
             Equippable,
             Equipped,
             MeleePowerBonus,
+            MeleeWeapon,
+            Attributes,
+            Skills,
             DefenseBonus,
             WantsToRemoveItem,
             ParticleLifetime,
             HungerClock,
             ProvidesFood,
             MagicMapper,
+            Teleporter,
             Hidden,
             EntryTrigger,
             EntityMoved,
-            SingleActivation
+            SingleActivation,
+            OtherLevelPosition,
+            MonsterAIState,
+            Faction,
+            Initiative,
+            MyTurn,
+            ActionCost,
+            StatusEffects,
+            InflictsStatus,
+            RangedWeapon,
+            Ammunition,
+            WantsToShoot,
+            SpellTemplate,
+            KnownSpells,
+            WantsToCastSpell,
+            TeachesSpell,
+            Identifies,
+            Enchantment,
+            RemovesCurse,
+            Weight,
+            Encumbrance,
+            ItemValue,
+            Gold,
+            Vendor,
+            BlocksVisibility,
+            Door,
+            WantsToOpenDoor,
+            WantsToCloseDoor,
+            LightSource,
+            Looter
         );
     }
 
@@ -217,7 +297,11 @@ pub fn load_game(ecs: &mut World, save_name: &str) {
         for (e, h) in (&entities, &helper).join() {
             let mut worldmap = ecs.write_resource::<super::map::Map>();
             *worldmap = h.map.clone();
-            worldmap.tile_content = vec![Vec::new(); super::map::MAP_COUNT];
+            worldmap.tile_content = vec![Vec::new(); worldmap.tiles.len()];
+            *ecs.write_resource::<RunSeed>() = RunSeed::new(h.seed);
+            *ecs.write_resource::<rltk::RandomNumberGenerator>() = h.rng.clone();
+            *ecs.write_resource::<MasterDungeonMap>() = h.dungeon_master.clone();
+            *ecs.write_resource::<GameClock>() = h.clock.clone();
             deleteme = Some(e);
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
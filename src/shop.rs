@@ -0,0 +1,147 @@
+use specs::prelude::*;
+
+use super::{
+    dungeon::MasterDungeonMap, encumbrance_system::stack_weight, gamelog::GameLog,
+    inventory_system::add_to_backpack, Ammunition, Enchantment, Encumbrance, Gold, InBackpack,
+    ItemValue, Name, Weight,
+};
+
+/// Gold the player starts a run with
+pub const STARTING_GOLD: i32 = 20;
+/// What every point of enchantment adds to (or a malus takes off) the price of an item
+const ENCHANTMENT_VALUE: i32 = 20;
+
+/// What a vendor asks for `item`, `None` when it's worthless. Ammunition is priced for the whole
+/// stack.
+pub fn buy_price(ecs: &World, item: Entity) -> Option<i32> {
+    let value = ecs.read_storage::<ItemValue>().get(item)?.gold;
+    let bonus = ecs
+        .read_storage::<Enchantment>()
+        .get(item)
+        .map_or(0, |enchantment| enchantment.bonus);
+    let count = ecs
+        .read_storage::<Ammunition>()
+        .get(item)
+        .map_or(1, |ammo| ammo.count);
+    Some(i32::max(1, value * count + bonus * ENCHANTMENT_VALUE))
+}
+
+/// What a vendor pays for `item`: half of what they'd ask for it
+pub fn sell_price(ecs: &World, item: Entity) -> Option<i32> {
+    buy_price(ecs, item).map(|price| i32::max(1, price / 2))
+}
+
+/// The gold `entity` carries
+pub fn gold(ecs: &World, entity: Entity) -> i32 {
+    ecs.read_storage::<Gold>()
+        .get(entity)
+        .map_or(0, |gold| gold.amount)
+}
+
+fn item_name(ecs: &World, item: Entity) -> String {
+    ecs.fetch::<MasterDungeonMap>().item_name(
+        &ecs.read_storage::<Name>().get(item).unwrap().name,
+        ecs.read_storage::<Enchantment>().get(item),
+    )
+}
+
+/// Moves the gold `victim` carried over to `killer`. Only entities carrying [Gold] collect any.
+pub fn loot_gold(ecs: &World, killer: Entity, victim: Entity) {
+    let mut gold = ecs.write_storage::<Gold>();
+    let amount = match gold.get_mut(victim) {
+        Some(victim_gold) if victim_gold.amount > 0 => std::mem::take(&mut victim_gold.amount),
+        _ => return,
+    };
+    let killer_gold = match gold.get_mut(killer) {
+        Some(gold) => gold,
+        None => return,
+    };
+    killer_gold.amount += amount;
+
+    if killer == *ecs.fetch::<Entity>() {
+        ecs.write_resource::<GameLog>()
+            .entries
+            .push(format!("You find {} gold.", amount));
+    }
+}
+
+/// `buyer` buys `item` out of `vendor`'s stock, if they can afford it and carry it
+pub fn buy(ecs: &World, buyer: Entity, vendor: Entity, item: Entity) {
+    let is_stock = ecs
+        .read_storage::<InBackpack>()
+        .get(item)
+        .is_some_and(|pack| pack.owner == vendor);
+    let price = match buy_price(ecs, item) {
+        Some(price) if is_stock => price,
+        _ => return,
+    };
+
+    let name = item_name(ecs, item);
+    let mut log = ecs.write_resource::<GameLog>();
+    if gold(ecs, buyer) < price {
+        log.entries.push(format!("You can't afford the {}.", name));
+        return;
+    }
+
+    // Nobody buys more than they can carry
+    let weight = stack_weight(
+        ecs.read_storage::<Weight>().get(item),
+        ecs.read_storage::<Ammunition>().get(item),
+    );
+    if let Some(encumbrance) = ecs.write_storage::<Encumbrance>().get_mut(buyer) {
+        if encumbrance.carried + weight > encumbrance.capacity {
+            log.entries.push(format!(
+                "You can't carry the {} as well, it's too heavy.",
+                name
+            ));
+            return;
+        }
+        encumbrance.carried += weight;
+    }
+
+    ecs.write_storage::<Gold>().get_mut(buyer).unwrap().amount -= price;
+
+    add_to_backpack(
+        &ecs.entities(),
+        buyer,
+        item,
+        &ecs.read_storage::<Name>(),
+        &mut ecs.write_storage::<InBackpack>(),
+        &mut ecs.write_storage::<Ammunition>(),
+    );
+
+    log.entries
+        .push(format!("You buy the {} for {} gold.", name, price));
+}
+
+/// `seller` sells `item` out of their backpack to `vendor`, who has no use for worthless things
+pub fn sell(ecs: &World, seller: Entity, vendor: Entity, item: Entity) {
+    let is_carried = ecs
+        .read_storage::<InBackpack>()
+        .get(item)
+        .is_some_and(|pack| pack.owner == seller);
+    if !is_carried {
+        return;
+    }
+
+    let name = item_name(ecs, item);
+    let price = match sell_price(ecs, item) {
+        Some(price) => price,
+        None => {
+            ecs.write_resource::<GameLog>()
+                .entries
+                .push(format!("The vendor isn't interested in the {}.", name));
+            return;
+        }
+    };
+
+    if let Some(gold) = ecs.write_storage::<Gold>().get_mut(seller) {
+        gold.amount += price;
+    }
+    ecs.write_storage::<InBackpack>()
+        .insert(item, InBackpack { owner: vendor })
+        .expect("Unable to insert backpack entry");
+    ecs.write_resource::<GameLog>()
+        .entries
+        .push(format!("You sell the {} for {} gold.", name, price));
+}
@@ -1,22 +1,37 @@
 use rltk::{RandomNumberGenerator, RGB};
 use specs::prelude::*;
 
+use crate::raws::{
+    get_spawn_table_for_depth, get_vendor_table_for_depth, spawn_named_entity, SpawnType, RAWS,
+};
 use crate::{
-    EntryTrigger, Hidden, HungerClock, MagicMapper, Map, ProvidesFood, SingleActivation, TileType,
+    DefenseBonus, Enchantment, Equippable, HungerClock, LightSource, Map, MeleeWeapon,
+    RangedWeapon, Vendor,
 };
 
 use super::{
-    AreaOfEffect, BlocksTile, CombatStats, Confusion, Consumable, DefenseBonus, EquipmentSlot,
-    Equippable, InflictsDamage, Item, MagicStats, MeleePowerBonus, Monster, Name, Player, Position,
-    ProvidesHealing, ProvidesManaRestore, RandomTable, Ranged, Rect, Renderable, SerializeMe,
-    Viewshed, MAP_WIDTH,
+    initiative_system::NORMAL_SPEED, shop::STARTING_GOLD, Attributes, CombatStats, Encumbrance,
+    Experience, Faction, Gold, Initiative, KnownSpells, MagicStats, Name, Player, Position,
+    RandomTable, Rect, Renderable, SerializeMe, Skills, Viewshed,
 };
 use specs::saveload::{MarkedBuilder, SimpleMarker};
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 const MAX_MONSTERS: i32 = 4;
 
+/// Percent chance for a weapon or piece of armour to be cursed, and how bad the curse gets
+const CURSE_CHANCE: i32 = 10;
+const MAX_CURSE_MALUS: i32 = 2;
+/// Percent chance for a weapon or piece of armour to be enchanted on the first level, growing
+/// with depth
+const ENCHANT_CHANCE: i32 = 10;
+const ENCHANT_CHANCE_PER_DEPTH: i32 = 3;
+const MAX_ENCHANTMENT: i32 = 3;
+
+/// Items a vendor rolls for its stock
+const VENDOR_STOCK: i32 = 6;
+
 /// Spawns the player and returns his/her entity object.
 pub fn spawn_player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
     ecs.create_entity()
@@ -31,6 +46,10 @@ pub fn spawn_player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             render_order: 0,
         })
         .with(Player {})
+        .with(LightSource {
+            color: RGB::from_f32(1.0, 0.9, 0.7),
+            range: 8,
+        })
         .with(Viewshed {
             visible_tiles: Vec::new(),
             range: 8,
@@ -43,453 +62,200 @@ pub fn spawn_player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             max_hp: 30,
             hp: 30,
             defense: 2,
-            power: 5,
+            power: 1,
         }) // TODO: revert max_hp to normal
+        .with(Attributes {
+            might: 12,
+            fitness: 12,
+            quickness: 11,
+            intelligence: 11,
+        })
+        .with(Skills {
+            melee: 1,
+            ranged: 1,
+            defense: 1,
+        })
+        // Filled in by the encumbrance system
+        .with(Encumbrance {
+            carried: 0.0,
+            capacity: 0.0,
+        })
         .with(MagicStats {
             max_mana: 10,
             mana: 10,
             power: 7,
         })
+        .with(KnownSpells {
+            spells: vec!["Magic Missile".to_string()],
+        })
+        .with(Experience {
+            level: 1,
+            xp: 0,
+            improvements: 0,
+        })
+        .with(Gold {
+            amount: STARTING_GOLD,
+        })
         .with(HungerClock {
             state: crate::HungerState::WellFed,
             duration: 30,
         })
+        .with(Faction {
+            name: "Player".to_string(),
+        })
+        .with(Initiative {
+            current: 0,
+            speed: NORMAL_SPEED,
+        })
         .marked::<SimpleMarker<SerializeMe>>()
         .build()
 }
 
 fn room_table(map_depth: i32) -> RandomTable {
-    RandomTable::new()
-        .add("Goblin", 14)
-        .add("Orc", 1 + map_depth)
-        .add("Health Potion", 4)
-        .add("Greater Health Potion", 2)
-        .add("Legendary Health Potion", 1)
-        .add("Mana Potion", 3)
-        .add("Greater Mana Potion", 2)
-        .add("Legendary Mana Potion", 1)
-        .add("Fireball Scroll", 1 + map_depth)
-        .add("Confusion Scroll", 2 + map_depth)
-        .add("Magic Missile Scroll", 3)
-        .add("Dagger", 7)
-        .add("Shield", 7)
-        .add("Longsword", map_depth - 1)
-        .add("Tower Shield", map_depth - 1)
-        .add("Rations", 10)
-        .add("Magic Mapping Scroll", 2)
-        .add("Bear Trap", 7)
-        .add("Spikes", 7)
-}
-
-fn orc(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('o'), "Orc");
-}
-fn goblin(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('g'), "Goblin");
-}
-
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: rltk::FontCharType, name: S) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph,
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 1,
-        })
-        .with(Viewshed {
-            visible_tiles: Vec::new(),
-            range: 8,
-            dirty: true,
-        })
-        .with(Monster {})
-        .with(Name {
-            name: name.to_string(),
-        })
-        .with(BlocksTile {})
-        .with(CombatStats {
-            max_hp: 16,
-            hp: 16,
-            defense: 1,
-            power: 4,
-        })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+    get_spawn_table_for_depth(&RAWS.lock().unwrap(), map_depth)
 }
 
-pub fn spawn_room(ecs: &mut World, room: &Rect, map_depth: i32) {
+/// Fills `spawn_list` with the entities to spawn inside `room`
+pub fn spawn_room(
+    map: &Map,
+    rng: &mut RandomNumberGenerator,
+    room: &Rect,
+    map_depth: i32,
+    spawn_list: &mut Vec<(usize, String)>,
+) {
     let mut possible_targets: Vec<usize> = Vec::new();
-    {
-        // Borrow scope
-        let map = ecs.fetch::<Map>();
-        for y in room.y1..room.y2 {
-            for x in room.x1..room.x2 {
-                let idx = map.xy_idx(x, y);
-                if map.tiles[idx] == TileType::Floor {
-                    possible_targets.push(idx);
-                }
+    for y in room.y1..room.y2 {
+        for x in room.x1..room.x2 {
+            let idx = map.xy_idx(x, y);
+            if map.tiles[idx].is_walkable() {
+                possible_targets.push(idx);
             }
         }
     }
 
-    spawn_region(ecs, &possible_targets, map_depth);
+    spawn_region(rng, &possible_targets, map_depth, spawn_list);
 }
 
+/// Fills `spawn_list` with the entities to spawn in the given area (a list of map indices)
 #[allow(clippy::map_entry)]
-pub fn spawn_region(ecs: &mut World, area: &[usize], map_depth: i32) {
+pub fn spawn_region(
+    rng: &mut RandomNumberGenerator,
+    area: &[usize],
+    map_depth: i32,
+    spawn_list: &mut Vec<(usize, String)>,
+) {
     let spawn_table = room_table(map_depth);
 
-    let mut spawn_points: HashMap<usize, String> = HashMap::new();
+    let mut spawn_points: BTreeMap<usize, String> = BTreeMap::new();
     let mut areas: Vec<usize> = Vec::from(area);
 
-    // Score to keep the borrow checker happy
-    {
-        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        let num_spawns = i32::min(
-            areas.len() as i32,
-            rng.roll_dice(1, MAX_MONSTERS + 3) + (map_depth - 1) - 3,
-        );
-        if num_spawns == 0 {
-            return;
-        }
+    let num_spawns = i32::min(
+        areas.len() as i32,
+        rng.roll_dice(1, MAX_MONSTERS + 3) + (map_depth - 1) - 3,
+    );
+    if num_spawns == 0 {
+        return;
+    }
 
-        for _i in 0..num_spawns {
-            let array_index = if areas.len() == 1 {
-                0usize
-            } else {
-                (rng.roll_dice(1, areas.len() as i32) - 1) as usize
-            };
-            let map_idx = areas[array_index];
-            spawn_points.insert(map_idx, spawn_table.roll(&mut rng));
-            areas.remove(array_index);
-        }
+    for _i in 0..num_spawns {
+        let array_index = if areas.len() == 1 {
+            0usize
+        } else {
+            (rng.roll_dice(1, areas.len() as i32) - 1) as usize
+        };
+        let map_idx = areas[array_index];
+        spawn_points.insert(map_idx, spawn_table.roll(rng));
+        areas.remove(array_index);
     }
 
-    // Actually spawn stuff
+    // Queue up the spawns
     for spawn in spawn_points.iter() {
-        spawn_entity(ecs, &spawn);
+        spawn_list.push((*spawn.0, spawn.1.to_string()));
     }
 }
 
-fn spawn_entity(ecs: &mut World, spawn: &(&usize, &String)) {
-    let x = (*spawn.0 % MAP_WIDTH) as i32;
-    let y = (*spawn.0 / MAP_WIDTH) as i32;
-
-    match spawn.1.as_ref() {
-        "Goblin" => goblin(ecs, x, y),
-        "Orc" => orc(ecs, x, y),
-        "Health Potion" => potion_of_healing(ecs, x, y),
-        "Grater Health Potion" => greater_potion_of_healing(ecs, x, y),
-        "Legendary Health Potion" => legendary_potion_of_healing(ecs, x, y),
-        "Mana Potion" => potion_of_mana(ecs, x, y),
-        "Grater Mana Potion" => greater_potion_of_mana(ecs, x, y),
-        "Legendary Mana Potion" => legendary_potion_of_mana(ecs, x, y),
-        "Fireball Scroll" => fireball_scroll(ecs, x, y),
-        "Confusion Scroll" => confusion_scroll(ecs, x, y),
-        "Magic Missile Scroll" => magic_missile_scroll(ecs, x, y),
-        "Dagger" => dagger(ecs, x, y),
-        "Shield" => shield(ecs, x, y),
-        "Longsword" => longsword(ecs, x, y),
-        "Tower Shield" => tower_shield(ecs, x, y),
-        "Rations" => rations(ecs, x, y),
-        "Magic Mapping Scroll" => magic_mapping_scroll(ecs, x, y),
-        "Bear Trap" => bear_trap(ecs, x, y),
-        "Spikes" => spikes(ecs, x, y),
-        _ => {}
+/// Spawns an entity of the spawn list, rolling the magic of the equipment found lying around and
+/// the stock of vendors
+pub fn spawn_entity(ecs: &mut World, spawn: &(&usize, &String), rng: &mut RandomNumberGenerator) {
+    let width = ecs.fetch::<Map>().width as usize;
+    let x = (*spawn.0 % width) as i32;
+    let y = (*spawn.0 / width) as i32;
+
+    let spawn_result = spawn_named_entity(
+        &RAWS.lock().unwrap(),
+        ecs,
+        spawn.1,
+        SpawnType::AtPosition { x, y },
+        rng,
+    );
+    match spawn_result {
+        Some(entity) => {
+            roll_enchantment(ecs, entity, rng);
+            stock_vendor(ecs, entity, rng);
+        }
+        None => rltk::console::log(format!(
+            "WARNING: We don't know how to spawn [{}]!",
+            spawn.1
+        )),
     }
 }
 
-pub fn potion_of_healing(ecs: &mut World, x: i32, y: i32) {
-    health_potion(ecs, x, y, "Potion of Healing", 8);
-}
-pub fn greater_potion_of_healing(ecs: &mut World, x: i32, y: i32) {
-    health_potion(ecs, x, y, "Greater Potion of Healing", 12);
-}
-pub fn legendary_potion_of_healing(ecs: &mut World, x: i32, y: i32) {
-    health_potion(ecs, x, y, "Legendary Potion of Healing", 20);
-}
-
-fn health_potion<S: ToString>(ecs: &mut World, x: i32, y: i32, name: S, heal_amount: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('¡'),
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: name.to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(ProvidesHealing {
-            heal_amount: heal_amount,
-        })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-pub fn potion_of_mana(ecs: &mut World, x: i32, y: i32) {
-    mana_potion(ecs, x, y, "Potion of Mana", 3);
-}
-pub fn greater_potion_of_mana(ecs: &mut World, x: i32, y: i32) {
-    mana_potion(ecs, x, y, "Greater Potion of Mana", 12);
-}
-pub fn legendary_potion_of_mana(ecs: &mut World, x: i32, y: i32) {
-    mana_potion(ecs, x, y, "Legendary Potion of Mana", 25);
-}
-
-fn mana_potion<S: ToString>(ecs: &mut World, x: i32, y: i32, name: S, mana_amount: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('¡'),
-            fg: RGB::named(rltk::MAGENTA),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: name.to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(ProvidesManaRestore {
-            mana_amount: mana_amount,
-        })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::CYAN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Magic Missile Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 8 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::ORANGE),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Fireball Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 8 })
-        .with(AreaOfEffect { radius: 3 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::PINK),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Confusion Scroll".to_string(),
-        })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(Confusion { turns: 4 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn dagger(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('/'),
-            fg: RGB::named(rltk::CYAN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Dagger".to_string(),
-        })
-        .with(Item {})
-        .with(Equippable {
-            slot: EquipmentSlot::Melee,
-        })
-        .with(MeleePowerBonus { power: 2 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn shield(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('('),
-            fg: RGB::named(rltk::CYAN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Shield".to_string(),
-        })
-        .with(Item {})
-        .with(Equippable {
-            slot: EquipmentSlot::Shield,
-        })
-        .with(DefenseBonus { defense: 1 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn longsword(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('/'),
-            fg: RGB::named(rltk::YELLOW),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Longsword".to_string(),
-        })
-        .with(Item {})
-        .with(Equippable {
-            slot: EquipmentSlot::Melee,
-        })
-        .with(MeleePowerBonus { power: 4 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn tower_shield(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('('),
-            fg: RGB::named(rltk::YELLOW),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Tower Shield".to_string(),
-        })
-        .with(Item {})
-        .with(Equippable {
-            slot: EquipmentSlot::Shield,
-        })
-        .with(DefenseBonus { defense: 3 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn rations(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('%'),
-            fg: RGB::named(rltk::GREEN),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Rations".to_string(),
-        })
-        .with(Item {})
-        .with(ProvidesFood {})
-        .with(Consumable {})
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
-
-fn magic_mapping_scroll(ecs: &mut World, x: i32, y: i32) {
-    let scroll_kind = {
-        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        rng.roll_dice(1, 3)
-    };
-
-    let (name, power) = match scroll_kind {
-        1 => ("Scroll of Magic Mapping", 7),
-        2 => ("Greater Scroll of Magic Mapping", 20),
-        _ => ("Legendary Scroll of Magic Mapping", 70),
+/// Fills `vendor`'s backpack with items of the kinds it sells, if it's a vendor
+fn stock_vendor(ecs: &mut World, vendor: Entity, rng: &mut RandomNumberGenerator) {
+    let categories = match ecs.read_storage::<Vendor>().get(vendor) {
+        Some(vendor) => vendor.categories.clone(),
+        None => return,
     };
 
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::CYAN3),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: name.to_string(),
-        })
-        .with(Item {})
-        .with(MagicMapper { power })
-        .with(Consumable {})
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+    // The town's vendors sell what can be found on the first level
+    let depth = i32::max(1, ecs.fetch::<Map>().depth);
+    let raws = RAWS.lock().unwrap();
+    let stock_table = get_vendor_table_for_depth(&raws, depth, &categories);
+    // The table comes up empty now and then, those rolls don't count towards the stock
+    let mut stocked = 0;
+    for _attempt in 0..VENDOR_STOCK * 4 {
+        let name = stock_table.roll(rng);
+        if let Some(item) =
+            spawn_named_entity(&raws, ecs, &name, SpawnType::Carried { by: vendor }, rng)
+        {
+            roll_enchantment(ecs, item, rng);
+            stocked += 1;
+            if stocked == VENDOR_STOCK {
+                break;
+            }
+        }
+    }
 }
 
-fn bear_trap(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('w'),
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Bear Trap".to_string(),
-        })
-        .with(Hidden {})
-        .with(EntryTrigger {})
-        .with(InflictsDamage { damage: 6 })
-        .with(SingleActivation {})
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
-}
+/// Maybe enchants (or curses) `item` if it's a weapon or armour. Deeper levels have more and
+/// stronger enchantments.
+fn roll_enchantment(ecs: &mut World, item: Entity, rng: &mut RandomNumberGenerator) {
+    let enchantable = ecs.read_storage::<Equippable>().contains(item)
+        && (ecs.read_storage::<MeleeWeapon>().contains(item)
+            || ecs.read_storage::<RangedWeapon>().contains(item)
+            || ecs.read_storage::<DefenseBonus>().contains(item));
+    if !enchantable {
+        return;
+    }
 
-fn spikes(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
-        .with(Position { x, y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('^'),
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-            render_order: 2,
-        })
-        .with(Name {
-            name: "Spikes".to_string(),
-        })
-        .with(Hidden {})
-        .with(EntryTrigger {})
-        .with(InflictsDamage { damage: 6 })
-        .marked::<SimpleMarker<SerializeMe>>()
-        .build();
+    let depth = ecs.fetch::<Map>().depth;
+    let roll = rng.roll_dice(1, 100);
+    let enchantment = if roll <= CURSE_CHANCE {
+        Enchantment {
+            bonus: -rng.roll_dice(1, MAX_CURSE_MALUS),
+            cursed: true,
+            known: false,
+        }
+    } else if roll > 100 - (ENCHANT_CHANCE + depth * ENCHANT_CHANCE_PER_DEPTH) {
+        Enchantment {
+            bonus: i32::min(MAX_ENCHANTMENT, rng.roll_dice(1, 1 + depth / 3)),
+            cursed: false,
+            known: false,
+        }
+    } else {
+        return;
+    };
+    ecs.write_storage::<Enchantment>()
+        .insert(item, enchantment)
+        .expect("Unable to insert enchantment");
 }
@@ -0,0 +1,93 @@
+use specs::prelude::*;
+
+use super::{
+    effects::{EffectQueue, EffectType, Targets},
+    gamelog::GameLog,
+    AreaOfEffect, MagicStats, Map, SpellTemplate, WantsToCastSpell,
+};
+
+/// Whether `caster` has the mana `spell` costs right now
+pub fn can_afford(ecs: &World, caster: Entity, spell: Entity) -> bool {
+    let cost = match ecs.read_storage::<SpellTemplate>().get(spell) {
+        Some(template) => template.mana_cost,
+        None => return false,
+    };
+    ecs.read_storage::<MagicStats>()
+        .get(caster)
+        .is_some_and(|magic| magic.mana >= cost)
+}
+
+pub struct SpellCastSystem {}
+
+impl<'a> System<'a> for SpellCastSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToCastSpell>,
+        ReadStorage<'a, SpellTemplate>,
+        ReadStorage<'a, AreaOfEffect>,
+        WriteStorage<'a, MagicStats>,
+        WriteExpect<'a, EffectQueue>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            map,
+            mut gamelog,
+            entities,
+            mut wants_cast,
+            spells,
+            aoe,
+            mut magic_stats,
+            mut effect_queue,
+        ) = data;
+
+        for (entity, cast) in (&entities, &wants_cast).join() {
+            let template = match spells.get(cast.spell) {
+                Some(template) => template,
+                None => continue,
+            };
+
+            // Casting takes the spell's whole cost out of the caster's mana, or fails
+            match magic_stats.get_mut(entity) {
+                Some(magic) if magic.mana >= template.mana_cost => {
+                    magic.mana -= template.mana_cost;
+                }
+                _ => {
+                    if entity == *player_entity {
+                        gamelog
+                            .entries
+                            .push("You don't have enough mana to cast that!".to_string());
+                    }
+                    continue;
+                }
+            }
+
+            // Targeting
+            let targets = match cast.target {
+                None => Targets::Single { target: entity },
+                Some(target) => match aoe.get(cast.spell) {
+                    None => Targets::Tile {
+                        tile_idx: map.xy_idx(target.x, target.y),
+                    },
+                    Some(area_effect) => Targets::Area {
+                        center: target,
+                        radius: area_effect.radius,
+                    },
+                },
+            };
+
+            effect_queue.add(
+                Some(entity),
+                EffectType::SpellUse { spell: cast.spell },
+                targets,
+            );
+        }
+
+        wants_cast.clear();
+    }
+}
@@ -0,0 +1,98 @@
+use super::{
+    effects::{EffectQueue, EffectType, Targets},
+    gamelog::GameLog,
+    initiative_system::GameClock,
+    Position, StatusEffect, StatusEffectKind, StatusEffects, Viewshed,
+};
+use specs::prelude::*;
+
+/// Counts down status effects on game time, applying what they do every tick. Whoever is frozen
+/// on another level (without a position) waits for the player to come back.
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, Viewshed>,
+        ReadExpect<'a, GameClock>,
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, EffectQueue>,
+        ReadStorage<'a, Position>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut statuses,
+            mut viewsheds,
+            clock,
+            player,
+            mut log,
+            mut effect_queue,
+            positions,
+        ) = data;
+
+        if !clock.ticked {
+            return;
+        }
+
+        let mut cured: Vec<Entity> = Vec::new();
+        for (entity, status, _pos) in (&entities, &mut statuses, &positions).join() {
+            for effect in status.effects.iter_mut() {
+                let tick_effect = match effect.kind {
+                    StatusEffectKind::Poison => Some(EffectType::Damage { amount: 1 }),
+                    StatusEffectKind::Regeneration => Some(EffectType::Healing { amount: 1 }),
+                    _ => None,
+                };
+                if let Some(tick_effect) = tick_effect {
+                    effect_queue.add(None, tick_effect, Targets::Single { target: entity });
+                }
+                effect.turns -= 1;
+            }
+
+            let mut worn_off: Vec<StatusEffectKind> = status
+                .effects
+                .iter()
+                .filter(|effect| effect.turns < 1)
+                .map(|effect| effect.kind)
+                .collect();
+            status.effects.retain(|effect| effect.turns > 0);
+            // A stack of poison is only over once the last dose is
+            worn_off.retain(|kind| !status.has(*kind));
+            worn_off.sort();
+            worn_off.dedup();
+
+            for kind in worn_off.iter() {
+                if entity == *player {
+                    log.entries
+                        .push(format!("You are no longer {}.", kind.adjective()));
+                }
+                if *kind == StatusEffectKind::Blindness {
+                    if let Some(viewshed) = viewsheds.get_mut(entity) {
+                        viewshed.dirty = true;
+                    }
+                }
+            }
+
+            if status.effects.is_empty() {
+                cured.push(entity);
+            }
+        }
+
+        for entity in cured.iter() {
+            statuses.remove(*entity);
+        }
+    }
+}
+
+/// `effects` as a phrase for the log, e.g. "poisoned and slowed"
+pub fn describe_statuses(effects: &[StatusEffect]) -> String {
+    let adjectives: Vec<&str> = effects
+        .iter()
+        .map(|effect| effect.kind.adjective())
+        .collect();
+    adjectives.join(" and ")
+}
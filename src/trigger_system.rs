@@ -1,4 +1,4 @@
-use crate::{InflictsDamage, ParticleBuilder, SingleActivation, SufferDamage};
+use crate::effects::{EffectQueue, EffectType, Targets};
 
 use super::{gamelog::GameLog, EntityMoved, EntryTrigger, Hidden, Map, Name, Position};
 use specs::prelude::*;
@@ -16,10 +16,7 @@ impl<'a> System<'a> for TriggerSystem {
         ReadStorage<'a, Name>,
         Entities<'a>,
         WriteExpect<'a, GameLog>,
-        WriteExpect<'a, ParticleBuilder>,
-        WriteStorage<'a, SufferDamage>,
-        ReadStorage<'a, InflictsDamage>,
-        ReadStorage<'a, SingleActivation>,
+        WriteExpect<'a, EffectQueue>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -32,15 +29,9 @@ impl<'a> System<'a> for TriggerSystem {
             names,
             entities,
             mut log,
-            mut particle_builder,
-            mut inflict_damage,
-            inflicts_damage,
-            single_activation,
+            mut effect_queue,
         ) = data;
 
-        // Will store the triggered entities that should trigger once
-        let mut entities_to_remove: Vec<Entity> = Vec::new();
-
         // Iterate entities that have moved
         for (entity, mut _entity_moved, pos) in (&entities, &mut entity_moved, &position).join() {
             let idx = map.xy_idx(pos.x, pos.y);
@@ -56,25 +47,14 @@ impl<'a> System<'a> for TriggerSystem {
                             log.entries.push(format!("{} triggers!", &name.name));
                         }
 
-                        let damage = inflicts_damage.get(*entity_id);
-                        if let Some(damage) = damage {
-                            particle_builder.request(
-                                pos.x,
-                                pos.y,
-                                rltk::RGB::named(rltk::ORANGE),
-                                rltk::RGB::named(rltk::BLACK),
-                                rltk::to_cp437('‼'),
-                                200.0,
-                            );
-                            SufferDamage::new_damage(&mut inflict_damage, entity, damage.damage);
-                            log.entries
-                                .push(format!("you suffer {} damage!", &damage.damage));
-                        }
-
-                        let sa = single_activation.get(*entity_id);
-                        if sa.is_some() {
-                            entities_to_remove.push(*entity_id);
-                        }
+                        // What the trap does is up to the effects queue
+                        effect_queue.add(
+                            None,
+                            EffectType::TriggerFire {
+                                trigger: *entity_id,
+                            },
+                            Targets::Single { target: entity },
+                        );
 
                         hidden.remove(*entity_id); // Not hidden anymore
                     }
@@ -82,13 +62,6 @@ impl<'a> System<'a> for TriggerSystem {
             }
         }
 
-        // Remove any single activation traps
-        for trigger in entities_to_remove.iter() {
-            entities
-                .delete(*trigger)
-                .expect("Unable to delete single activation trigger");
-        }
-
         // remove all entity movement markers
         entity_moved.clear();
     }
@@ -1,4 +1,4 @@
-use crate::{gamelog::GameLog, Hidden, Name};
+use crate::{gamelog::GameLog, Hidden, Name, StatusEffectKind, StatusEffects};
 
 use super::{Map, Player, Position, Viewshed};
 use rltk::{field_of_view, Point};
@@ -17,24 +17,45 @@ impl<'a> System<'a> for VisibilitySystem {
         WriteExpect<'a, rltk::RandomNumberGenerator>,
         WriteExpect<'a, GameLog>,
         ReadStorage<'a, Name>,
+        ReadStorage<'a, StatusEffects>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, entities, mut viewshed, pos, player, mut hidden, mut rng, mut log, names) =
-            data;
+        let (
+            mut map,
+            entities,
+            mut viewshed,
+            pos,
+            player,
+            mut hidden,
+            mut rng,
+            mut log,
+            names,
+            statuses,
+        ) = data;
 
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
             if viewshed.dirty {
                 viewshed.dirty = false;
                 viewshed.visible_tiles.clear();
-                viewshed.visible_tiles =
-                    field_of_view(Point::new(pos.x, pos.y), viewshed.range, &*map);
+                // The blind only make out what's right next to them
+                let blind = statuses
+                    .get(ent)
+                    .is_some_and(|status| status.has(StatusEffectKind::Blindness));
+                let range = if blind { 1 } else { viewshed.range };
+                viewshed.visible_tiles = field_of_view(Point::new(pos.x, pos.y), range, &*map);
                 viewshed
                     .visible_tiles
                     .retain(|p| p.x >= 0 && p.x < map.width && p.y >= 0 && p.y < map.height);
+                // In a fixed order, since spotting hidden things below rolls dice tile by tile
+                viewshed.visible_tiles.sort_by_key(|p| (p.y, p.x));
 
-                // If this is the player, reveal what they can see
+                // If this is the player, reveal what they can see. Unlike monsters they need light
+                // for that.
                 if player.get(ent).is_some() {
+                    viewshed
+                        .visible_tiles
+                        .retain(|p| map.is_lit(map.xy_idx(p.x, p.y)));
                     for t in map.visible_tiles.iter_mut() {
                         *t = false
                     }
@@ -0,0 +1,43 @@
+//! Helpers shared by the integration tests
+
+#![allow(dead_code)]
+
+use hellorust::headless::HeadlessGame;
+use hellorust::{Map, PlayerCommand, Position, RunState, TileType};
+use rltk::Point;
+use specs::prelude::*;
+
+/// Puts the player on (x, y) right away, without spending a turn
+pub fn teleport(game: &mut HeadlessGame, x: i32, y: i32) {
+    let ecs = game.ecs_mut();
+    let player = *ecs.fetch::<Entity>();
+    if let Some(pos) = ecs.write_storage::<Position>().get_mut(player) {
+        pos.x = x;
+        pos.y = y;
+    }
+    *ecs.write_resource::<Point>() = Point::new(x, y);
+}
+
+/// Where the first tile of `tile_type` on the current level is
+pub fn find_tile(game: &HeadlessGame, tile_type: TileType) -> Option<Point> {
+    let map = game.ecs().fetch::<Map>();
+    map.tiles
+        .iter()
+        .position(|tile| *tile == tile_type)
+        .map(|idx| Point::new(idx as i32 % map.width, idx as i32 / map.width))
+}
+
+/// Walks straight onto the stairs down (whatever is in the way) and takes them
+pub fn take_stairs_down(game: &mut HeadlessGame) -> RunState {
+    let stairs = find_tile(game, TileType::DownStairs).expect("a level without stairs down");
+    teleport(game, stairs.x, stairs.y);
+    game.step(PlayerCommand::Descend)
+}
+
+pub fn player_pos(game: &HeadlessGame) -> Point {
+    *game.ecs().fetch::<Point>()
+}
+
+pub fn depth(game: &HeadlessGame) -> i32 {
+    game.ecs().fetch::<Map>().depth
+}
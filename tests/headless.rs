@@ -0,0 +1,282 @@
+//! Plays scripted games through the headless runner
+
+mod common;
+
+use hellorust::dijkstra_map_system::DijkstraMaps;
+use hellorust::headless::HeadlessGame;
+use hellorust::{
+    saveload_system, CombatStats, GameLog, HungerClock, InBackpack, Item, MagicStats, Map, Name,
+    PlayerCommand, Position, RunState, SpellTemplate, TileType,
+};
+use rltk::Point;
+use specs::prelude::*;
+
+const SEED: u64 = 1;
+
+/// The names of whatever the player carries, sorted
+fn backpack(game: &HeadlessGame) -> Vec<String> {
+    let ecs = game.ecs();
+    let player = *ecs.fetch::<Entity>();
+    let mut names: Vec<String> = (
+        &ecs.read_storage::<InBackpack>(),
+        &ecs.read_storage::<Name>(),
+    )
+        .join()
+        .filter(|(pack, _name)| pack.owner == player)
+        .map(|(_pack, name)| name.name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+/// The name and position of everything on the level, sorted
+fn everything(game: &HeadlessGame) -> Vec<(String, i32, i32)> {
+    let ecs = game.ecs();
+    let mut entities: Vec<(String, i32, i32)> =
+        (&ecs.read_storage::<Name>(), &ecs.read_storage::<Position>())
+            .join()
+            .map(|(name, pos)| (name.name.clone(), pos.x, pos.y))
+            .collect();
+    entities.sort();
+    entities
+}
+
+fn player_hp(game: &HeadlessGame) -> i32 {
+    let ecs = game.ecs();
+    let player = *ecs.fetch::<Entity>();
+    ecs.read_storage::<CombatStats>().get(player).unwrap().hp
+}
+
+fn player_mana(game: &HeadlessGame) -> i32 {
+    let ecs = game.ecs();
+    let player = *ecs.fetch::<Entity>();
+    ecs.read_storage::<MagicStats>().get(player).unwrap().mana
+}
+
+/// How long until the player gets hungrier, which goes down whenever time passes
+fn hunger_left(game: &HeadlessGame) -> i32 {
+    let ecs = game.ecs();
+    let player = *ecs.fetch::<Entity>();
+    ecs.read_storage::<HungerClock>()
+        .get(player)
+        .unwrap()
+        .duration
+}
+
+/// Puts the player back to full health, so scripts don't end with the first monster they meet
+fn heal_player(game: &mut HeadlessGame) {
+    let ecs = game.ecs_mut();
+    let player = *ecs.fetch::<Entity>();
+    if let Some(stats) = ecs.write_storage::<CombatStats>().get_mut(player) {
+        stats.hp = stats.max_hp;
+    }
+}
+
+/// Wanders around (and down) the dungeon the same way every time, picking up whatever is found.
+/// The player never dies on the way, so it gets deep enough for the monsters to fight each other.
+fn play_script(game: &mut HeadlessGame, turns: usize) {
+    const DIRECTIONS: [(i32, i32); 8] = [
+        (0, -1),
+        (1, -1),
+        (1, 0),
+        (1, 1),
+        (0, 1),
+        (-1, 1),
+        (-1, 0),
+        (-1, -1),
+    ];
+    let mut state: u32 = 12345;
+    for turn in 0..turns {
+        heal_player(game);
+        // Leave every level soon enough, there's more going on further down
+        if turn % 60 == 20 && common::find_tile(game, TileType::DownStairs).is_some() {
+            common::take_stairs_down(game);
+            continue;
+        }
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        let command = match (state >> 16) % 10 {
+            0 => PlayerCommand::PickUp,
+            1 => PlayerCommand::SkipTurn,
+            roll => {
+                let (delta_x, delta_y) = DIRECTIONS[(roll as usize + turn) % DIRECTIONS.len()];
+                PlayerCommand::Move { delta_x, delta_y }
+            }
+        };
+        if game.step(command) == RunState::GameOver {
+            return;
+        }
+    }
+}
+
+#[test]
+fn moving_walks_onto_a_free_tile() {
+    let mut game = HeadlessGame::new(SEED);
+    let start = common::player_pos(&game);
+    let (delta_x, delta_y) = {
+        let map = game.ecs().fetch::<Map>();
+        [(-1, 0), (1, 0), (0, -1), (0, 1)]
+            .into_iter()
+            .find(|(dx, dy)| {
+                let idx = map.xy_idx(start.x + dx, start.y + dy);
+                !map.blocked[idx] && map.tile_content[idx].is_empty()
+            })
+            .expect("the player starts boxed in")
+    };
+
+    assert_eq!(
+        game.step(PlayerCommand::Move { delta_x, delta_y }),
+        RunState::AwaitingInput
+    );
+    assert_eq!(
+        common::player_pos(&game),
+        Point::new(start.x + delta_x, start.y + delta_y)
+    );
+}
+
+#[test]
+fn picking_up_puts_the_item_in_the_backpack() {
+    let mut game = HeadlessGame::new(SEED);
+    common::take_stairs_down(&mut game);
+    let (item, x, y) = {
+        let ecs = game.ecs();
+        (
+            &ecs.read_storage::<Item>(),
+            &ecs.read_storage::<Name>(),
+            &ecs.read_storage::<Position>(),
+        )
+            .join()
+            .map(|(_item, name, pos)| (name.name.clone(), pos.x, pos.y))
+            .min_by_key(|(_name, x, y)| (*y, *x))
+            .expect("nothing to pick up on the first level")
+    };
+    let carried = backpack(&game).len();
+
+    common::teleport(&mut game, x, y);
+    game.step(PlayerCommand::PickUp);
+
+    let after = backpack(&game);
+    assert_eq!(after.len(), carried + 1);
+    assert!(after.contains(&item));
+}
+
+#[test]
+fn stairs_lead_down_and_back_up() {
+    let mut game = HeadlessGame::new(SEED);
+    let town = common::depth(&game);
+
+    assert_eq!(common::take_stairs_down(&mut game), RunState::AwaitingInput);
+    assert_eq!(common::depth(&game), town + 1);
+
+    let stairs = common::find_tile(&game, TileType::UpStairs).expect("no stairs back up");
+    common::teleport(&mut game, stairs.x, stairs.y);
+    assert_eq!(game.step(PlayerCommand::Ascend), RunState::AwaitingInput);
+    assert_eq!(common::depth(&game), town);
+}
+
+#[test]
+fn stairs_commands_off_the_stairs_take_no_turn() {
+    let mut game = HeadlessGame::new(SEED);
+    let town = common::depth(&game);
+    let hunger = hunger_left(&game);
+
+    assert_eq!(game.step(PlayerCommand::Descend), RunState::AwaitingInput);
+    assert_eq!(game.step(PlayerCommand::Ascend), RunState::AwaitingInput);
+
+    assert_eq!(common::depth(&game), town);
+    assert_eq!(hunger_left(&game), hunger);
+    let log = &game.ecs().fetch::<GameLog>().entries;
+    assert!(log.contains(&"There is no way down from here.".to_string()));
+    assert!(log.contains(&"There is no way up from here.".to_string()));
+}
+
+#[test]
+fn aiming_off_the_map_or_out_of_sight_is_refused() {
+    let mut game = HeadlessGame::new(SEED);
+    let spell = {
+        let ecs = game.ecs();
+        (
+            &ecs.entities(),
+            &ecs.read_storage::<SpellTemplate>(),
+            &ecs.read_storage::<Name>(),
+        )
+            .join()
+            .find(|(_entity, _spell, name)| name.name == "Magic Missile")
+            .map(|(entity, _spell, _name)| entity)
+            .expect("no magic missile spell")
+    };
+    let far_corner = {
+        let map = game.ecs().fetch::<Map>();
+        Point::new(map.width - 1, map.height - 1)
+    };
+    let hunger = hunger_left(&game);
+    let mana = player_mana(&game);
+
+    for target in [Point::new(-1, -1), far_corner] {
+        let state = game.step(PlayerCommand::CastSpell {
+            spell,
+            target: Some(target),
+        });
+        assert_eq!(state, RunState::AwaitingInput);
+    }
+
+    assert_eq!(hunger_left(&game), hunger);
+    assert_eq!(player_mana(&game), mana);
+}
+
+#[test]
+fn a_saved_game_loads_back() {
+    const SAVE_NAME: &str = "headless_roundtrip";
+
+    let mut game = HeadlessGame::new(SEED);
+    play_script(&mut game, 60);
+    let saved = (
+        common::player_pos(&game),
+        common::depth(&game),
+        player_hp(&game),
+        backpack(&game),
+        everything(&game),
+    );
+    let saved_tiles = game.ecs().fetch::<Map>().tiles.clone();
+
+    std::fs::create_dir_all("./save_files").unwrap();
+    saveload_system::save_game(game.ecs_mut(), SAVE_NAME);
+    let mut loaded = HeadlessGame::new(SEED + 1);
+    saveload_system::load_game(loaded.ecs_mut(), SAVE_NAME);
+    saveload_system::delete_save(SAVE_NAME);
+    *loaded.ecs_mut().write_resource::<DijkstraMaps>() = DijkstraMaps::default();
+
+    assert_eq!(
+        (
+            common::player_pos(&loaded),
+            common::depth(&loaded),
+            player_hp(&loaded),
+            backpack(&loaded),
+            everything(&loaded),
+        ),
+        saved
+    );
+    assert!(loaded.ecs().fetch::<Map>().tiles == saved_tiles);
+    assert_eq!(
+        loaded.step(PlayerCommand::SkipTurn),
+        RunState::AwaitingInput
+    );
+}
+
+#[test]
+fn the_same_script_replays_the_same_game() {
+    for seed in [SEED, 4] {
+        let mut first = HeadlessGame::new(seed);
+        let mut second = HeadlessGame::new(seed);
+        play_script(&mut first, 600);
+        play_script(&mut second, 600);
+
+        assert_eq!(
+            first.ecs().fetch::<GameLog>().entries,
+            second.ecs().fetch::<GameLog>().entries,
+            "seed {} told a different story the second time",
+            seed
+        );
+        assert_eq!(everything(&first), everything(&second));
+        assert_eq!(common::depth(&first), common::depth(&second));
+    }
+}
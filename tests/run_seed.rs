@@ -0,0 +1,108 @@
+//! A run seed has to give the same levels, with the same things on them, every time
+
+mod common;
+
+use hellorust::headless::HeadlessGame;
+use hellorust::{Gold, Map, Name, Player, Position, TileType};
+use rltk::RandomNumberGenerator;
+use specs::prelude::*;
+
+const SEEDS: [u64; 3] = [1, 4, 7];
+const DEPTHS: i32 = 6;
+
+/// A level's tiles, and the name and position of everything on it
+type Level = (Vec<TileType>, Vec<(String, i32, i32)>);
+
+/// The level the game is on
+fn level(game: &HeadlessGame) -> Level {
+    let ecs = game.ecs();
+    let tiles = ecs.fetch::<Map>().tiles.clone();
+    let mut entities: Vec<(String, i32, i32)> =
+        (&ecs.read_storage::<Name>(), &ecs.read_storage::<Position>())
+            .join()
+            .map(|(name, pos)| (name.name.clone(), pos.x, pos.y))
+            .collect();
+    entities.sort();
+    (tiles, entities)
+}
+
+/// Every level from the town down to `depths`, as the seed builds them
+fn levels(seed: u64, depths: i32) -> Vec<Level> {
+    let mut game = HeadlessGame::new(seed);
+    let mut levels = vec![level(&game)];
+    for _ in 0..depths {
+        common::take_stairs_down(&mut game);
+        levels.push(level(&game));
+    }
+    levels
+}
+
+/// How much gold the monsters on each level from the town down to `depths` carries. Before each
+/// level is built, the gameplay generator is rolled `rolls` times, as if a few turns went by.
+fn gold(seed: u64, depths: i32, rolls: i32) -> Vec<Vec<(String, i32, i32, i32)>> {
+    let mut game = HeadlessGame::new(seed);
+    let mut levels = Vec::new();
+    for _ in 0..depths {
+        for _ in 0..rolls {
+            game.ecs_mut()
+                .write_resource::<RandomNumberGenerator>()
+                .roll_dice(1, 6);
+        }
+        common::take_stairs_down(&mut game);
+        let ecs = game.ecs();
+        let mut gold: Vec<(String, i32, i32, i32)> = (
+            &ecs.read_storage::<Name>(),
+            &ecs.read_storage::<Position>(),
+            &ecs.read_storage::<Gold>(),
+            !&ecs.read_storage::<Player>(),
+        )
+            .join()
+            .map(|(name, pos, gold, _not_player)| (name.name.clone(), pos.x, pos.y, gold.amount))
+            .collect();
+        gold.sort();
+        levels.push(gold);
+    }
+    levels
+}
+
+#[test]
+fn same_seed_builds_the_same_levels() {
+    for seed in SEEDS {
+        let first = levels(seed, DEPTHS);
+        let second = levels(seed, DEPTHS);
+        for (depth, (a, b)) in first.iter().zip(second.iter()).enumerate() {
+            assert!(
+                a.0 == b.0,
+                "seed {} built different tiles at depth {}",
+                seed,
+                depth
+            );
+            assert_eq!(
+                a.1, b.1,
+                "seed {} spawned different things at depth {}",
+                seed, depth
+            );
+        }
+    }
+}
+
+#[test]
+fn different_seeds_build_different_levels() {
+    assert!(levels(1, 1)[1].0 != levels(3, 1)[1].0);
+}
+
+#[test]
+fn earlier_play_leaves_the_gold_on_a_level_alone() {
+    for seed in SEEDS {
+        let untouched = gold(seed, DEPTHS, 0);
+        assert!(
+            untouched
+                .iter()
+                .flatten()
+                .any(|(_name, _x, _y, amount)| *amount > 0),
+            "seed {} has nobody carrying gold",
+            seed
+        );
+        assert_eq!(untouched, gold(seed, DEPTHS, 7), "seed {}", seed);
+    }
+}